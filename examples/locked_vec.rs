@@ -25,7 +25,7 @@ impl LockedVec {
     /// Returns Err for other OS errors (e.g., resource limits).
     pub fn new(len: usize) -> io::Result<Self> {
         // Allocate a zeroed buffer. We won't change capacity after locking.
-        let buf = vec![0u8; len];
+        let mut buf = vec![0u8; len];
 
         // Attempt to lock pages. Treat Unsupported as a non-fatal condition.
         let ptr = buf.as_ptr() as *const std::os::raw::c_void;
@@ -81,9 +81,11 @@ impl LockedVec {
 impl Drop for LockedVec {
     fn drop(&mut self) {
         // Zeroize contents while still locked (if locked).
+        // Volatile, so the wipe is not optimized away although the Vec is freed next.
         for b in &mut self.buf {
-            *b = 0;
+            unsafe { std::ptr::write_volatile(b, 0) };
         }
+        std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
 
         if self.locked {
             let ptr = self.buf.as_ptr() as *const std::os::raw::c_void;
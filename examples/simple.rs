@@ -69,9 +69,11 @@ fn main() -> io::Result<()> {
     }
 
     // Zeroize secret before drop as a good hygiene (example only; use a proper zeroize crate in production).
+    // Volatile writes: a plain `fill(0)` here is a dead store the optimizer may drop.
     for b in &mut secret {
-        *b = 0;
+        unsafe { std::ptr::write_volatile(b, 0) };
     }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
 
     println!("Secret zeroized and example complete.");
     Ok(())
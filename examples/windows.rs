@@ -20,15 +20,17 @@ fn main() -> std::io::Result<()> {
     use std::thread;
     use std::time::Duration;
 
-    // Best-effort: suppress common Windows error dialogs for this process.
-    // This is process-wide and inherited by children. Not security-related; purely UX/ops.
-    let previous_error_mode: io::Result<u32> =
-        os_memlock::suppress_windows_error_dialogs_for_process();
-    match &previous_error_mode {
-        Ok(prev) => println!("Windows error mode adjusted; previous mode: 0x{prev:08x}"),
+    // Best-effort: suppress common Windows error dialogs for this process until the guard
+    // drops. This is process-wide and inherited by children. Not security-related; purely UX/ops.
+    let error_mode_guard = os_memlock::suppress_windows_error_dialogs_with_guard();
+    match &error_mode_guard {
+        Ok(guard) => println!(
+            "Windows error mode adjusted; previous mode: 0x{:08x}",
+            guard.previous_mode()
+        ),
         Err(e) if e.kind() == io::ErrorKind::Unsupported => {
             println!(
-                "suppress_windows_error_dialogs_for_process is unsupported on this platform/build"
+                "suppress_windows_error_dialogs_with_guard is unsupported on this platform/build"
             )
         }
         Err(e) => eprintln!("Failed to set Windows error mode: {e}"),
@@ -70,18 +72,16 @@ fn main() -> std::io::Result<()> {
     }
 
     // Zeroize secret before drop as good hygiene (example only; use a proper zeroize crate in production).
+    // Written volatilely: `secret` is dead after this, so plain stores could be elided.
     for b in &mut secret {
-        *b = 0;
+        unsafe { std::ptr::write_volatile(b, 0) };
     }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
     println!("Secret zeroized.");
 
-    // Restore the previous Windows error mode if we changed it.
-    if let Ok(prev) = previous_error_mode {
-        match os_memlock::set_windows_error_mode(prev) {
-            Ok(_old) => println!("Windows error mode restored to 0x{prev:08x}"),
-            Err(e) => eprintln!("Failed to restore Windows error mode: {e}"),
-        }
-    }
+    // Dropping the guard restores the previous Windows error mode.
+    drop(error_mode_guard);
+    println!("Windows error mode restored.");
 
     println!("Windows example complete.");
     Ok(())
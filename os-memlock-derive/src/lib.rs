@@ -0,0 +1,104 @@
+//! Derive macros for `os-memlock`. Use them through the `derive` feature of that crate.
+
+#![warn(missing_docs)]
+
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, parse_macro_input};
+
+/// Generate a `Locked<Name>` container that keeps the struct in locked memory.
+///
+/// The generated type wraps `os_memlock::LockedBox<Name>`, has the same visibility as
+/// the struct, derefs (mutably) to it, prints no contents in `Debug`, and zeroizes and
+/// unlocks its storage on drop. It is constructed with `Locked<Name>::new(value)` or
+/// `LockedSecret::into_locked(value)`.
+#[proc_macro_derive(LockedSecret)]
+pub fn derive_locked_secret(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    if !matches!(input.data, Data::Struct(_)) {
+        return syn::Error::new_spanned(
+            &input.ident,
+            "LockedSecret can only be derived for structs",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let locked = format_ident!("Locked{}", name);
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let doc = format!("Locked, zeroize-on-drop container for [`{name}`].");
+    let locked_name = locked.to_string();
+
+    quote! {
+        #[doc = #doc]
+        #vis struct #locked #impl_generics (::os_memlock::LockedBox<#name #ty_generics>) #where_clause;
+
+        impl #impl_generics #locked #ty_generics #where_clause {
+            /// Move `value` into freshly allocated locked memory.
+            #vis fn new(value: #name #ty_generics) -> ::std::io::Result<Self> {
+                ::os_memlock::LockedBox::new(value).map(Self)
+            }
+
+            /// Whether the backing storage is locked in RAM.
+            #vis fn is_locked(&self) -> bool {
+                ::os_memlock::LockedBox::is_locked(&self.0)
+            }
+        }
+
+        impl #impl_generics ::os_memlock::LockedSecret for #name #ty_generics #where_clause {
+            type Locked = #locked #ty_generics;
+
+            fn into_locked(self) -> ::std::io::Result<Self::Locked> {
+                #locked::new(self)
+            }
+        }
+
+        impl #impl_generics ::std::ops::Deref for #locked #ty_generics #where_clause {
+            type Target = #name #ty_generics;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl #impl_generics ::std::ops::DerefMut for #locked #ty_generics #where_clause {
+            fn deref_mut(&mut self) -> &mut Self::Target {
+                &mut self.0
+            }
+        }
+
+        impl #impl_generics ::std::fmt::Debug for #locked #ty_generics #where_clause {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter<'_>) -> ::std::fmt::Result {
+                f.debug_struct(#locked_name).finish_non_exhaustive()
+            }
+        }
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use os_memlock::LockedSecret;
+
+    #[derive(LockedSecret)]
+    struct SessionKeys {
+        enc: [u8; 32],
+        mac: [u8; 32],
+    }
+
+    #[test]
+    fn generated_container_derefs_and_redacts_debug() {
+        let mut keys = SessionKeys {
+            enc: [1; 32],
+            mac: [2; 32],
+        }
+        .into_locked()
+        .unwrap();
+        keys.mac[0] = 9;
+        assert_eq!(keys.enc, [1; 32]);
+        assert_eq!(keys.mac[0], 9);
+        assert_eq!(format!("{keys:?}"), "LockedSessionKeys { .. }");
+    }
+}
@@ -0,0 +1,307 @@
+//! Erasing secrets passed on the command line.
+
+use std::io;
+
+use crate::SecureBuf;
+
+/// Copy command-line argument `index` into a locked buffer and scrub the original.
+///
+/// Index 0 is the program name, matching `std::env::args()`. This exists for tools that
+/// must accept a secret as an argument; it shortens the window in which the secret is
+/// visible, but other users may already have seen it via `ps` before `main` ran.
+///
+/// Platform:
+/// - Linux/Android: the argument area is located via `/proc/self/stat` and the argument
+///   is overwritten with zeros in place, so the value no longer appears in
+///   `/proc/<pid>/cmdline` (it shows up as a run of NUL bytes instead).
+/// - macOS/iOS: the string behind `_NSGetArgv()` is overwritten with zeros in place.
+/// - Windows: the value is taken from `CommandLineToArgvW`, then its span in the PEB
+///   command line (`GetCommandLineW`) and in the ANSI copy (`GetCommandLineA`) is
+///   overwritten with `*`, so the argument count seen by other tools is unchanged.
+/// - Elsewhere: returns `Unsupported`.
+///
+/// Strings already obtained from `std::env::args()` are separate heap copies and are
+/// not affected; later calls to it will see the scrubbed value.
+///
+/// Returns:
+/// - `Ok(buf)` with the argument's bytes (UTF-8 on Windows).
+/// - `Err(InvalidInput)` if there is no argument at `index`.
+/// - Other `io::Error`s from reading `/proc`, the OS, or allocating the buffer.
+///
+/// # Safety
+/// No other thread may read the process arguments while this runs, including via
+/// `std::env::args()` or `GetCommandLineW`: the storage is overwritten in place without
+/// any synchronization. Call it early in `main`, before spawning threads.
+pub unsafe fn scrub_argv_secret(index: usize) -> io::Result<SecureBuf> {
+    // Safety: forwarded to the caller.
+    unsafe { imp::scrub(index) }
+}
+
+fn no_such_argument() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "no argument at this index")
+}
+
+/// Copy the `index`-th NUL-terminated string of `area` into a locked buffer and zeroize it.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn take_nth(area: &mut [u8], index: usize) -> io::Result<SecureBuf> {
+    let mut start = 0;
+    for _ in 0..index {
+        let nul = area[start..]
+            .iter()
+            .position(|&b| b == 0)
+            .ok_or_else(no_such_argument)?;
+        start += nul + 1;
+    }
+    let arg = &mut area[start..];
+    let len = arg
+        .iter()
+        .position(|&b| b == 0)
+        .ok_or_else(no_such_argument)?;
+    let buf = SecureBuf::from_slice(&arg[..len])?;
+    crate::zeroize::zeroize(&mut arg[..len]);
+    Ok(buf)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod imp {
+    use std::io;
+    use std::slice;
+
+    use crate::SecureBuf;
+
+    /// `(arg_start, arg_end)` of this process: fields 48 and 49 of `/proc/self/stat`.
+    fn arg_area() -> io::Result<(usize, usize)> {
+        let stat = std::fs::read_to_string("/proc/self/stat")?;
+        let malformed = || io::Error::new(io::ErrorKind::InvalidData, "unexpected /proc/self/stat");
+        // The command name in field 2 may contain spaces and parentheses; skip past it.
+        let rest = &stat[stat.rfind(')').ok_or_else(malformed)? + 1..];
+        let mut fields = rest.split_whitespace().skip(45);
+        let mut next = || -> io::Result<usize> {
+            fields
+                .next()
+                .and_then(|f| f.parse().ok())
+                .ok_or_else(malformed)
+        };
+        let (start, end) = (next()?, next()?);
+        if start == 0 || end < start {
+            return Err(malformed());
+        }
+        Ok((start, end))
+    }
+
+    pub(super) unsafe fn scrub(index: usize) -> io::Result<SecureBuf> {
+        let (start, end) = arg_area()?;
+        // Safety: the kernel reports the argument strings' location on our own stack,
+        // which stays mapped and writable for the life of the process; the caller
+        // guarantees nothing reads it concurrently.
+        let area = unsafe { slice::from_raw_parts_mut(start as *mut u8, end - start) };
+        super::take_nth(area, index)
+    }
+}
+
+#[cfg(target_vendor = "apple")]
+mod imp {
+    use std::ffi::CStr;
+    use std::io;
+    use std::slice;
+
+    use crate::SecureBuf;
+    use crate::zeroize::zeroize;
+
+    pub(super) unsafe fn scrub(index: usize) -> io::Result<SecureBuf> {
+        // Safety: argc/argv are set up by the loader; each entry is a NUL-terminated,
+        // writable string, and the caller guarantees nothing reads them concurrently.
+        unsafe {
+            let argc = *libc::_NSGetArgc();
+            let argv = *libc::_NSGetArgv();
+            if argv.is_null() || index >= argc.max(0) as usize {
+                return Err(super::no_such_argument());
+            }
+            let raw = *argv.add(index);
+            if raw.is_null() {
+                return Err(super::no_such_argument());
+            }
+            let len = CStr::from_ptr(raw).to_bytes().len();
+            let arg = slice::from_raw_parts_mut(raw.cast::<u8>(), len);
+            let buf = SecureBuf::from_slice(arg)?;
+            zeroize(arg);
+            Ok(buf)
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::slice;
+
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::System::Environment::{GetCommandLineA, GetCommandLineW};
+    use windows_sys::Win32::UI::Shell::CommandLineToArgvW;
+
+    use super::cmdline::arg_span;
+    use crate::SecureBuf;
+    use crate::zeroize::zeroize;
+
+    /// View a NUL-terminated string as a mutable slice, excluding the terminator.
+    unsafe fn terminated<'a, T: Copy + Default + PartialEq>(ptr: *mut T) -> &'a mut [T] {
+        let mut len = 0;
+        unsafe {
+            while *ptr.add(len) != T::default() {
+                len += 1;
+            }
+            slice::from_raw_parts_mut(ptr, len)
+        }
+    }
+
+    /// The argument as parsed by the OS, converted to UTF-8 in locked memory.
+    fn parsed_argument(index: usize) -> io::Result<SecureBuf> {
+        let mut argc = 0;
+        let argv = unsafe { CommandLineToArgvW(GetCommandLineW(), &mut argc) };
+        if argv.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let result = if index < argc.max(0) as usize {
+            // Safety: CommandLineToArgvW returns `argc` NUL-terminated strings in one
+            // LocalAlloc block that we own until LocalFree.
+            let units = unsafe { terminated(*argv.add(index)) };
+            let buf = crate::secure_buf::utf8_from_utf16(units.iter().copied());
+            let len = units.len() * 2;
+            zeroize(unsafe { slice::from_raw_parts_mut(units.as_mut_ptr().cast::<u8>(), len) });
+            buf
+        } else {
+            Err(super::no_such_argument())
+        };
+        unsafe { LocalFree(argv.cast()) };
+        result
+    }
+
+    pub(super) unsafe fn scrub(index: usize) -> io::Result<SecureBuf> {
+        let buf = parsed_argument(index)?;
+        // Safety: both pointers refer to process-owned, writable command-line buffers
+        // (the PEB's copy and the ANSI copy made at startup); the caller guarantees
+        // nothing reads them concurrently.
+        unsafe {
+            let wide = terminated(GetCommandLineW().cast_mut());
+            if let Some(span) = arg_span(wide, index) {
+                wide[span].fill(u16::from(b'*'));
+            }
+            let narrow = terminated(GetCommandLineA().cast_mut());
+            if let Some(span) = arg_span(narrow, index) {
+                narrow[span].fill(b'*');
+            }
+        }
+        Ok(buf)
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    windows
+)))]
+mod imp {
+    use std::io;
+
+    use crate::SecureBuf;
+
+    pub(super) unsafe fn scrub(_index: usize) -> io::Result<SecureBuf> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "scrub_argv_secret unsupported on this platform",
+        ))
+    }
+}
+
+/// Locating arguments in a raw Windows command line.
+#[cfg(any(windows, test))]
+mod cmdline {
+    use std::ops::Range;
+
+    fn is_blank(c: u32) -> bool {
+        c == u32::from(b' ') || c == u32::from(b'\t')
+    }
+
+    /// Raw span (quotes and escapes included) of argument `index` in `cmd`.
+    ///
+    /// Follows the MSVC/`CommandLineToArgvW` rules: the program name ends at the next
+    /// quote if it starts with one, otherwise at whitespace; later arguments toggle
+    /// quoting on `"` preceded by an even number of backslashes.
+    pub(super) fn arg_span<T: Copy + Into<u32>>(cmd: &[T], index: usize) -> Option<Range<usize>> {
+        let at = |i: usize| cmd.get(i).map(|&c| c.into());
+        let quote = u32::from(b'"');
+        let backslash = u32::from(b'\\');
+
+        let mut i = 0;
+        let end = if at(0) == Some(quote) {
+            i = 1;
+            while at(i).is_some_and(|c| c != quote) {
+                i += 1;
+            }
+            (i + 1).min(cmd.len())
+        } else {
+            while at(i).is_some_and(|c| !is_blank(c)) {
+                i += 1;
+            }
+            i
+        };
+        if index == 0 {
+            return Some(0..end);
+        }
+
+        i = end;
+        for n in 1.. {
+            while at(i).is_some_and(is_blank) {
+                i += 1;
+            }
+            if i == cmd.len() {
+                return None;
+            }
+            let start = i;
+            let mut quoted = false;
+            let mut backslashes = 0;
+            while let Some(c) = at(i) {
+                if is_blank(c) && !quoted {
+                    break;
+                }
+                if c == quote && backslashes % 2 == 0 {
+                    quoted = !quoted;
+                }
+                backslashes = if c == backslash { backslashes + 1 } else { 0 };
+                i += 1;
+            }
+            if n == index {
+                return Some(start..i);
+            }
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::cmdline::arg_span;
+
+    #[test]
+    fn windows_command_line_spans() {
+        let cmd = br#""C:\Program Files\tool.exe" --key "a b\"c" x\\"y z" last"#;
+        let spans: Vec<_> = (0..5).map(|i| arg_span(cmd, i).map(|r| &cmd[r])).collect();
+        assert_eq!(spans[0], Some(&br#""C:\Program Files\tool.exe""#[..]));
+        assert_eq!(spans[1], Some(&b"--key"[..]));
+        assert_eq!(spans[2], Some(&br#""a b\"c""#[..]));
+        assert_eq!(spans[3], Some(&br#"x\\"y z""#[..]));
+        assert_eq!(spans[4], Some(&b"last"[..]));
+        assert_eq!(arg_span(cmd, 5), None);
+
+        let wide: Vec<u16> = "tool.exe\tsecret ".encode_utf16().collect();
+        assert_eq!(arg_span(&wide, 1), Some(9..15));
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn scrub_rejects_missing_argument() {
+        let err = unsafe { super::scrub_argv_secret(usize::MAX) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+    }
+}
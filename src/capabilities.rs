@@ -0,0 +1,248 @@
+//! Which optional kernel interfaces the running kernel provides.
+
+use std::fmt;
+
+/// Kernel interfaces available to this process, as probed by [`KernelCapabilities::detect`].
+///
+/// Each interface is probed once with a harmless call (a zero-length range, or an
+/// invalid fd) and counts as available unless the kernel reports ENOSYS (or EINVAL for
+/// an unknown `madvise` advice). Seccomp filters that answer ENOSYS therefore show up
+/// as unavailable, which is what callers need to know.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KernelCapabilities {
+    mlock2: bool,
+    memfd_secret: bool,
+    mseal: bool,
+    process_madvise: bool,
+    wipe_on_fork: bool,
+    protection_keys: bool,
+    app_container: bool,
+}
+
+impl KernelCapabilities {
+    /// Probe the running kernel.
+    ///
+    /// Each call probes afresh (a handful of syscalls); keep the result if it is needed
+    /// repeatedly.
+    ///
+    /// Platform:
+    /// - Linux and Android. Elsewhere every capability is reported unavailable, except
+    ///   [`wipe_on_fork`](Self::wipe_on_fork) on OpenBSD and NetBSD, and
+    ///   [`app_container`](Self::app_container) on Windows.
+    pub fn detect() -> Self {
+        imp::detect()
+    }
+
+    /// `mlock2(2)` (Linux 4.4), needed for on-fault locking with [`crate::MLOCK_ONFAULT`].
+    pub fn mlock2(&self) -> bool {
+        self.mlock2
+    }
+
+    /// `memfd_secret(2)` (Linux 5.14, when booted with `secretmem.enable`), used by
+    /// [`SecureBuf::new_secret_mem`](crate::SecureBuf::new_secret_mem).
+    pub fn memfd_secret(&self) -> bool {
+        self.memfd_secret
+    }
+
+    /// `mseal(2)` (Linux 6.10, 64-bit), used by [`crate::mseal`].
+    pub fn mseal(&self) -> bool {
+        self.mseal
+    }
+
+    /// `process_madvise(2)` (Linux 5.10), used by [`crate::madvise_batch`].
+    pub fn process_madvise(&self) -> bool {
+        self.process_madvise
+    }
+
+    /// `MADV_WIPEONFORK` (Linux 4.14), or `MAP_INHERIT_ZERO` on OpenBSD and NetBSD, used
+    /// by [`crate::madvise_wipeonfork`].
+    pub fn wipe_on_fork(&self) -> bool {
+        self.wipe_on_fork
+    }
+
+    /// Memory protection keys enabled by CPU and kernel, used by [`crate::PkeyBuf`].
+    pub fn protection_keys(&self) -> bool {
+        self.protection_keys
+    }
+
+    /// Whether the process runs in an AppContainer (UWP or packaged-app sandbox), where
+    /// memory locking, `SetErrorMode` and Windows Error Reporting calls are restricted
+    /// and return `Unsupported` with that reason. Windows only.
+    pub fn app_container(&self) -> bool {
+        self.app_container
+    }
+
+    fn list(&self) -> [(&'static str, bool); 6] {
+        [
+            ("mlock2", self.mlock2),
+            ("memfd_secret", self.memfd_secret),
+            ("mseal", self.mseal),
+            ("process_madvise", self.process_madvise),
+            ("MADV_WIPEONFORK", self.wipe_on_fork),
+            ("protection keys", self.protection_keys),
+        ]
+    }
+}
+
+impl fmt::Display for KernelCapabilities {
+    /// One line naming what is available and what is not, for logs and bug reports.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, available) in [true, false].into_iter().enumerate() {
+            let names: Vec<_> = self
+                .list()
+                .into_iter()
+                .filter(|&(_, a)| a == available)
+                .map(|(name, _)| name)
+                .collect();
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            let label = if available {
+                "available"
+            } else {
+                "unavailable"
+            };
+            if names.is_empty() {
+                write!(f, "{label}: none")?;
+            } else {
+                write!(f, "{label}: {}", names.join(", "))?;
+            }
+        }
+        if self.app_container {
+            f.write_str("; restricted: AppContainer sandbox")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod imp {
+    use std::os::raw::{c_long, c_ulong};
+    use std::ptr;
+
+    use super::KernelCapabilities;
+
+    pub(super) fn detect() -> KernelCapabilities {
+        let zero: c_ulong = 0;
+        let null = ptr::null_mut::<libc::c_void>();
+        // Safety (all probes below): zero-length ranges and invalid fds; the kernel
+        // dereferences nothing.
+        KernelCapabilities {
+            mlock2: implemented(unsafe { libc::syscall(libc::SYS_mlock2, null, zero, zero) }),
+            memfd_secret: memfd_secret(),
+            mseal: crate::sys::SYS_MSEAL
+                .is_some_and(|nr| implemented(unsafe { libc::syscall(nr, null, zero, zero) })),
+            process_madvise: implemented(unsafe {
+                libc::syscall(
+                    libc::SYS_process_madvise,
+                    c_long::from(-1),
+                    null,
+                    zero,
+                    c_long::from(libc::MADV_COLD),
+                    zero,
+                )
+            }),
+            wipe_on_fork: unsafe { libc::madvise(null, 0, libc::MADV_WIPEONFORK) } == 0,
+            protection_keys: crate::pkey::supported(),
+            app_container: false,
+        }
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "s390x"
+        )
+    ))]
+    fn memfd_secret() -> bool {
+        // Safety: plain syscall with an integer flag argument.
+        let fd = unsafe { libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC) };
+        if fd >= 0 {
+            // Safety: the descriptor was just created and is not used elsewhere.
+            unsafe { libc::close(fd as libc::c_int) };
+        }
+        implemented(fd)
+    }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        any(
+            target_arch = "x86",
+            target_arch = "x86_64",
+            target_arch = "aarch64",
+            target_arch = "s390x"
+        )
+    )))]
+    fn memfd_secret() -> bool {
+        false
+    }
+
+    /// Whether a raw syscall result shows the kernel knows the call.
+    fn implemented(rc: c_long) -> bool {
+        rc >= 0 || std::io::Error::last_os_error().raw_os_error() != Some(libc::ENOSYS)
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod imp {
+    use super::KernelCapabilities;
+
+    pub(super) fn detect() -> KernelCapabilities {
+        KernelCapabilities {
+            // MAP_INHERIT_ZERO predates every OpenBSD and NetBSD release Rust supports.
+            wipe_on_fork: cfg!(any(target_os = "openbsd", target_os = "netbsd")),
+            #[cfg(windows)]
+            app_container: crate::windows::is_app_container(),
+            ..KernelCapabilities::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KernelCapabilities;
+
+    #[test]
+    fn detection_matches_what_the_crate_gets() {
+        let caps = KernelCapabilities::detect();
+        assert!(caps.to_string().starts_with("available: "));
+        #[cfg(target_os = "linux")]
+        {
+            let buf = crate::SecureBuf::new_secret_mem(1).unwrap();
+            assert_eq!(buf.is_secret_mem(), caps.memfd_secret());
+            // A mapping of its own: advice on a heap page would outlive the buffer and wipe
+            // whatever malloc puts there next in other tests' children.
+            let buf = crate::secure_mmap(1).unwrap();
+            let (addr, len) = buf.region();
+            // Safety: the buffer's own mapping, which outlives the call.
+            let wiped = unsafe { crate::madvise_wipeonfork(addr, len) };
+            assert_eq!(wiped.is_ok(), caps.wipe_on_fork());
+        }
+    }
+
+    #[test]
+    fn display_lists_both_sides() {
+        let caps = KernelCapabilities {
+            mlock2: true,
+            mseal: true,
+            ..KernelCapabilities::default()
+        };
+        assert_eq!(
+            caps.to_string(),
+            "available: mlock2, mseal; unavailable: memfd_secret, process_madvise, \
+             MADV_WIPEONFORK, protection keys"
+        );
+        let sandboxed = KernelCapabilities {
+            app_container: true,
+            ..caps
+        };
+        assert!(
+            sandboxed
+                .to_string()
+                .ends_with("; restricted: AppContainer sandbox")
+        );
+    }
+}
@@ -0,0 +1,89 @@
+//! Minimal ChaCha20 keystream (RFC 8439) for sealing secrets in memory.
+//!
+//! Only confidentiality is needed: sealed data never leaves the process and is decrypted
+//! with the key that sealed it, so there is no MAC and no public API.
+
+use std::slice;
+
+use crate::zeroize::zeroize;
+
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+fn wipe_words(words: &mut [u32; 16]) {
+    // Safety: a [u32; 16] is 64 initialized bytes with no padding.
+    zeroize(unsafe { slice::from_raw_parts_mut(words.as_mut_ptr().cast::<u8>(), 64) });
+}
+
+/// XOR the ChaCha20 keystream for (`key`, `nonce`), starting at block `counter`, into `data`.
+///
+/// Applying it twice with the same arguments restores the input. Key-derived state and
+/// keystream blocks are zeroized before returning.
+pub(crate) fn xor_keystream(key: &[u8; 32], nonce: &[u8; 12], counter: u32, data: &mut [u8]) {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+    for (word, bytes) in state[4..12].iter_mut().zip(key.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+    state[12] = counter;
+    for (word, bytes) in state[13..].iter_mut().zip(nonce.chunks_exact(4)) {
+        *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+    }
+
+    let mut x = [0u32; 16];
+    let mut block = [0u8; 64];
+    for chunk in data.chunks_mut(64) {
+        x = state;
+        for _ in 0..10 {
+            quarter_round(&mut x, 0, 4, 8, 12);
+            quarter_round(&mut x, 1, 5, 9, 13);
+            quarter_round(&mut x, 2, 6, 10, 14);
+            quarter_round(&mut x, 3, 7, 11, 15);
+            quarter_round(&mut x, 0, 5, 10, 15);
+            quarter_round(&mut x, 1, 6, 11, 12);
+            quarter_round(&mut x, 2, 7, 8, 13);
+            quarter_round(&mut x, 3, 4, 9, 14);
+        }
+        for (out, (a, b)) in block.chunks_exact_mut(4).zip(x.iter().zip(&state)) {
+            out.copy_from_slice(&a.wrapping_add(*b).to_le_bytes());
+        }
+        for (d, k) in chunk.iter_mut().zip(&block) {
+            *d ^= k;
+        }
+        state[12] = state[12].wrapping_add(1);
+    }
+
+    wipe_words(&mut state);
+    wipe_words(&mut x);
+    zeroize(&mut block);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::xor_keystream;
+
+    #[test]
+    fn matches_rfc8439_encryption_vector() {
+        // RFC 8439, section 2.4.2.
+        let key: [u8; 32] = std::array::from_fn(|i| i as u8);
+        let nonce = [0, 0, 0, 0, 0, 0, 0, 0x4a, 0, 0, 0, 0];
+        let mut data = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.".to_vec();
+        xor_keystream(&key, &nonce, 1, &mut data);
+        let expected = "6e2e359a2568f98041ba0728dd0d6981e97e7aec1d4360c20a27afccfd9fae0b\
+                        f91b65c5524733ab8f593dabcd62b3571639d624e65152ab8f530c359f0861d8\
+                        07ca0dbf500d6a6156a38e088a22b65e52bc514d16ccf806818ce91ab7793736\
+                        5af90bbf74a35be6b40b8eedf2785e42874d";
+        let hex: String = data.iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, expected);
+    }
+}
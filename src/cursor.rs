@@ -0,0 +1,105 @@
+//! `std::io` adapters that stream directly into and out of locked buffers.
+
+use std::fmt;
+use std::io::{self, BufRead, Read, Seek, SeekFrom, Write};
+
+use crate::SecureBuf;
+
+/// A `Read`/`Write`/`Seek` cursor over a [`SecureBuf`].
+///
+/// Bytes are copied straight between the I/O source or sink and locked memory, so
+/// plaintext never needs an unlocked staging buffer. Writes are bounded by the
+/// buffer length: once the end is reached, `write` returns `Ok(0)` and `write_all`
+/// fails with `WriteZero`. Use [`SecureCursor::position`] to learn how much was filled.
+///
+/// For read-only access to shared buffers, `LockedArc::as_slice()` already
+/// implements `Read` as a `&[u8]`.
+pub struct SecureCursor<'a> {
+    inner: io::Cursor<&'a mut [u8]>,
+}
+
+impl SecureBuf {
+    /// Borrow this buffer as a cursor positioned at the start.
+    pub fn cursor(&mut self) -> SecureCursor<'_> {
+        SecureCursor {
+            inner: io::Cursor::new(self.as_mut_slice()),
+        }
+    }
+}
+
+impl SecureCursor<'_> {
+    /// Current position in bytes from the start of the buffer.
+    pub fn position(&self) -> u64 {
+        self.inner.position()
+    }
+
+    /// Move the cursor to `pos` bytes from the start of the buffer.
+    pub fn set_position(&mut self, pos: u64) {
+        self.inner.set_position(pos)
+    }
+}
+
+impl Read for SecureCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl BufRead for SecureCursor<'_> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.inner.consume(amt)
+    }
+}
+
+impl Write for SecureCursor<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for SecureCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+impl fmt::Debug for SecureCursor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("SecureCursor")
+            .field("position", &self.inner.position())
+            .field("len", &self.inner.get_ref().len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read, Seek, SeekFrom, Write};
+
+    use crate::SecureBuf;
+
+    #[test]
+    fn write_then_read_back_through_cursor() {
+        let mut buf = SecureBuf::new(8).unwrap();
+        let mut cursor = buf.cursor();
+        cursor.write_all(b"abcd").unwrap();
+        assert_eq!(
+            cursor.write_all(b"efghij").unwrap_err().kind(),
+            io::ErrorKind::WriteZero
+        );
+        cursor.seek(SeekFrom::Start(2)).unwrap();
+        let mut out = [0u8; 3];
+        cursor.read_exact(&mut out).unwrap();
+        assert_eq!(&out, b"cde");
+        assert_eq!(buf.as_slice(), b"abcdefgh");
+    }
+}
@@ -0,0 +1,214 @@
+//! Serde deserialization of secret fields straight into locked memory.
+//!
+//! Deserializers hand string and byte values to a visitor either borrowed from the input
+//! or in a temporary `String`/`Vec<u8>`. The visitors here copy them into a
+//! [`SecureBuf`] immediately and zeroize any owned temporary they receive. Copies made
+//! before that point (the input text itself, or scratch buffers the format uses while
+//! unescaping) are outside this crate's control.
+
+use std::fmt;
+use std::ops::Deref;
+
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+
+use crate::SecureBuf;
+use crate::zeroize::zeroize;
+
+/// Secret bytes deserialized directly into a [`SecureBuf`].
+///
+/// Accepts byte strings, strings (their UTF-8 bytes), and sequences of `u8`, so it works
+/// for both binary formats and text formats such as JSON or TOML.
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub struct LockedBytes(SecureBuf);
+
+impl LockedBytes {
+    /// Take the underlying locked buffer.
+    pub fn into_inner(self) -> SecureBuf {
+        self.0
+    }
+}
+
+impl Deref for LockedBytes {
+    type Target = SecureBuf;
+
+    fn deref(&self) -> &SecureBuf {
+        &self.0
+    }
+}
+
+impl fmt::Debug for LockedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("LockedBytes")
+            .field("len", &self.0.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A secret UTF-8 string deserialized directly into a [`SecureBuf`].
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub struct LockedString(SecureBuf);
+
+impl LockedString {
+    /// Borrow the secret as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Safety: the contents were validated as UTF-8 on construction and are never
+        // mutated afterwards.
+        unsafe { std::str::from_utf8_unchecked(self.0.as_slice()) }
+    }
+
+    /// Whether the backing storage is locked in RAM.
+    pub fn is_locked(&self) -> bool {
+        self.0.is_locked()
+    }
+
+    /// Take the underlying locked buffer holding the UTF-8 bytes.
+    pub fn into_inner(self) -> SecureBuf {
+        self.0
+    }
+}
+
+impl Deref for LockedString {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for LockedString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("LockedString")
+            .field("len", &self.0.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn locked_copy<E: de::Error>(bytes: &[u8]) -> Result<SecureBuf, E> {
+    SecureBuf::from_slice(bytes).map_err(E::custom)
+}
+
+/// Copy an owned temporary into locked memory, then zeroize the temporary.
+fn locked_move<E: de::Error>(mut bytes: Vec<u8>) -> Result<SecureBuf, E> {
+    let buf = locked_copy(&bytes);
+    zeroize(&mut bytes);
+    buf
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = LockedBytes;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a byte string, string, or sequence of bytes")
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        locked_copy(v).map(LockedBytes)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        locked_move(v).map(LockedBytes)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        self.visit_byte_buf(v.into_bytes())
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        // Never trust a size hint from the input for the initial allocation.
+        let hint = seq.size_hint().unwrap_or(0).min(4096);
+        let mut buf = SecureBuf::new(hint).map_err(de::Error::custom)?;
+        let mut len = 0;
+        while let Some(byte) = seq.next_element::<u8>()? {
+            if len == buf.len() {
+                // Double, so elements cost amortized O(1) regions; `grow` wipes the old one.
+                buf.grow((len * 2).max(32)).map_err(de::Error::custom)?;
+            }
+            buf.as_mut_slice()[len] = byte;
+            len += 1;
+        }
+        buf.truncate(len);
+        Ok(LockedBytes(buf))
+    }
+}
+
+impl<'de> Deserialize<'de> for LockedBytes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+struct StringVisitor;
+
+impl<'de> Visitor<'de> for StringVisitor {
+    type Value = LockedString;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        locked_copy(v.as_bytes()).map(LockedString)
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        locked_move(v.into_bytes()).map(LockedString)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        match std::str::from_utf8(v) {
+            Ok(s) => self.visit_str(s),
+            Err(_) => Err(E::invalid_value(
+                de::Unexpected::Other("non-UTF-8 bytes"),
+                &self,
+            )),
+        }
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, mut v: Vec<u8>) -> Result<Self::Value, E> {
+        let result = self.visit_bytes(&v);
+        zeroize(&mut v);
+        result
+    }
+}
+
+impl<'de> Deserialize<'de> for LockedString {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_string(StringVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use serde::de::value::{BytesDeserializer, Error, SeqDeserializer, StrDeserializer};
+
+    use super::{LockedBytes, LockedString};
+
+    #[test]
+    fn deserializes_strings_bytes_and_sequences() {
+        let s = LockedString::deserialize(StrDeserializer::<Error>::new("p@ss")).unwrap();
+        assert_eq!(&*s, "p@ss");
+        assert!(!format!("{s:?}").contains("p@ss"));
+
+        let b = LockedBytes::deserialize(BytesDeserializer::<Error>::new(b"\x00\xff")).unwrap();
+        assert_eq!(b.as_slice(), b"\x00\xff");
+
+        let bytes: Vec<u8> = (0..100).collect();
+        // `filter` hides the length, so the buffer has to grow as bytes arrive.
+        let seq = SeqDeserializer::<_, Error>::new(bytes.iter().copied().filter(|_| true));
+        assert_eq!(
+            LockedBytes::deserialize(seq).unwrap().as_slice(),
+            &bytes[..]
+        );
+
+        assert!(LockedString::deserialize(BytesDeserializer::<Error>::new(b"\xff")).is_err());
+    }
+}
@@ -0,0 +1,388 @@
+//! Telling security-policy denials apart from ordinary `mlock`/`madvise` failures.
+//!
+//! A seccomp filter or a MAC policy (SELinux, AppArmor) answering EPERM looks exactly
+//! like an exhausted `RLIMIT_MEMLOCK`, and so does the macOS App Sandbox, so failures of
+//! the Unix wrappers are checked against the process's policy state and, when a policy
+//! is the likely cause, carry a [`PolicyDenial`] instead of the bare errno.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The security mechanism blamed by a [`PolicyDenial`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SecurityPolicy {
+    /// A seccomp filter (`Seccomp: 2` in `/proc/self/status`), as installed by most
+    /// container runtimes, gVisor, systemd's `SystemCallFilter=`, and browser sandboxes.
+    Seccomp,
+    /// SELinux in enforcing mode (`/sys/fs/selinux/enforce`).
+    SeLinux,
+    /// An AppArmor profile in enforce mode confining this process.
+    AppArmor,
+    /// The macOS App Sandbox (`APP_SANDBOX_CONTAINER_ID` is set in the environment).
+    AppSandbox,
+    /// The macOS hardened runtime (`CS_RUNTIME` in the process's code-signing status),
+    /// blamed only outside the App Sandbox.
+    HardenedRuntime,
+}
+
+/// A memory call refused by a security policy rather than by limits or bad arguments.
+///
+/// Returned inside the `io::Error` of the Unix wrappers ([`mlock`](crate::mlock),
+/// [`munlock`](crate::munlock), [`madvise`](crate::madvise),
+/// [`mlockall`](crate::mlockall), [`munlockall`](crate::munlockall),
+/// [`mlock2`](crate::mlock2)), and of the `setrlimit` calls made by
+/// [`raise_memlock_limit`](crate::raise_memlock_limit) and the macOS core-dump helpers;
+/// the error keeps the kind of the original errno. Get it back with
+/// [`PolicyDenial::find`].
+///
+/// Detection is a heuristic: the errno a policy returns is indistinguishable from the
+/// kernel's own, so a denial is reported only when a policy is active and the errno has
+/// no ordinary explanation (e.g. EPERM from `mlock` while `RLIMIT_MEMLOCK` is non-zero).
+/// MAC policies are blamed only for what they can actually deny: EPERM from locking
+/// under a zero limit although `CAP_IPC_LOCK` is effective, and EACCES. On macOS, EPERM
+/// and EACCES from locking, or from a `setrlimit` that raises no hard limit, are blamed
+/// on the App Sandbox or the hardened runtime when the process runs under one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PolicyDenial {
+    call: &'static str,
+    policy: SecurityPolicy,
+    errno: i32,
+}
+
+impl PolicyDenial {
+    /// The denial carried by `err`, if it was classified as one.
+    pub fn find(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+
+    /// The refused call, e.g. `"mlock"`.
+    pub fn call(&self) -> &'static str {
+        self.call
+    }
+
+    /// The policy blamed for the denial.
+    pub fn policy(&self) -> SecurityPolicy {
+        self.policy
+    }
+
+    /// The errno the call failed with.
+    pub fn raw_os_error(&self) -> i32 {
+        self.errno
+    }
+}
+
+impl fmt::Display for PolicyDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let os = io::Error::from_raw_os_error(self.errno);
+        match self.policy {
+            SecurityPolicy::Seccomp => write!(
+                f,
+                "{} was blocked by the process's seccomp filter ({os}); allow {} in the \
+                 sandbox's seccomp profile (e.g. the container runtime's, or systemd's \
+                 SystemCallFilter=)",
+                self.call, self.call
+            ),
+            SecurityPolicy::SeLinux => write!(
+                f,
+                "{} was blocked by security policy: SELinux is enforcing ({os}); look for an \
+                 AVC denial in the audit log (ausearch -m avc) and allow it, e.g. the \
+                 ipc_lock capability, in the process's domain",
+                self.call
+            ),
+            SecurityPolicy::AppArmor => write!(
+                f,
+                "{} was blocked by security policy: the process's AppArmor profile is \
+                 enforcing ({os}); look for apparmor=\"DENIED\" in the audit log and add \
+                 `capability ipc_lock,` to the profile",
+                self.call
+            ),
+            SecurityPolicy::AppSandbox => write!(
+                f,
+                "{} was refused inside the macOS App Sandbox ({os}); the sandbox profile \
+                 has no entitlement granting it, so run the code that handles secrets in a \
+                 helper without com.apple.security.app-sandbox (e.g. an XPC service or \
+                 login item), or accept unlocked memory with SecureBuf::new_best_effort",
+                self.call
+            ),
+            SecurityPolicy::HardenedRuntime => write!(
+                f,
+                "{} was refused while the process runs under the macOS hardened runtime \
+                 ({os}); compare with a build signed without `codesign -o runtime`, and \
+                 check `codesign -d --entitlements - <binary>` for the exception the call \
+                 needs",
+                self.call
+            ),
+        }
+    }
+}
+
+impl Error for PolicyDenial {}
+
+/// Replace `err` from `call` with a [`PolicyDenial`] when a policy likely caused it.
+pub(crate) fn classify(err: io::Error, call: &'static str) -> io::Error {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if let Some(errno) = err.raw_os_error()
+        && let Ok(status) = std::fs::read_to_string("/proc/self/status")
+    {
+        let memlock = crate::memlock_limit().ok().and_then(|limit| limit.soft);
+        if let Some(policy) = detect(call, errno, &status, memlock, mac_policy()) {
+            return io::Error::new(
+                err.kind(),
+                PolicyDenial {
+                    call,
+                    policy,
+                    errno,
+                },
+            );
+        }
+    }
+    #[cfg(target_os = "macos")]
+    if let Some(errno) = err.raw_os_error()
+        && let Some(policy) = detect_apple(call, errno, app_sandboxed(), hardened_runtime())
+    {
+        return io::Error::new(
+            err.kind(),
+            PolicyDenial {
+                call,
+                policy,
+                errno,
+            },
+        );
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+    let _ = call;
+    err
+}
+
+/// Which macOS policy, if any, explains `errno` from `call`.
+///
+/// Callers only classify `setrlimit` failures that raise no hard limit: raising one
+/// without root is refused with EPERM regardless of any sandbox.
+#[cfg(target_os = "macos")]
+fn detect_apple(call: &str, errno: i32, sandboxed: bool, hardened: bool) -> Option<SecurityPolicy> {
+    let policy = if sandboxed {
+        SecurityPolicy::AppSandbox
+    } else if hardened {
+        SecurityPolicy::HardenedRuntime
+    } else {
+        return None;
+    };
+    // Darwin's own answers for these calls are EAGAIN, ENOMEM and EINVAL.
+    match (call, errno) {
+        (
+            "mlock" | "munlock" | "mlockall" | "munlockall" | "setrlimit",
+            libc::EPERM | libc::EACCES,
+        ) => Some(policy),
+        _ => None,
+    }
+}
+
+/// Whether the process runs in the App Sandbox, which sets the container's bundle ID in
+/// the environment of every sandboxed process.
+#[cfg(target_os = "macos")]
+fn app_sandboxed() -> bool {
+    std::env::var_os("APP_SANDBOX_CONTAINER_ID").is_some()
+}
+
+/// Whether the process was signed with the hardened runtime (`codesign -o runtime`).
+#[cfg(target_os = "macos")]
+fn hardened_runtime() -> bool {
+    use std::ffi::c_void;
+
+    const CS_OPS_STATUS: libc::c_uint = 0;
+    const CS_RUNTIME: u32 = 0x0001_0000;
+
+    unsafe extern "C" {
+        // Declared in <sys/codesign.h>; libc does not bind it.
+        fn csops(
+            pid: libc::pid_t,
+            ops: libc::c_uint,
+            useraddr: *mut c_void,
+            usersize: libc::size_t,
+        ) -> libc::c_int;
+    }
+
+    let mut flags: u32 = 0;
+    // Safety: `flags` is a writable buffer of the size passed.
+    let rc = unsafe {
+        csops(
+            libc::getpid(),
+            CS_OPS_STATUS,
+            (&mut flags as *mut u32).cast(),
+            size_of::<u32>(),
+        )
+    };
+    rc == 0 && flags & CS_RUNTIME != 0
+}
+
+/// Which policy, if any, explains `errno` from `call`, given `/proc/self/status`, the
+/// soft `RLIMIT_MEMLOCK` (`None` for unlimited) and the enforcing MAC policy.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn detect(
+    call: &str,
+    errno: i32,
+    status: &str,
+    memlock: Option<u64>,
+    mac: Option<SecurityPolicy>,
+) -> Option<SecurityPolicy> {
+    let seccomp = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Seccomp:"))
+        .is_some_and(|mode| mode.trim() == "2")
+        .then_some(SecurityPolicy::Seccomp);
+    match errno {
+        // Every call here predates any kernel Rust supports.
+        libc::ENOSYS => seccomp,
+        // None of these calls return EACCES by themselves.
+        libc::EACCES => seccomp.or(mac),
+        libc::EPERM => match call {
+            // Under a zero limit locking needs CAP_IPC_LOCK: without it EPERM is the
+            // kernel's own answer, with it only a MAC policy denying the capability (or
+            // a filter) explains EPERM. Above zero the capability is never consulted.
+            "mlock" | "mlock2" | "mlockall" if memlock == Some(0) => {
+                if crate::limits::has_cap_ipc_lock(status) {
+                    mac.or(seccomp)
+                } else {
+                    None
+                }
+            }
+            "mlock" | "mlock2" | "mlockall" | "munlock" | "munlockall" => seccomp,
+            // madvise returns EPERM for sealed ranges and privileged advice.
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The MAC policy enforcing on this process, if any.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn mac_policy() -> Option<SecurityPolicy> {
+    use std::fs;
+
+    if fs::read_to_string("/sys/fs/selinux/enforce").is_ok_and(|mode| mode.trim() == "1") {
+        return Some(SecurityPolicy::SeLinux);
+    }
+    // The LSM-specific file exists since Linux 5.1; before that AppArmor owns the
+    // shared one.
+    let label = fs::read_to_string("/proc/self/attr/apparmor/current")
+        .or_else(|_| fs::read_to_string("/proc/self/attr/current"))
+        .ok()?;
+    apparmor_enforcing(&label).then_some(SecurityPolicy::AppArmor)
+}
+
+/// Whether an AppArmor label such as `docker-default (enforce)` is enforced.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn apparmor_enforcing(label: &str) -> bool {
+    let label = label.trim_end_matches(['\0', '\n']);
+    label.ends_with(" (enforce)") || label.ends_with(" (kill)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PolicyDenial;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn seccomp_is_blamed_only_without_another_explanation() {
+        use super::{SecurityPolicy, apparmor_enforcing, detect};
+
+        let filtered = "Name:\tapp\nCapEff:\t0000000000000000\nSeccomp:\t2\n";
+        let unfiltered = "Name:\tapp\nCapEff:\t0000000000000000\nSeccomp:\t0\n";
+        let capable = "Name:\tapp\nCapEff:\t0000000000004000\nSeccomp:\t0\n";
+        let blamed = Some(SecurityPolicy::Seccomp);
+        let selinux = Some(SecurityPolicy::SeLinux);
+        assert_eq!(
+            detect("mlock", libc::EPERM, filtered, Some(65536), None),
+            blamed
+        );
+        assert_eq!(
+            detect("madvise", libc::ENOSYS, filtered, None, None),
+            blamed
+        );
+        assert_eq!(detect("mlock", libc::EPERM, filtered, Some(0), None), None);
+        assert_eq!(
+            detect("mlock", libc::ENOMEM, filtered, Some(65536), None),
+            None
+        );
+        assert_eq!(detect("madvise", libc::EPERM, filtered, None, None), None);
+        assert_eq!(
+            detect("mlock", libc::EPERM, unfiltered, Some(65536), None),
+            None
+        );
+
+        // MAC policies only get the blame when they denied CAP_IPC_LOCK.
+        assert_eq!(
+            detect("mlock", libc::EPERM, capable, Some(0), selinux),
+            selinux
+        );
+        assert_eq!(
+            detect("mlock", libc::EPERM, unfiltered, Some(0), selinux),
+            None
+        );
+        assert_eq!(
+            detect("mlock", libc::EPERM, capable, Some(65536), selinux),
+            None
+        );
+        assert_eq!(
+            detect("madvise", libc::EACCES, unfiltered, None, selinux),
+            selinux
+        );
+        assert_eq!(
+            detect("madvise", libc::ENOSYS, unfiltered, None, selinux),
+            None
+        );
+
+        assert!(apparmor_enforcing("docker-default (enforce)\n"));
+        assert!(!apparmor_enforcing("unconfined\n"));
+        assert!(!apparmor_enforcing("snap.app (complain)\n"));
+    }
+
+    #[test]
+    fn denial_survives_the_io_error() {
+        let denial = PolicyDenial {
+            call: "mlock",
+            policy: super::SecurityPolicy::Seccomp,
+            errno: 1,
+        };
+        let err = std::io::Error::new(std::io::ErrorKind::PermissionDenied, denial.clone());
+        assert_eq!(PolicyDenial::find(&err), Some(&denial));
+        assert!(
+            err.to_string()
+                .starts_with("mlock was blocked by the process's seccomp")
+        );
+        assert_eq!(PolicyDenial::find(&std::io::Error::other("x")), None);
+
+        let sandboxed = PolicyDenial {
+            call: "mlock",
+            policy: super::SecurityPolicy::AppSandbox,
+            errno: 1,
+        };
+        assert!(
+            sandboxed
+                .to_string()
+                .starts_with("mlock was refused inside the macOS App Sandbox")
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn apple_policies_are_blamed_only_for_refusals() {
+        use super::{SecurityPolicy, detect_apple};
+
+        let sandbox = Some(SecurityPolicy::AppSandbox);
+        assert_eq!(detect_apple("mlock", libc::EPERM, true, true), sandbox);
+        assert_eq!(
+            detect_apple("setrlimit", libc::EACCES, true, false),
+            sandbox
+        );
+        assert_eq!(
+            detect_apple("mlock", libc::EPERM, false, true),
+            Some(SecurityPolicy::HardenedRuntime)
+        );
+        assert_eq!(detect_apple("mlock", libc::EPERM, false, false), None);
+        assert_eq!(detect_apple("mlock", libc::EAGAIN, true, true), None);
+        assert_eq!(detect_apple("madvise", libc::EPERM, true, true), None);
+    }
+}
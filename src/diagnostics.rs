@@ -0,0 +1,83 @@
+//! Where failures that cannot be returned, mostly from `Drop`, are reported.
+//!
+//! By default they go to stderr. With the `os-log` feature on Apple platforms they go to
+//! unified logging instead (subsystem `os-memlock`, category `diagnostics`, type
+//! `error`), so daemons whose stderr is discarded still record them:
+//!
+//! ```text
+//! log show --predicate 'subsystem == "os-memlock"' --last 1h
+//! ```
+
+use std::fmt;
+
+/// Report a failure that has no caller to return it to; formats like `eprintln!`.
+macro_rules! diag {
+    ($($arg:tt)*) => {
+        $crate::diagnostics::report(format_args!($($arg)*))
+    };
+}
+
+pub(crate) fn report(args: fmt::Arguments<'_>) {
+    #[cfg(all(feature = "os-log", target_vendor = "apple"))]
+    os_log::error(&args.to_string());
+    #[cfg(not(all(feature = "os-log", target_vendor = "apple")))]
+    eprintln!("os-memlock: {args}");
+}
+
+#[cfg(all(feature = "os-log", target_vendor = "apple"))]
+mod os_log {
+    use std::ffi::{CString, c_char, c_void};
+    use std::sync::OnceLock;
+
+    /// `OS_LOG_TYPE_ERROR`.
+    const TYPE_ERROR: u8 = 0x10;
+
+    unsafe extern "C" {
+        /// The Mach-O header of the image holding the format string, which `os_log`
+        /// records the string relative to.
+        static __dso_handle: u8;
+
+        fn os_log_create(subsystem: *const c_char, category: *const c_char) -> *mut c_void;
+
+        // What the `os_log` macro expands to; there is no function taking a plain string.
+        fn _os_log_impl(
+            dso: *const c_void,
+            log: *mut c_void,
+            kind: u8,
+            format: *const c_char,
+            buf: *const u8,
+            size: u32,
+        );
+    }
+
+    /// The `os_log_t` for the crate, created once and never released.
+    fn log() -> *mut c_void {
+        static LOG: OnceLock<usize> = OnceLock::new();
+        *LOG.get_or_init(|| {
+            // Safety: two NUL-terminated strings; the result is valid for the process's
+            // lifetime.
+            unsafe { os_log_create(c"os-memlock".as_ptr(), c"diagnostics".as_ptr()) as usize }
+        }) as *mut c_void
+    }
+
+    pub(super) fn error(message: &str) {
+        let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+        // The argument buffer `__builtin_os_log_format` builds for "%{public}s": summary
+        // flags (has non-scalar items), argument count, then the argument's descriptor
+        // (string, public), size and value.
+        let mut buf = vec![0x02, 0x01, 0x22, size_of::<usize>() as u8];
+        buf.extend_from_slice(&(message.as_ptr() as usize).to_ne_bytes());
+        // Safety: the format is a NUL-terminated string in this image, and `buf`
+        // describes one pointer to `message`, which outlives the call.
+        unsafe {
+            _os_log_impl(
+                (&raw const __dso_handle).cast(),
+                log(),
+                TYPE_ERROR,
+                c"%{public}s".as_ptr(),
+                buf.as_ptr(),
+                buf.len() as u32,
+            )
+        };
+    }
+}
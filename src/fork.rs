@@ -0,0 +1,361 @@
+//! Registry of live secret regions and the `fork` handlers that act on it.
+//!
+//! Every private region owned by a [`SecureBuf`](crate::SecureBuf), a
+//! [`PhysicalBuf`](crate::PhysicalBuf) or a [`LockedHeap`](crate::LockedHeap) is
+//! recorded here.
+//! The lock is a spin lock rather than a `Mutex` because the atfork prepare handler
+//! takes it before `fork` and the parent and child handlers release it afterwards,
+//! which a guard-based lock cannot express.
+
+use std::cell::UnsafeCell;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// A region to act on in forked children.
+pub(crate) struct Region {
+    pub(crate) addr: usize,
+    pub(crate) len: usize,
+}
+
+struct Registry {
+    busy: AtomicBool,
+    regions: UnsafeCell<Vec<Region>>,
+}
+
+// Safety: `regions` is only accessed while `busy` is held.
+unsafe impl Sync for Registry {}
+
+static REGISTRY: Registry = Registry {
+    busy: AtomicBool::new(false),
+    regions: UnsafeCell::new(Vec::new()),
+};
+
+fn acquire() {
+    while REGISTRY
+        .busy
+        .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+        .is_err()
+    {
+        std::thread::yield_now();
+    }
+}
+
+fn release() {
+    REGISTRY.busy.store(false, Ordering::Release);
+}
+
+/// Run `f` with the registry locked, so no `fork` observes a half-updated region.
+pub(crate) fn with_regions<R>(f: impl FnOnce(&mut Vec<Region>) -> R) -> R {
+    struct Release;
+    impl Drop for Release {
+        fn drop(&mut self) {
+            release();
+        }
+    }
+    acquire();
+    let _release = Release;
+    // Safety: the lock is held until `_release` drops.
+    f(unsafe { &mut *REGISTRY.regions.get() })
+}
+
+/// Record a region owned by a secret buffer.
+pub(crate) fn track(addr: *mut u8, len: usize) {
+    with_regions(|regions| {
+        regions.push(Region {
+            addr: addr as usize,
+            len,
+        })
+    });
+}
+
+/// Forget the region starting at `addr`; a no-op if it was never tracked.
+pub(crate) fn untrack(addr: *mut u8) {
+    with_regions(|regions| {
+        if let Some(i) = regions.iter().position(|r| r.addr == addr as usize) {
+            regions.swap_remove(i);
+        }
+    });
+}
+
+/// Install a `pthread_atfork` child handler that zeroizes every live secret buffer in
+/// forked children.
+///
+/// Behavior:
+/// - Covers [`SecureBuf`](crate::SecureBuf) and every container built on it, whether
+///   heap-backed or from `secure_mmap*`. Heap regions cannot carry `MADV_WIPEONFORK`,
+///   and kernels before Linux 4.14 (and other Unix systems) lack it entirely, so this
+///   protects against libraries that `fork` without `exec`.
+/// - Buffers from [`SecureBuf::new_secret_mem`](crate::SecureBuf::new_secret_mem) that
+///   got secret memory are skipped: that mapping is shared with the parent, which
+///   would be wiped as well.
+/// - Regions gated with [`set_page_access`](crate::set_page_access) are made readable and
+///   writable again in the child before the wipe; the parent keeps its protection.
+/// - Only `fork` through libc runs atfork handlers; raw `clone` and `vfork` do not.
+/// - Idempotent; the handler cannot be removed once installed.
+///
+/// Platform:
+/// - Unix. Elsewhere returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(())` once the handler is installed.
+/// - `Err(...)` with the error from `pthread_atfork` (ENOMEM) otherwise.
+pub fn register_fork_wipe() -> io::Result<()> {
+    imp::register_wipe()
+}
+
+/// Install a `pthread_atfork` child handler that re-locks every live secret buffer in
+/// forked children, for fork-server and prefork-worker designs where the child keeps
+/// using the secrets.
+///
+/// Behavior:
+/// - Memory locks are not inherited across `fork`, so without this the child's copies
+///   of locked buffers are swappable. The handler calls `mlock` on each registered
+///   region (and re-applies the dontdump hint) before `fork` returns in the child.
+/// - Locking failures in the child (e.g. a lower `RLIMIT_MEMLOCK`) are ignored, since
+///   the handler has no way to report them.
+/// - Combined with [`register_fork_wipe`], regions are wiped first and then locked.
+/// - Only `fork` through libc runs atfork handlers; raw `clone` and `vfork` do not.
+/// - Idempotent; the handler cannot be removed once installed.
+///
+/// Platform:
+/// - Unix. Elsewhere returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(())` once the handler is installed.
+/// - `Err(...)` with the error from `pthread_atfork` (ENOMEM) otherwise.
+pub fn register_fork_relock() -> io::Result<()> {
+    imp::register_relock()
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+    use std::os::raw::c_void;
+    use std::slice;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::{REGISTRY, acquire, release};
+    use crate::zeroize::zeroize;
+
+    static WIPE: AtomicBool = AtomicBool::new(false);
+    static RELOCK: AtomicBool = AtomicBool::new(false);
+
+    /// Install the atfork handlers once; what the child handler does is chosen by flags.
+    fn install() -> io::Result<()> {
+        static INSTALLED: Mutex<bool> = Mutex::new(false);
+        let mut installed = INSTALLED.lock().unwrap_or_else(|e| e.into_inner());
+        if !*installed {
+            // Safety: the handlers only touch the registry and the regions it lists.
+            let rc = unsafe { libc::pthread_atfork(Some(prepare), Some(parent), Some(child)) };
+            if rc != 0 {
+                return Err(io::Error::from_raw_os_error(rc));
+            }
+            *installed = true;
+        }
+        Ok(())
+    }
+
+    pub(super) fn register_wipe() -> io::Result<()> {
+        install()?;
+        WIPE.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub(super) fn register_relock() -> io::Result<()> {
+        install()?;
+        RELOCK.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+
+    extern "C" fn prepare() {
+        acquire();
+    }
+
+    extern "C" fn parent() {
+        release();
+    }
+
+    /// Runs in the child, where only the forking thread exists; the registry is still
+    /// locked from `prepare`, so nothing here allocates or waits.
+    extern "C" fn child() {
+        // Safety: `prepare` holds the lock on behalf of this handler.
+        let regions = unsafe { &*REGISTRY.regions.get() };
+        if WIPE.load(Ordering::Relaxed) {
+            crate::pkey::with_all_keys(|| {
+                for region in regions {
+                    // Safety: tracked regions are whole pages that stay mapped until
+                    // untracked, but may have been made read-only or inaccessible with
+                    // `set_page_access`; make this process's copy writable again (the
+                    // parent's protection is its own), then wipe it.
+                    unsafe {
+                        libc::mprotect(
+                            region.addr as *mut c_void,
+                            region.len,
+                            libc::PROT_READ | libc::PROT_WRITE,
+                        );
+                        zeroize(slice::from_raw_parts_mut(
+                            region.addr as *mut u8,
+                            region.len,
+                        ));
+                    }
+                }
+            });
+        }
+        if RELOCK.load(Ordering::Relaxed) {
+            // The public wrappers explain failures by reading /proc, which allocates and
+            // could deadlock on a malloc lock held by a thread that did not survive
+            // `fork`; call the system directly instead.
+            for region in regions {
+                let addr = region.addr as *mut c_void;
+                // Safety: tracked regions stay mapped until untracked; failures are
+                // deliberately ignored (see `register_fork_relock`).
+                unsafe {
+                    // Zircon cannot lock, and the error would be allocated.
+                    #[cfg(not(target_os = "fuchsia"))]
+                    let _ = crate::sys::mlock(addr, region.len);
+                    #[cfg(not(any(target_os = "aix", target_os = "nto")))]
+                    if let Some(advice) = crate::unix::raw_advice(crate::Advice::DontDump) {
+                        libc::madvise(addr.cast(), region.len, advice);
+                    }
+                }
+            }
+        }
+        release();
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+
+    pub(super) fn register_wipe() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(super) fn register_relock() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "fork handlers unsupported on this platform",
+        )
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use crate::SecureBuf;
+
+    #[test]
+    fn forked_child_sees_wiped_secrets() {
+        super::register_fork_wipe().unwrap();
+        super::register_fork_wipe().unwrap();
+        let heap = SecureBuf::from_slice(b"heap-secret").unwrap();
+        let mut mapped = crate::secure_mmap(12).unwrap();
+        mapped.as_mut_slice().copy_from_slice(b"mmap-secret!");
+
+        // Safety: the child only reads memory and calls _exit.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let wiped = heap
+                .as_slice()
+                .iter()
+                .chain(mapped.as_slice())
+                .all(|&b| b == 0);
+            // Safety: async-signal-safe exit without running destructors.
+            unsafe { libc::_exit(if wiped { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        // Safety: waiting for the child just forked.
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+        assert_eq!(heap.as_slice(), b"heap-secret");
+        assert_eq!(mapped.as_slice(), b"mmap-secret!");
+    }
+
+    #[test]
+    fn forked_child_wipes_inaccessible_secrets() {
+        use crate::{PageAccess, set_page_access};
+
+        // Protection changes and the fork handler are process-wide.
+        if !crate::test_util::in_own_process("fork::tests::forked_child_wipes_inaccessible_secrets")
+        {
+            return;
+        }
+        super::register_fork_wipe().unwrap();
+        let mut buf = crate::secure_mmap(12).unwrap();
+        buf.as_mut_slice().copy_from_slice(b"gated-secret");
+        let (addr, len) = buf.region();
+        // Safety: the whole mapping owned by `buf`, untouched until it is made writable
+        // again below.
+        unsafe { set_page_access(addr, len, PageAccess::NoAccess).unwrap() };
+
+        // Safety: the child only reads memory and calls _exit.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let wiped = buf.as_slice().iter().all(|&b| b == 0);
+            // Safety: async-signal-safe exit without running destructors.
+            unsafe { libc::_exit(if wiped { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        // Safety: waiting for the child just forked.
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(
+            libc::WIFEXITED(status),
+            "child died with status {status:#x}"
+        );
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+
+        // Safety: as above; `buf` is read and dropped only after this.
+        unsafe { set_page_access(addr, len, PageAccess::ReadWrite).unwrap() };
+        assert_eq!(buf.as_slice(), b"gated-secret");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn forked_child_relocks_secrets() {
+        super::register_fork_relock().unwrap();
+        let _buf = SecureBuf::new(4096).unwrap();
+
+        // Safety: the child only makes syscalls and calls _exit.
+        let pid = unsafe { libc::fork() };
+        assert!(pid >= 0);
+        if pid == 0 {
+            let locked = locked_kib().is_some_and(|kib| kib >= 4);
+            // Safety: async-signal-safe exit without running destructors.
+            unsafe { libc::_exit(if locked { 0 } else { 1 }) };
+        }
+        let mut status = 0;
+        // Safety: waiting for the child just forked.
+        assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+        assert!(libc::WIFEXITED(status));
+        assert_eq!(libc::WEXITSTATUS(status), 0);
+    }
+
+    /// VmLck from /proc/self/status, read without allocating.
+    #[cfg(target_os = "linux")]
+    fn locked_kib() -> Option<u64> {
+        let mut buf = [0u8; 4096];
+        // Safety: a NUL-terminated path; `buf` is valid for its length.
+        let n = unsafe {
+            let fd = libc::open(c"/proc/self/status".as_ptr(), libc::O_RDONLY);
+            let n = libc::read(fd, buf.as_mut_ptr().cast(), buf.len());
+            libc::close(fd);
+            n
+        };
+        let status = std::str::from_utf8(&buf[..usize::try_from(n).ok()?]).ok()?;
+        let line = status.lines().find(|l| l.starts_with("VmLck:"))?;
+        line["VmLck:".len()..]
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse()
+            .ok()
+    }
+}
@@ -0,0 +1,300 @@
+//! Containers that encrypt their contents after a period without access.
+
+use std::fmt;
+use std::io;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::chacha::xor_keystream;
+use crate::{SecureBuf, SuspendHook, on_suspend};
+
+/// A locked secret that is encrypted in place once it has been idle for a while.
+///
+/// Behavior:
+/// - After `idle` elapses without access, a background thread (one per process, started
+///   on first use) encrypts the contents with ChaCha20 under a fresh random key. The key
+///   lives in its own locked allocation, so the plaintext is no longer in RAM as such.
+/// - The next [`IdleSealed::with_secret`] or [`IdleSealed::with_secret_mut`] call
+///   decrypts transparently and discards the key.
+/// - The contents stay in locked memory throughout and are zeroized on drop.
+///
+/// This raises the bar against memory scraping of long-lived keys; it does not protect
+/// against an attacker who can read all of the process's memory at once.
+#[cfg_attr(docsrs, doc(cfg(feature = "getrandom")))]
+pub struct IdleSealed {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    idle: Duration,
+    state: Mutex<State>,
+}
+
+struct State {
+    data: SecureBuf,
+    /// The ephemeral key while sealed, `None` while the contents are plaintext.
+    key: Option<SecureBuf>,
+    last_access: Instant,
+}
+
+impl State {
+    fn seal(&mut self) -> io::Result<()> {
+        if self.key.is_none() {
+            let key = SecureBuf::random(32)?;
+            apply(&key, &mut self.data);
+            self.key = Some(key);
+        }
+        Ok(())
+    }
+
+    /// Decrypt if sealed; returns whether anything changed.
+    fn unseal(&mut self) -> bool {
+        match self.key.take() {
+            Some(key) => {
+                apply(&key, &mut self.data);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+fn apply(key: &SecureBuf, data: &mut SecureBuf) {
+    let key: &[u8; 32] = key.as_slice().try_into().expect("32-byte sealing key");
+    // Every seal uses a fresh key, so a fixed nonce never repeats under one key.
+    xor_keystream(key, &[0; 12], 0, data.as_mut_slice());
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // A panic inside a caller's closure must not make the secret unreachable.
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl IdleSealed {
+    /// Take ownership of `secret` and seal it after `idle` without access.
+    ///
+    /// An `idle` too long to add to an [`Instant`] (e.g. `Duration::MAX`) means the
+    /// secret is only sealed explicitly, by [`IdleSealed::seal_now`] or on suspend.
+    ///
+    /// Returns an error if the background sealing thread could not be started.
+    pub fn new(secret: SecureBuf, idle: Duration) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            idle,
+            state: Mutex::new(State {
+                data: secret,
+                key: None,
+                last_access: Instant::now(),
+            }),
+        });
+        register(&shared)?;
+        Ok(Self { shared })
+    }
+
+    /// Run `f` over the plaintext, decrypting first if the secret was sealed.
+    ///
+    /// The idle timer restarts when `f` returns. `f` must not access this container again
+    /// (that would deadlock).
+    pub fn with_secret<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        self.access(|data| f(data.as_slice()))
+    }
+
+    /// Like [`IdleSealed::with_secret`], with mutable access to the plaintext.
+    pub fn with_secret_mut<R>(&self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        self.access(|data| f(data.as_mut_slice()))
+    }
+
+    fn access<R>(&self, f: impl FnOnce(&mut SecureBuf) -> R) -> R {
+        let mut state = lock(&self.shared.state);
+        let unsealed = state.unseal();
+        let result = f(&mut state.data);
+        state.last_access = Instant::now();
+        drop(state);
+        if unsealed {
+            // The sealer may be waiting with no deadline; give it this one.
+            let _registry = lock(&SEALER.registry);
+            SEALER.wake.notify_one();
+        }
+        result
+    }
+
+    /// Encrypt the contents now instead of waiting for the idle timeout.
+    pub fn seal_now(&self) -> io::Result<()> {
+        lock(&self.shared.state).seal()
+    }
+
+    /// Seal this secret whenever [`crate::prepare_for_suspend`] runs, e.g. before the
+    /// system hibernates.
+    ///
+    /// The hook holds only a weak reference, so it does not keep the secret alive.
+    pub fn seal_on_suspend(&self) -> SuspendHook {
+        let weak = Arc::downgrade(&self.shared);
+        on_suspend(move || {
+            if let Some(shared) = weak.upgrade()
+                && let Err(e) = lock(&shared.state).seal()
+            {
+                diag!("failed to seal secret before suspend: {e}");
+            }
+        })
+    }
+
+    /// Whether the contents are currently encrypted.
+    pub fn is_sealed(&self) -> bool {
+        lock(&self.shared.state).key.is_some()
+    }
+
+    /// Length of the secret in bytes.
+    pub fn len(&self) -> usize {
+        lock(&self.shared.state).data.len()
+    }
+
+    /// Whether the secret has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The configured idle timeout.
+    pub fn idle_timeout(&self) -> Duration {
+        self.shared.idle
+    }
+}
+
+impl fmt::Debug for IdleSealed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("IdleSealed")
+            .field("idle", &self.shared.idle)
+            .field("sealed", &self.is_sealed())
+            .finish_non_exhaustive()
+    }
+}
+
+struct Sealer {
+    registry: Mutex<Registry>,
+    wake: Condvar,
+}
+
+struct Registry {
+    entries: Vec<Weak<Shared>>,
+    started: bool,
+}
+
+static SEALER: Sealer = Sealer {
+    registry: Mutex::new(Registry {
+        entries: Vec::new(),
+        started: false,
+    }),
+    wake: Condvar::new(),
+};
+
+fn register(shared: &Arc<Shared>) -> io::Result<()> {
+    let mut registry = lock(&SEALER.registry);
+    if !registry.started {
+        thread::Builder::new()
+            .name("os-memlock-idle-sealer".into())
+            .spawn(run_sealer)?;
+        registry.started = true;
+    }
+    registry.entries.push(Arc::downgrade(shared));
+    SEALER.wake.notify_one();
+    Ok(())
+}
+
+fn run_sealer() {
+    let mut registry = lock(&SEALER.registry);
+    loop {
+        let now = Instant::now();
+        let mut next: Option<Instant> = None;
+        registry.entries.retain(|weak| {
+            let Some(shared) = weak.upgrade() else {
+                return false;
+            };
+            let mut state = lock(&shared.state);
+            if state.key.is_none() {
+                // A timeout too long for `Instant` to represent never expires.
+                let Some(mut deadline) = state.last_access.checked_add(shared.idle) else {
+                    return true;
+                };
+                if deadline <= now {
+                    match state.seal() {
+                        Ok(()) => return true,
+                        Err(e) => {
+                            // Avoid spinning on a persistent RNG failure; retry later.
+                            diag!("failed to seal idle secret: {e}");
+                            state.last_access = now;
+                            let Some(retry) = now.checked_add(shared.idle) else {
+                                return true;
+                            };
+                            deadline = retry;
+                        }
+                    }
+                }
+                next = Some(next.map_or(deadline, |n| n.min(deadline)));
+            }
+            true
+        });
+        registry = match next {
+            Some(deadline) => {
+                let timeout = deadline.saturating_duration_since(now);
+                SEALER
+                    .wake
+                    .wait_timeout(registry, timeout)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0
+            }
+            None => SEALER
+                .wake
+                .wait(registry)
+                .unwrap_or_else(PoisonError::into_inner),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use std::time::Duration;
+
+    use super::IdleSealed;
+    use crate::SecureBuf;
+
+    #[test]
+    fn seals_when_idle_and_unseals_on_access() {
+        let secret = SecureBuf::from_slice(b"master key").unwrap();
+        let sealed = IdleSealed::new(secret, Duration::from_millis(20)).unwrap();
+        assert!(sealed.with_secret(|s| s == b"master key"));
+
+        let start = std::time::Instant::now();
+        while !sealed.is_sealed() {
+            assert!(start.elapsed() < Duration::from_secs(5), "never sealed");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(sealed.with_secret(|s| s == b"master key"));
+        assert!(!sealed.is_sealed());
+
+        let _hook = sealed.seal_on_suspend();
+        crate::prepare_for_suspend();
+        assert!(sealed.is_sealed());
+        sealed.with_secret_mut(|s| s[0] = b'M');
+        assert!(sealed.with_secret(|s| s == b"Master key"));
+    }
+
+    #[test]
+    fn unrepresentable_idle_timeouts_never_seal() {
+        let forever =
+            IdleSealed::new(SecureBuf::from_slice(b"forever").unwrap(), Duration::MAX).unwrap();
+        // The shared sealer thread must survive it and keep sealing the others.
+        let soon = IdleSealed::new(
+            SecureBuf::from_slice(b"soon").unwrap(),
+            Duration::from_millis(20),
+        )
+        .unwrap();
+        let start = std::time::Instant::now();
+        while !soon.is_sealed() {
+            assert!(start.elapsed() < Duration::from_secs(5), "never sealed");
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!forever.is_sealed());
+        assert!(forever.with_secret(|s| s == b"forever"));
+    }
+}
@@ -0,0 +1,272 @@
+//! Secrets held in the Linux kernel keyring instead of process memory.
+
+use std::ffi::CString;
+use std::fmt;
+use std::io;
+use std::os::raw::{c_long, c_ulong};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::SecureBuf;
+
+/// A secret stored as a `user` key in this process's kernel keyring.
+///
+/// Behavior:
+/// - The payload lives in kernel memory, so it never appears in core dumps, swap, or
+///   `/proc/<pid>/mem`, and other processes (even of the same user) cannot read it
+///   through the process keyring.
+/// - It is copied into a locked [`SecureBuf`] only while in use, through
+///   [`KeyringSecret::with_secret`] or [`KeyringSecret::read`].
+/// - On drop the key is invalidated, which removes it from every keyring at once.
+///
+/// Payloads are limited to 1..=32767 bytes by the `user` key type and count against
+/// the per-user key quota (`/proc/sys/kernel/keys/maxbytes`).
+///
+/// Platform:
+/// - Linux. Container runtimes commonly block `add_key`/`keyctl` in their default
+///   seccomp profile, which surfaces as `PermissionDenied`.
+pub struct KeyringSecret {
+    id: i32,
+    len: usize,
+}
+
+impl KeyringSecret {
+    /// Store `payload` in the process keyring under `description`.
+    ///
+    /// The key is named `<description>:<n>`, with `n` unique within the process:
+    /// `add_key` updates an existing key of the same name in place, so secrets created
+    /// with one description would otherwise share a key and overwrite each other.
+    ///
+    /// The caller is responsible for zeroizing `payload` afterwards.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` once the kernel holds the key.
+    /// - `Err(InvalidInput)` if `description` contains a NUL byte.
+    /// - `Err(Unsupported)` if the kernel was built without keys (ENOSYS).
+    /// - `Err(...)` with the OS error otherwise (EINVAL for an empty or oversized
+    ///   payload, EDQUOT when the key quota is exhausted).
+    pub fn new(description: &str, payload: &[u8]) -> io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        if description.contains('\0') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "NUL in description",
+            ));
+        }
+        let n = NEXT.fetch_add(1, Ordering::Relaxed);
+        let description =
+            CString::new(format!("{description}:{n}")).expect("checked for NUL above");
+        // Safety: both strings are NUL-terminated and `payload` is valid for its length.
+        let id = cvt(unsafe {
+            libc::syscall(
+                libc::SYS_add_key,
+                c"user".as_ptr(),
+                description.as_ptr(),
+                payload.as_ptr(),
+                payload.len(),
+                c_long::from(libc::KEY_SPEC_PROCESS_KEYRING),
+            )
+        })?;
+        Ok(Self {
+            id: id as i32,
+            len: payload.len(),
+        })
+    }
+
+    /// Copy the payload into a new locked buffer.
+    ///
+    /// Returns `Err(...)` with the OS error if the key is no longer readable (e.g.
+    /// EKEYEXPIRED after [`KeyringSecret::set_timeout`]) or the buffer could not be
+    /// allocated or locked.
+    pub fn read(&self) -> io::Result<SecureBuf> {
+        let mut len = self.len;
+        loop {
+            let mut buf = SecureBuf::new(len)?;
+            let out = buf.as_mut_slice();
+            // Safety: `out` is valid for `len` writable bytes.
+            let full = cvt(unsafe { keyctl(libc::KEYCTL_READ, self.id, out.as_mut_ptr(), len) })?;
+            // The kernel reports the full size but copies at most `len` bytes.
+            if full as usize == len {
+                return Ok(buf);
+            }
+            len = full as usize;
+        }
+    }
+
+    /// Run `f` over the payload in a temporary locked buffer, zeroized afterwards.
+    ///
+    /// Returns:
+    /// - `Ok(R)` with the closure's result.
+    /// - `Err(...)` as for [`KeyringSecret::read`]; `f` is not called.
+    pub fn with_secret<R>(&self, f: impl FnOnce(&[u8]) -> R) -> io::Result<R> {
+        let buf = self.read()?;
+        Ok(f(buf.as_slice()))
+    }
+
+    /// Replace the payload in the kernel.
+    pub fn update(&mut self, payload: &[u8]) -> io::Result<()> {
+        // Safety: `payload` is valid for its length.
+        cvt(unsafe {
+            keyctl(
+                libc::KEYCTL_UPDATE,
+                self.id,
+                payload.as_ptr().cast_mut(),
+                payload.len(),
+            )
+        })?;
+        self.len = payload.len();
+        Ok(())
+    }
+
+    /// Have the kernel expire the key `secs` seconds from now (0 clears the timeout).
+    ///
+    /// Reads fail with EKEYEXPIRED afterwards, bounding how long the secret exists even
+    /// if the process never drops it.
+    pub fn set_timeout(&self, secs: u32) -> io::Result<()> {
+        let secs = c_ulong::from(secs);
+        // Safety: no pointer arguments.
+        cvt(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                c_long::from(libc::KEYCTL_SET_TIMEOUT),
+                c_long::from(self.id),
+                secs,
+            )
+        })?;
+        Ok(())
+    }
+
+    /// The key's serial number, as shown by `keyctl show`.
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// Payload length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the payload is empty; never true for a stored key.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for KeyringSecret {
+    fn drop(&mut self) {
+        // Safety: no pointer arguments.
+        let invalidated = cvt(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                c_long::from(libc::KEYCTL_INVALIDATE),
+                c_long::from(self.id),
+            )
+        });
+        // KEYCTL_INVALIDATE needs Linux 3.5; revoking still makes the key unreadable.
+        if invalidated.is_err() {
+            // Safety: as above.
+            let revoked = cvt(unsafe {
+                libc::syscall(
+                    libc::SYS_keyctl,
+                    c_long::from(libc::KEYCTL_REVOKE),
+                    c_long::from(self.id),
+                )
+            });
+            if let Err(e) = revoked {
+                diag!("failed to revoke keyring secret: {e}");
+            }
+        }
+    }
+}
+
+impl fmt::Debug for KeyringSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("KeyringSecret")
+            .field("id", &self.id)
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// keyctl(2) for the operations that take (key, buffer, length).
+unsafe fn keyctl(op: u32, id: i32, buf: *mut u8, len: usize) -> c_long {
+    // Safety: forwarded from the caller.
+    unsafe {
+        libc::syscall(
+            libc::SYS_keyctl,
+            c_long::from(op),
+            c_long::from(id),
+            buf,
+            len,
+        )
+    }
+}
+
+fn cvt(rc: c_long) -> io::Result<c_long> {
+    if rc >= 0 {
+        return Ok(rc);
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "kernel keyring unsupported by this kernel",
+        )),
+        _ => Err(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::KeyringSecret;
+
+    #[test]
+    fn round_trips_through_the_kernel() {
+        let mut secret = match KeyringSecret::new("os-memlock-test", b"db-password") {
+            Ok(secret) => secret,
+            // Blocked by seccomp in many containers, or compiled out of the kernel.
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::PermissionDenied | ErrorKind::Unsupported
+                ) =>
+            {
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        assert_eq!(secret.read().unwrap().as_slice(), b"db-password");
+        secret.update(b"rotated-db-password").unwrap();
+        let len = secret.with_secret(|bytes| {
+            assert_eq!(bytes, b"rotated-db-password");
+            bytes.len()
+        });
+        assert_eq!(len.unwrap(), 19);
+        assert!(!format!("{secret:?}").contains("password"));
+
+        let id = secret.id();
+        drop(secret);
+        let gone = KeyringSecret { id, len: 19 };
+        assert!(gone.read().is_err());
+        std::mem::forget(gone);
+    }
+
+    #[test]
+    fn same_description_keeps_keys_apart() {
+        let Ok(first) = KeyringSecret::new("os-memlock-shared", b"first") else {
+            return;
+        };
+        let second = KeyringSecret::new("os-memlock-shared", b"second").unwrap();
+        assert_ne!(first.id(), second.id());
+        drop(second);
+        assert_eq!(first.read().unwrap().as_slice(), b"first");
+    }
+
+    #[test]
+    fn rejects_nul_in_description() {
+        let err = KeyringSecret::new("a\0b", b"x").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -5,12 +5,191 @@
 
 use std::io;
 use std::os::raw::c_void;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod argv;
+mod capabilities;
+#[cfg(feature = "getrandom")]
+mod chacha;
+mod cursor;
+#[cfg(feature = "serde")]
+mod de;
+mod denial;
+#[macro_use]
+mod diagnostics;
+#[cfg(any(feature = "hex", feature = "base64"))]
+mod encoding;
+mod fork;
+#[cfg(feature = "getrandom")]
+mod idle;
+#[cfg(all(feature = "keyring", target_os = "linux"))]
+mod keyring;
+mod limits;
+mod locked_arc;
+mod locked_box;
+mod locked_heap;
+mod locked_lazy;
+mod locked_once;
+#[cfg(windows)]
+mod minidump;
+mod physical_buf;
+mod pkey;
+mod posture;
+mod prompt;
+mod protect_memory;
+#[cfg(feature = "getrandom")]
+mod random;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod scm;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sealed_memfd;
+mod secret_env;
+mod secret_file;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod secret_memfd;
+mod secure_buf;
+mod spill;
+#[cfg(feature = "getrandom")]
+mod split;
+mod support;
+mod suspend;
+#[cfg(unix)]
+mod sys;
+#[cfg(test)]
+mod test_util;
+mod thp;
+mod thread_stack;
+mod verify;
+mod zeroize;
+
+pub use argv::scrub_argv_secret;
+pub use capabilities::KernelCapabilities;
+pub use cursor::SecureCursor;
+#[cfg(feature = "serde")]
+pub use de::{LockedBytes, LockedString};
+pub use denial::{PolicyDenial, SecurityPolicy};
+pub use fork::{register_fork_relock, register_fork_wipe};
+#[cfg(feature = "getrandom")]
+pub use idle::IdleSealed;
+#[cfg(all(feature = "keyring", target_os = "linux"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "keyring", target_os = "linux"))))]
+pub use keyring::KeyringSecret;
+pub use limits::{
+    CgroupMemory, CgroupPressure, LockPrivileges, MemlockLimit, MemlockLimitSource,
+    WorkingSetLimits, cgroup_memory, lock_privileges, memlock_limit, memlock_limit_source,
+    raise_memlock_limit, working_set_limits,
+};
+pub use locked_arc::LockedArc;
+pub use locked_box::{LockedBox, LockedSecret};
+pub use locked_heap::{HeapSecret, LockedHeap};
+pub use locked_lazy::LockedLazy;
+pub use locked_once::LockedOnce;
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+pub use minidump::MiniDumpExclusions;
+pub use physical_buf::PhysicalBuf;
+pub use pkey::PkeyBuf;
+pub use posture::{
+    CoreDumpDestination, CoreDumpHandler, CoredumpFilter, PtraceProtection, core_dump_destination,
+    get_coredump_filter, ptrace_protection, set_coredump_filter,
+};
+pub use prompt::{MAX_PROMPT_SECRET_LEN, read_secret_from_stdin};
+pub use protect_memory::{
+    PROTECT_MEMORY_BLOCK_SIZE, ProtectScope, protect_memory, protected_len, unprotect_memory,
+};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub use sealed_memfd::SealedMemfd;
+pub use secret_env::secret_from_env;
+pub use secret_file::{MAX_SECRET_FILE_GROWTH, read_secret_file, read_secret_file_private};
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub use secret_memfd::SecretMemfd;
+pub use secure_buf::{
+    SecureBuf, secure_mmap, secure_mmap_hugepages, secure_mmap_on_node, with_locked_secret,
+};
+pub use spill::SpillableSecret;
+#[cfg(feature = "getrandom")]
+pub use split::SplitSecret;
+pub use support::{Capability, PlatformSupport, Support, platform_support};
+pub use suspend::{
+    SuspendHook, SuspendWatch, on_suspend, prepare_for_suspend, watch_system_suspend,
+};
+pub use thread_stack::{ThreadStackLock, lock_thread_stack};
+pub use verify::{
+    LockEffectiveness, ProcessLockStats, ResidencyReport, is_resident, locked_bytes,
+    process_lock_stats, verify_effective_lock, verify_region_locked,
+};
+
+/// Derive a locked, zeroize-on-drop container for a struct of key material.
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use os_memlock_derive::LockedSecret;
 
 #[inline]
 fn unsupported(msg: &'static str) -> io::Result<()> {
     Err(io::Error::new(io::ErrorKind::Unsupported, msg))
 }
 
+/// Size of a virtual memory page in bytes, queried once and cached.
+///
+/// The granularity of [`mlock`], [`madvise`] and page protections.
+///
+/// Platform:
+/// - Unix: `sysconf(_SC_PAGESIZE)`.
+/// - Windows: `dwPageSize` from `GetSystemInfo`.
+/// - Elsewhere, or if the query fails: 4096.
+pub fn page_size() -> usize {
+    static PAGE_SIZE: AtomicUsize = AtomicUsize::new(0);
+    let cached = PAGE_SIZE.load(Ordering::Relaxed);
+    if cached != 0 {
+        return cached;
+    }
+    #[cfg(unix)]
+    let size = match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        n if n > 0 => n as usize,
+        _ => 4096,
+    };
+    #[cfg(windows)]
+    let size = windows::system_info().page_size;
+    #[cfg(not(any(unix, windows)))]
+    let size = 4096;
+    PAGE_SIZE.store(size, Ordering::Relaxed);
+    size
+}
+
+/// Alignment of the addresses at which new mappings can start.
+///
+/// `VirtualAlloc` reserves address space in these units (64 KiB), so a region whose
+/// size is not a multiple of it leaves the rest of its last unit unusable. Unix maps at
+/// page granularity.
+///
+/// Platform:
+/// - Windows: `dwAllocationGranularity` from `GetSystemInfo`.
+/// - Elsewhere: [`page_size`].
+pub fn allocation_granularity() -> usize {
+    #[cfg(windows)]
+    return windows::system_info().allocation_granularity;
+    #[cfg(not(windows))]
+    page_size()
+}
+
+/// Size of a large page, if the platform has them.
+///
+/// Platform:
+/// - Windows: `GetLargePageMinimum` (usually 2 MiB); `None` if the processor has no
+///   large pages. Allocating them also needs `SeLockMemoryPrivilege`.
+/// - Linux/Android: the PMD-level transparent hugepage size (usually 2 MiB).
+/// - Elsewhere: `None`.
+pub fn large_page_size() -> Option<usize> {
+    #[cfg(windows)]
+    return windows::large_page_minimum();
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    return Some(thp::hugepage_size());
+    #[cfg(not(any(windows, target_os = "linux", target_os = "android")))]
+    None
+}
+
 #[cfg(unix)]
 mod unix {
     use super::{c_void, io};
@@ -54,12 +233,7 @@ mod unix {
         // Safety:
         // - We do not dereference addr.
         // - Caller guarantees (addr, len) is a valid region they own during the call.
-        let rc = unsafe { libc::mlock(addr, len) };
-        if rc == 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
-        }
+        unsafe { crate::sys::mlock(addr, len) }.map_err(|e| crate::denial::classify(e, "mlock"))
     }
 
     /// Unlock the pages containing the specified memory region.
@@ -79,68 +253,419 @@ mod unix {
             return Ok(());
         }
         // Safety: same preconditions as mlock.
-        let rc = unsafe { libc::munlock(addr, len) };
-        if rc == 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
-        }
+        unsafe { crate::sys::munlock(addr, len) }.map_err(|e| crate::denial::classify(e, "munlock"))
     }
 
-    /// Best-effort advisory to exclude the memory region from core dumps.
+    /// Apply `advice` to a memory region with madvise(2).
+    ///
+    /// `Advice` variants map to the platform's constant (e.g. `DontDump` is
+    /// MADV_DONTDUMP on Linux and MADV_NOCORE on FreeBSD and DragonFly).
     ///
-    /// On Linux, this wraps madvise(MADV_DONTDUMP). On FreeBSD, this wraps
-    /// madvise(MADV_NOCORE). On other Unix targets, this returns Unsupported.
+    /// Platform:
+    /// - AIX and QNX: routed through posix_madvise(3), since QNX has no madvise and
+    ///   AIX's takes a `caddr_t`. Only the POSIX advice (`Normal`, `Random`,
+    ///   `Sequential`, `WillNeed`, `DontNeed`) is available there.
+    /// - OpenBSD and NetBSD: `WipeOnFork` and `KeepOnFork` are routed through
+    ///   minherit(2) (`MAP_INHERIT_ZERO` and `MAP_INHERIT_COPY`), their fork-inheritance
+    ///   counterpart.
     ///
     /// Returns:
-    /// - Ok(()) when the hint is applied
-    /// - Err(...) with last_os_error() if the call failed
-    /// - Err(Unsupported) if not supported on this platform
+    /// - Ok(()) when the advice is applied (zero-length regions are a no-op)
+    /// - Err(...) with the OS error if the call failed
+    /// - Err(Unsupported) if this platform has no equivalent for `advice`
     ///
     /// # Safety
-    /// The caller must ensure that (addr, len) denotes a valid memory mapping for
-    /// this process and that the region is not deallocated or remapped concurrently.
-    #[cfg(target_os = "linux")]
-    pub unsafe fn madvise_dontdump(addr: *mut c_void, len: usize) -> io::Result<()> {
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    /// Destructive advice (`DontNeed`, `Free`) may discard the region's contents.
+    #[cfg(not(any(target_os = "aix", target_os = "nto")))]
+    pub unsafe fn madvise(addr: *mut c_void, len: usize, advice: super::Advice) -> io::Result<()> {
+        #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+        if let Some(inherit) = raw_inherit(advice) {
+            if len == 0 {
+                return Ok(());
+            }
+            // Safety: as for madvise below; minherit only changes the mapping's
+            // inheritance.
+            if unsafe { libc::minherit(addr, len, inherit) } != 0 {
+                let err = io::Error::last_os_error();
+                return Err(crate::denial::classify(err, "minherit"));
+            }
+            return Ok(());
+        }
+        if raw_advice(advice).is_none() {
+            return super::unsupported("madvise advice unsupported on this platform");
+        }
         if len == 0 {
             return Ok(());
         }
         // Safety:
         // - We do not dereference addr.
         // - Caller guarantees (addr, len) is a valid region they own during the call.
-        let rc = libc::madvise(addr, len, libc::MADV_DONTDUMP);
-        if rc == 0 {
-            Ok(())
+        unsafe { crate::sys::madvise(addr, len, advice) }
+            .map_err(|e| crate::denial::classify(e, "madvise"))
+    }
+
+    #[cfg(any(target_os = "aix", target_os = "nto"))]
+    pub unsafe fn madvise(addr: *mut c_void, len: usize, advice: super::Advice) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { posix_madvise(addr, len, advice) }
+    }
+
+    /// Apply `advice` to many regions with as few syscalls as possible.
+    ///
+    /// Platform:
+    /// - Linux 5.10+: one process_madvise(2) call per 1024 regions, on a pidfd for this
+    ///   process. Regions the kernel does not accept that way (before Linux 6.13 most
+    ///   advice is only valid through madvise) fall back to `madvise` one at a time.
+    /// - Other Unix targets: one `madvise` per region.
+    ///
+    /// Behavior:
+    /// - Every region is attempted, even after one fails.
+    ///
+    /// Returns:
+    /// - Ok(()) when the advice was applied to every region
+    /// - Err(...) with the first failing region's error otherwise
+    /// - Err(Unsupported) if this platform has no equivalent for `advice`
+    ///
+    /// # Safety
+    /// Every (addr, len) pair must satisfy the requirements of [`madvise`].
+    pub unsafe fn madvise_batch(
+        regions: &[(*mut c_void, usize)],
+        advice: super::Advice,
+    ) -> io::Result<()> {
+        #[cfg(target_os = "linux")]
+        let pidfd = if regions.len() > 1 {
+            batch::pidfd_self()
         } else {
-            Err(io::Error::last_os_error())
+            None
+        };
+        let mut result = Ok(());
+        for chunk in regions.chunks(1024) {
+            #[cfg(target_os = "linux")]
+            let done = match &pidfd {
+                Some(pidfd) => batch::advise_prefix(pidfd, chunk, advice),
+                None => 0,
+            };
+            #[cfg(not(target_os = "linux"))]
+            let done = 0;
+            for &(addr, len) in &chunk[done..] {
+                // Safety: forwarded from the caller.
+                if let Err(e) = unsafe { madvise(addr, len, advice) }
+                    && result.is_ok()
+                {
+                    result = Err(e);
+                }
+            }
         }
+        result
     }
 
-    /// FreeBSD: use MADV_NOCORE to request exclusion from core dumps.
-    #[cfg(target_os = "freebsd")]
-    /// # Safety
-    /// The caller must ensure that (addr, len) denotes a valid memory mapping for
-    /// this process and that the region is not deallocated or remapped concurrently.
-    pub unsafe fn madvise_dontdump(addr: *mut c_void, len: usize) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    pub(crate) mod batch {
+        use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+        use std::os::raw::{c_long, c_uint, c_void};
+
+        /// A pidfd for this process. Opened per batch rather than cached, since a cached
+        /// one would still name the parent after `fork`.
+        pub(crate) fn pidfd_self() -> Option<OwnedFd> {
+            let flags: c_uint = 0;
+            // Safety: pidfd_open takes no pointers.
+            let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, libc::getpid(), flags) };
+            // Safety: a new fd owned by nothing else.
+            (fd >= 0).then(|| unsafe { OwnedFd::from_raw_fd(fd as libc::c_int) })
+        }
+
+        /// Advise `chunk` in one process_madvise(2) call; returns how many leading
+        /// regions were fully advised (0 if the call failed outright).
+        pub(crate) fn advise_prefix(
+            pidfd: &OwnedFd,
+            chunk: &[(*mut c_void, usize)],
+            advice: crate::Advice,
+        ) -> usize {
+            let Some(raw) = super::raw_advice(advice) else {
+                return 0;
+            };
+            let iov: Vec<libc::iovec> = chunk
+                .iter()
+                .map(|&(iov_base, iov_len)| libc::iovec { iov_base, iov_len })
+                .collect();
+            let flags: c_uint = 0;
+            // Safety: `iov` is valid for `iov.len()` entries; the regions themselves are
+            // guaranteed by madvise_batch's caller and are not dereferenced here.
+            let rc = unsafe {
+                libc::syscall(
+                    libc::SYS_process_madvise,
+                    pidfd.as_raw_fd(),
+                    iov.as_ptr(),
+                    iov.len(),
+                    c_long::from(raw),
+                    flags,
+                )
+            };
+            if rc < 0 {
+                return 0;
+            }
+            // The kernel stops at the first region it cannot advise and reports the
+            // bytes done so far.
+            let mut advised = rc as usize;
+            chunk
+                .iter()
+                .take_while(|&&(_, len)| {
+                    let fits = len <= advised;
+                    advised -= if fits { len } else { 0 };
+                    fits
+                })
+                .count()
+        }
+    }
+
+    /// madvise fallback built on posix_madvise(3), limited to the POSIX advice values.
+    #[cfg(any(target_os = "aix", target_os = "nto", all(test, target_os = "linux")))]
+    pub(super) unsafe fn posix_madvise(
+        addr: *mut c_void,
+        len: usize,
+        advice: super::Advice,
+    ) -> io::Result<()> {
+        use super::Advice::*;
+        let raw = match advice {
+            Normal => libc::POSIX_MADV_NORMAL,
+            Random => libc::POSIX_MADV_RANDOM,
+            Sequential => libc::POSIX_MADV_SEQUENTIAL,
+            WillNeed => libc::POSIX_MADV_WILLNEED,
+            DontNeed => libc::POSIX_MADV_DONTNEED,
+            _ => return super::unsupported("madvise advice unsupported on this platform"),
+        };
         if len == 0 {
             return Ok(());
         }
-        let rc = libc::madvise(addr, len, libc::MADV_NOCORE);
+        // Safety: as for madvise; posix_madvise returns the error number instead of
+        // setting errno.
+        let rc = unsafe { libc::posix_madvise(addr, len, raw) };
         if rc == 0 {
             Ok(())
         } else {
-            Err(io::Error::last_os_error())
+            Err(io::Error::from_raw_os_error(rc))
+        }
+    }
+
+    #[cfg(not(any(target_os = "aix", target_os = "nto")))]
+    #[allow(unreachable_patterns)]
+    pub(crate) fn raw_advice(advice: super::Advice) -> Option<libc::c_int> {
+        use super::Advice::*;
+        Some(match advice {
+            Normal => libc::MADV_NORMAL,
+            Random => libc::MADV_RANDOM,
+            Sequential => libc::MADV_SEQUENTIAL,
+            WillNeed => libc::MADV_WILLNEED,
+            DontNeed => libc::MADV_DONTNEED,
+            #[cfg(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "netbsd",
+                target_os = "illumos",
+                target_os = "solaris",
+                target_vendor = "apple"
+            ))]
+            Free => libc::MADV_FREE,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            DontDump => libc::MADV_DONTDUMP,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            DoDump => libc::MADV_DODUMP,
+            #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+            DontDump => libc::MADV_NOCORE,
+            #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+            DoDump => libc::MADV_CORE,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            WipeOnFork => libc::MADV_WIPEONFORK,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            KeepOnFork => libc::MADV_KEEPONFORK,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            DontFork => libc::MADV_DONTFORK,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            DoFork => libc::MADV_DOFORK,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Hugepage => libc::MADV_HUGEPAGE,
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            NoHugepage => libc::MADV_NOHUGEPAGE,
+            _ => return None,
+        })
+    }
+
+    /// The minherit(2) inheritance standing in for fork-related `advice`.
+    #[cfg(any(target_os = "openbsd", target_os = "netbsd"))]
+    fn raw_inherit(advice: super::Advice) -> Option<libc::c_int> {
+        match advice {
+            super::Advice::WipeOnFork => Some(libc::MAP_INHERIT_ZERO),
+            super::Advice::KeepOnFork => Some(libc::MAP_INHERIT_COPY),
+            _ => None,
         }
     }
 
-    /// See madvise_dontdump above. On other Unix targets, this is unsupported.
-    #[cfg(not(any(target_os = "linux", target_os = "freebsd")))]
+    /// Best-effort advisory to exclude the memory region from core dumps.
+    ///
+    /// On Linux, this wraps madvise(MADV_DONTDUMP). On FreeBSD and DragonFly, this
+    /// wraps madvise(MADV_NOCORE). On other Unix targets, this returns Unsupported; NetBSD,
+    /// illumos, Solaris and AIX have no per-region advice, so use
+    /// [`disable_core_dumps_for_process`](crate::disable_core_dumps_for_process) or
+    /// coreadm(8) there.
+    /// Equivalent to `madvise(addr, len, Advice::DontDump)`.
+    ///
+    /// Returns:
+    /// - Ok(()) when the hint is applied
+    /// - Err(...) with last_os_error() if the call failed
+    /// - Err(Unsupported) if not supported on this platform
+    ///
     /// # Safety
-    /// This function is marked unsafe for signature consistency. On unsupported Unix
-    /// targets it always returns Unsupported; callers compiling cross-platform
-    /// must still treat (addr, len) as potentially unsafe inputs.
-    pub unsafe fn madvise_dontdump(_addr: *mut c_void, _len: usize) -> io::Result<()> {
-        super::unsupported("madvise-based dump exclusion unsupported on this platform")
+    /// The caller must ensure that (addr, len) denotes a valid memory mapping for
+    /// this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_dontdump(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::DontDump) }
+    }
+
+    /// Ask the kernel to zero the region in child processes after `fork`.
+    ///
+    /// On Linux, this wraps madvise(MADV_WIPEONFORK) (kernel 4.14+). The child sees
+    /// zero-filled pages in place of the parent's contents; the parent is unaffected.
+    /// Only private anonymous mappings qualify (heap and `mmap(MAP_ANONYMOUS)` memory).
+    /// On OpenBSD and NetBSD, this wraps minherit(MAP_INHERIT_ZERO), with the same
+    /// effect on whole pages. On other Unix targets, this returns Unsupported.
+    ///
+    /// Returns:
+    /// - Ok(()) when the advice is applied
+    /// - Err(...) with last_os_error() if the call failed (EINVAL on older kernels or
+    ///   file-backed/shared mappings)
+    /// - Err(Unsupported) if not supported on this platform
+    ///
+    /// # Safety
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_wipeonfork(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::WipeOnFork) }
+    }
+
+    /// Exclude the region from child address spaces created by `fork`.
+    ///
+    /// On Linux, this wraps madvise(MADV_DONTFORK). The pages are simply absent in the
+    /// child, so a child that touches them before `exec` faults; keep the region to
+    /// memory only the parent uses. Undo with `madvise_dofork`.
+    /// On other Unix targets, this returns Unsupported.
+    ///
+    /// Returns:
+    /// - Ok(()) when the advice is applied
+    /// - Err(...) with last_os_error() if the call failed
+    /// - Err(Unsupported) if not supported on this platform
+    ///
+    /// # Safety
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_dontfork(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::DontFork) }
+    }
+
+    /// Undo `madvise_dontfork`, so children inherit the region again.
+    ///
+    /// On Linux, this wraps madvise(MADV_DOFORK). On other Unix targets, this returns
+    /// Unsupported.
+    ///
+    /// # Safety
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_dofork(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::DoFork) }
+    }
+
+    /// Keep transparent hugepages out of the region.
+    ///
+    /// On Linux, this wraps madvise(MADV_NOHUGEPAGE). With THP set to `always`, a small
+    /// locked secret can otherwise be collapsed into a 2 MiB page, and the whole hugepage
+    /// counts against RLIMIT_MEMLOCK. On other Unix targets, this returns Unsupported.
+    ///
+    /// Returns:
+    /// - Ok(()) when the advice is applied
+    /// - Err(...) with last_os_error() if the call failed (EINVAL on kernels without THP)
+    /// - Err(Unsupported) if not supported on this platform
+    ///
+    /// # Safety
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_nohugepage(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::NoHugepage) }
+    }
+
+    /// Opt the region into transparent hugepages.
+    ///
+    /// On Linux, this wraps madvise(MADV_HUGEPAGE), which helps large locked arenas by
+    /// cutting TLB misses and page-table entries. THP must be `always` or `madvise` in
+    /// /sys/kernel/mm/transparent_hugepage/enabled. On other Unix targets, this returns
+    /// Unsupported.
+    ///
+    /// # Safety
+    /// The caller must ensure that (addr, len) denotes a valid, page-aligned memory mapping
+    /// for this process and that the region is not deallocated or remapped concurrently.
+    pub unsafe fn madvise_hugepage(addr: *mut c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        unsafe { madvise(addr, len, super::Advice::Hugepage) }
+    }
+
+    /// Lock all pages mapped into the process (and/or future mappings) in RAM.
+    ///
+    /// Returns:
+    /// - Ok(()) on success
+    /// - Err(...) with last_os_error() on failure (e.g. ENOMEM over RLIMIT_MEMLOCK, EPERM)
+    /// - Err(Unsupported) if `MclFlags::ONFAULT` is requested outside Linux/Android, and
+    ///   always on Haiku and Fuchsia
+    pub fn mlockall(flags: super::MclFlags) -> io::Result<()> {
+        // Haiku has no mlockall at all.
+        #[cfg(target_os = "haiku")]
+        {
+            let _ = flags;
+            super::unsupported("mlockall unsupported on Haiku")
+        }
+        #[cfg(not(target_os = "haiku"))]
+        {
+            #[cfg(not(target_os = "redox"))]
+            use libc::{MCL_CURRENT, MCL_FUTURE};
+            // relibc's values; the libc crate does not bind them for Redox.
+            #[cfg(target_os = "redox")]
+            const MCL_CURRENT: libc::c_int = 1;
+            #[cfg(target_os = "redox")]
+            const MCL_FUTURE: libc::c_int = 2;
+
+            let mut raw = 0;
+            if flags.contains(super::MclFlags::CURRENT) {
+                raw |= MCL_CURRENT;
+            }
+            if flags.contains(super::MclFlags::FUTURE) {
+                raw |= MCL_FUTURE;
+            }
+            if flags.contains(super::MclFlags::ONFAULT) {
+                #[cfg(any(target_os = "linux", target_os = "android"))]
+                {
+                    raw |= libc::MCL_ONFAULT;
+                }
+                #[cfg(not(any(target_os = "linux", target_os = "android")))]
+                return super::unsupported("MCL_ONFAULT unsupported on this platform");
+            }
+            crate::sys::mlockall(raw).map_err(|e| crate::denial::classify(e, "mlockall"))
+        }
+    }
+
+    /// Unlock every locked page of the process, including regions locked with `mlock`.
+    ///
+    /// Returns:
+    /// - Ok(()) on success
+    /// - Err(...) with last_os_error() on failure
+    /// - Err(Unsupported) on Haiku and Fuchsia
+    pub fn munlockall() -> io::Result<()> {
+        crate::sys::munlockall().map_err(|e| crate::denial::classify(e, "munlockall"))
     }
 }
 
@@ -148,6 +673,28 @@ mod unix {
 mod non_unix {
     use super::{c_void, io};
 
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise(
+        _addr: *mut c_void,
+        _len: usize,
+        _advice: super::Advice,
+    ) -> io::Result<()> {
+        super::unsupported("madvise unsupported on this platform")
+    }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported.
+    pub unsafe fn madvise_batch(
+        _regions: &[(*mut c_void, usize)],
+        _advice: super::Advice,
+    ) -> io::Result<()> {
+        super::unsupported("madvise unsupported on this platform")
+    }
+
     /// # Safety
     /// This function is marked unsafe for signature consistency across platforms.
     /// On non-Unix targets it always returns Unsupported; callers compiling
@@ -171,23 +718,83 @@ mod non_unix {
     pub unsafe fn madvise_dontdump(_addr: *mut c_void, _len: usize) -> io::Result<()> {
         super::unsupported("madvise(MADV_DONTDUMP) unsupported on this platform")
     }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise_wipeonfork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise(MADV_WIPEONFORK) unsupported on this platform")
+    }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise_dontfork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise(MADV_DONTFORK) unsupported on this platform")
+    }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise_dofork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise(MADV_DOFORK) unsupported on this platform")
+    }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise_nohugepage(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise(MADV_NOHUGEPAGE) unsupported on this platform")
+    }
+
+    /// # Safety
+    /// This function is marked unsafe for signature consistency across platforms.
+    /// On non-Unix targets it always returns Unsupported; callers compiling
+    /// cross-platform must still treat (addr, len) as potentially unsafe inputs.
+    pub unsafe fn madvise_hugepage(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise(MADV_HUGEPAGE) unsupported on this platform")
+    }
 }
 
-/// Disable core dumps for the current process on macOS by setting the RLIMIT_CORE soft limit to 0.
+/// Disable core dumps for the current process by setting the RLIMIT_CORE soft limit to 0.
 ///
 /// Platform:
-/// - macOS only. On other platforms, see the cross-platform stub which returns Unsupported.
+/// - macOS, NetBSD, DragonFly, illumos, Solaris and AIX. Except on DragonFly, none of
+///   them can exclude single regions from core dumps. On other platforms, see the cross-platform
+///   stub which returns Unsupported.
 ///
 /// Behavior:
 /// - This is a process-wide policy and is inherited by child processes.
 /// - Lowering the soft limit is typically permitted; raising it back may require extra privileges.
-/// - May fail in sandboxed or restricted environments; returns io::Error from the OS.
+/// - May fail in sandboxed or restricted environments; a refusal by the App Sandbox or
+///   the hardened runtime carries a [`PolicyDenial`] with guidance.
 ///
 /// Returns:
 /// - Ok(()) on success.
 /// - Err(io::Error) with last_os_error() on failure.
-#[cfg(target_os = "macos")]
-#[cfg_attr(docsrs, doc(cfg(target_os = "macos")))]
+#[cfg(any(
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "aix"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    )))
+)]
 pub fn disable_core_dumps_for_process() -> io::Result<()> {
     // Fetch existing limits so we can preserve the hard limit (rlim_max).
     let mut old = libc::rlimit {
@@ -205,7 +812,8 @@ pub fn disable_core_dumps_for_process() -> io::Result<()> {
     };
     let rc2 = unsafe { libc::setrlimit(libc::RLIMIT_CORE, &new_lim as *const _) };
     if rc2 != 0 {
-        return Err(io::Error::last_os_error());
+        // Lowering the soft limit is always allowed, so a refusal comes from a policy.
+        return Err(denial::classify(io::Error::last_os_error(), "setrlimit"));
     }
     Ok(())
 }
@@ -213,35 +821,68 @@ pub fn disable_core_dumps_for_process() -> io::Result<()> {
 /// Disable core dumps for the current process.
 ///
 /// Platform:
-/// - This stub is compiled on non-macOS targets and always returns Unsupported.
+/// - This stub is compiled on targets other than macOS, NetBSD, DragonFly, illumos,
+///   Solaris and AIX and always returns Unsupported.
 ///
 /// See also:
-/// - On macOS, disable_core_dumps_for_process attempts to set RLIMIT_CORE to 0.
-#[cfg(not(target_os = "macos"))]
-#[cfg_attr(docsrs, doc(cfg(not(target_os = "macos"))))]
+/// - On those targets, disable_core_dumps_for_process attempts to set RLIMIT_CORE to 0.
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "aix"
+)))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(not(any(
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    ))))
+)]
 pub fn disable_core_dumps_for_process() -> io::Result<()> {
     unsupported("disable_core_dumps_for_process unsupported on this platform")
 }
 
-/// RAII guard that disables core dumps on macOS and restores the previous RLIMIT_CORE on drop.
+/// RAII guard that disables core dumps on macOS, NetBSD, DragonFly, illumos, Solaris and
+/// AIX and restores the previous RLIMIT_CORE on drop.
 ///
-/// On non-macOS platforms, this type is still defined to keep cross-platform signatures
+/// On other platforms, this type is still defined to keep cross-platform signatures
 /// consistent, but creating it is not possible via this crate's API.
 #[derive(Debug)]
 pub struct CoreDumpsDisabledGuard {
-    #[cfg(target_os = "macos")]
+    #[cfg(any(
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    ))]
     old: libc::rlimit,
 }
 
-#[cfg(target_os = "macos")]
+#[cfg(any(
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "aix"
+))]
 impl Drop for CoreDumpsDisabledGuard {
     fn drop(&mut self) {
         // Best-effort: restore previous soft/hard core limits.
         let rc = unsafe { libc::setrlimit(libc::RLIMIT_CORE, &self.old as *const _) };
         if rc != 0 {
             // Avoid panicking in Drop; emit a diagnostic.
-            eprintln!(
-                "os-memlock: failed to restore RLIMIT_CORE: {}",
+            diag!(
+                "failed to restore RLIMIT_CORE: {}",
                 io::Error::last_os_error()
             );
         }
@@ -251,12 +892,30 @@ impl Drop for CoreDumpsDisabledGuard {
 /// Disable core dumps for the current process and return a guard that restores the previous limit on drop.
 ///
 /// Platform:
-/// - macOS only. On other platforms, this function returns Unsupported.
+/// - macOS, NetBSD, DragonFly, illumos, Solaris and AIX. On other platforms, this
+///   function returns Unsupported.
 ///
 /// Behavior:
 /// - Sets RLIMIT_CORE soft limit to 0; guard restores previous limit on Drop.
-#[cfg(target_os = "macos")]
-#[cfg_attr(docsrs, doc(cfg(target_os = "macos")))]
+#[cfg(any(
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "aix"
+))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    )))
+)]
 pub fn disable_core_dumps_with_guard() -> io::Result<CoreDumpsDisabledGuard> {
     let mut old = libc::rlimit {
         rlim_cur: 0,
@@ -272,13 +931,36 @@ pub fn disable_core_dumps_with_guard() -> io::Result<CoreDumpsDisabledGuard> {
     };
     let rc2 = unsafe { libc::setrlimit(libc::RLIMIT_CORE, &new_lim as *const _) };
     if rc2 != 0 {
-        return Err(io::Error::last_os_error());
+        // Lowering the soft limit is always allowed, so a refusal comes from a policy.
+        return Err(denial::classify(io::Error::last_os_error(), "setrlimit"));
     }
     Ok(CoreDumpsDisabledGuard { old })
 }
 
-#[cfg(not(target_os = "macos"))]
-#[cfg_attr(docsrs, doc(cfg(not(target_os = "macos"))))]
+/// Disable core dumps for the current process and return a restoring guard.
+///
+/// Platform:
+/// - This stub is compiled on targets other than macOS, NetBSD, DragonFly, illumos,
+///   Solaris and AIX and always returns Unsupported.
+#[cfg(not(any(
+    target_os = "macos",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "aix"
+)))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(not(any(
+        target_os = "macos",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    ))))
+)]
 pub fn disable_core_dumps_with_guard() -> io::Result<CoreDumpsDisabledGuard> {
     Err(io::Error::new(
         io::ErrorKind::Unsupported,
@@ -286,61 +968,682 @@ pub fn disable_core_dumps_with_guard() -> io::Result<CoreDumpsDisabledGuard> {
     ))
 }
 
-// Re-export platform module functions at the crate root for a stable API.
-#[cfg(unix)]
-#[cfg_attr(docsrs, doc(cfg(unix)))]
-pub use unix::{madvise_dontdump, mlock, munlock};
+/// Set the process's "dumpable" attribute with prctl(PR_SET_DUMPABLE).
+///
+/// Platform:
+/// - Linux and Android. On other platforms, see the stub which returns Unsupported.
+///
+/// Behavior:
+/// - `false` stops the kernel from writing core dumps and blocks ptrace attach (and
+///   reading /proc/<pid>/mem and friends) by unprivileged processes of the same user,
+///   covering more than an RLIMIT_CORE of 0 does.
+/// - The attribute is reset to the `fs.suid_dumpable` default on `execve` and when the
+///   process changes credentials, so set it after dropping privileges.
+///
+/// Returns:
+/// - Ok(()) on success.
+/// - Err(io::Error) with last_os_error() on failure.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub fn set_dumpable(dumpable: bool) -> io::Result<()> {
+    sys::set_dumpable(dumpable)
+}
 
-#[cfg(windows)]
-mod windows {
-    use super::{c_void, io};
-    use windows_sys::Win32::System::Memory::{VirtualLock, VirtualUnlock};
+/// Set the process's "dumpable" attribute.
+///
+/// Platform:
+/// - This stub is compiled on non-Linux targets and always returns Unsupported.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[cfg_attr(docsrs, doc(cfg(not(any(target_os = "linux", target_os = "android")))))]
+pub fn set_dumpable(_dumpable: bool) -> io::Result<()> {
+    unsupported("set_dumpable unsupported on this platform")
+}
 
-    /// Lock the pages containing the specified memory region to prevent paging on Windows.
-    ///
-    /// Returns:
-    /// - `Ok(())` on success
-    /// - `Err(...)` with `last_os_error()` on failure
-    ///
-    /// # Safety
-    /// The caller must ensure that `(addr, len)` refers to a valid, non-null memory
-    /// region owned by this process for the duration of the call, and that the region
-    /// is not deallocated, unmapped, or remapped concurrently.
-    pub unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
-        if len == 0 {
-            return Ok(());
-        }
-        let ok = unsafe { VirtualLock(addr as *mut _, len) };
-        if ok != 0 {
-            Ok(())
-        } else {
-            Err(io::Error::last_os_error())
+/// Query the process's "dumpable" attribute with prctl(PR_GET_DUMPABLE).
+///
+/// Platform:
+/// - Linux and Android. On other platforms, see the stub which returns Unsupported.
+///
+/// Returns:
+/// - Ok(false) if core dumps and unprivileged ptrace are disabled.
+/// - Ok(true) otherwise, including the root-only mode set by `fs.suid_dumpable = 2`.
+/// - Err(io::Error) with last_os_error() on failure.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub fn is_dumpable() -> io::Result<bool> {
+    sys::is_dumpable()
+}
+
+/// Query the process's "dumpable" attribute.
+///
+/// Platform:
+/// - This stub is compiled on non-Linux targets and always returns Unsupported.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[cfg_attr(docsrs, doc(cfg(not(any(target_os = "linux", target_os = "android")))))]
+pub fn is_dumpable() -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "is_dumpable unsupported on this platform",
+    ))
+}
+
+/// RAII guard that clears the dumpable attribute on Linux and restores it on drop.
+///
+/// On other platforms, this type is still defined to keep cross-platform signatures
+/// consistent, but creating it is not possible via this crate's API.
+#[derive(Debug)]
+pub struct DumpableGuard {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    was_dumpable: bool,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Drop for DumpableGuard {
+    fn drop(&mut self) {
+        // Best-effort: restore the previous attribute.
+        if let Err(e) = set_dumpable(self.was_dumpable) {
+            // Avoid panicking in Drop; emit a diagnostic.
+            diag!("failed to restore PR_SET_DUMPABLE: {e}");
         }
     }
+}
 
-    /// Unlock the pages containing the specified memory region on Windows.
-    ///
-    /// Returns:
-    /// - `Ok(())` on success
-    /// - `Err(...)` with `last_os_error()` on failure
-    ///
-    /// # Safety
-    /// The caller must ensure that `(addr, len)` refers to a valid, non-null memory
-    /// region owned by this process for the duration of the call, and that the region
-    /// is not deallocated, unmapped, or remapped concurrently.
-    pub unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
+/// Clear the dumpable attribute and return a guard that restores the previous value on drop.
+///
+/// Platform:
+/// - Linux and Android only. On other platforms, this function returns Unsupported.
+///
+/// Behavior:
+/// - Calls `set_dumpable(false)`; the guard restores the previous attribute on Drop.
+/// - PR_SET_DUMPABLE only accepts 0 and 1, so a previous root-only mode
+///   (`fs.suid_dumpable = 2`) is restored as 1.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub fn disable_dumpable_with_guard() -> io::Result<DumpableGuard> {
+    let was_dumpable = is_dumpable()?;
+    set_dumpable(false)?;
+    Ok(DumpableGuard { was_dumpable })
+}
+
+/// Clear the dumpable attribute and return a restoring guard.
+///
+/// Platform:
+/// - This stub is compiled on non-Linux targets and always returns Unsupported.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[cfg_attr(docsrs, doc(cfg(not(any(target_os = "linux", target_os = "android")))))]
+pub fn disable_dumpable_with_guard() -> io::Result<DumpableGuard> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "disable_dumpable_with_guard unsupported on this platform",
+    ))
+}
+
+/// Forbid tracing of the current process with procctl(PROC_TRACE_CTL,
+/// PROC_TRACE_CTL_DISABLE), the closest FreeBSD analog to clearing the dumpable
+/// attribute.
+///
+/// Platform:
+/// - FreeBSD only. On other platforms, see the stub which returns Unsupported.
+///
+/// Behavior:
+/// - Debuggers can no longer attach with ptrace(2), ktrace(2) is refused, and the kernel
+///   writes no core dump for the process, even for root.
+/// - Process-wide, inherited by children created with `fork` and kept across `execve`.
+///
+/// Returns:
+/// - Ok(()) on success.
+/// - Err(io::Error) with last_os_error() on failure, e.g. EBUSY if a debugger is
+///   attached already.
+#[cfg(target_os = "freebsd")]
+#[cfg_attr(docsrs, doc(cfg(target_os = "freebsd")))]
+pub fn disable_tracing() -> io::Result<()> {
+    let mut ctl = libc::PROC_TRACE_CTL_DISABLE;
+    // Safety: `ctl` is the int the command reads; the process id is our own.
+    let rc = unsafe {
+        libc::procctl(
+            libc::P_PID,
+            libc::getpid() as libc::id_t,
+            libc::PROC_TRACE_CTL,
+            (&mut ctl as *mut libc::c_int).cast(),
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Forbid tracing of the current process.
+///
+/// Platform:
+/// - This stub is compiled on non-FreeBSD targets and always returns Unsupported. On
+///   Linux, see [`set_dumpable`].
+#[cfg(not(target_os = "freebsd"))]
+#[cfg_attr(docsrs, doc(cfg(not(target_os = "freebsd"))))]
+pub fn disable_tracing() -> io::Result<()> {
+    unsupported("disable_tracing unsupported on this platform")
+}
+
+/// Seal a memory mapping with mseal(2), so it can no longer be unmapped, moved,
+/// resized, re-protected, or mapped over for the rest of the process's life.
+///
+/// Platform:
+/// - Linux 6.10+ on 64-bit targets. On other platforms, see the stub which returns
+///   Unsupported.
+///
+/// Behavior:
+/// - Stops a compromised component from later swapping a secret mapping for memory it
+///   controls, or re-protecting it to defeat guard pages. Destructive `madvise` on
+///   read-only private pages is refused as well.
+/// - Irreversible: the range stays mapped until the process exits or execs.
+///
+/// Returns:
+/// - Ok(()) on success; zero-length regions are a no-op.
+/// - Err(Unsupported) if the kernel predates mseal (ENOSYS), and on MIPS, alpha and
+///   x32, whose syscall numbers the crate does not carry.
+/// - Err(io::Error) with last_os_error() otherwise, e.g. EINVAL for an unaligned
+///   `addr` or a range with unmapped holes.
+///
+/// # Safety
+/// (addr, len) must be a page-aligned range of whole mappings owned by the caller that
+/// no code (including the allocator, so never heap memory) will need to unmap, remap,
+/// or mprotect afterwards.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[cfg_attr(docsrs, doc(cfg(any(target_os = "linux", target_os = "android"))))]
+pub unsafe fn mseal(addr: *mut c_void, len: usize) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    let Some(nr) = sys::SYS_MSEAL else {
+        return unsupported("mseal unsupported on this architecture");
+    };
+    let flags: libc::c_ulong = 0;
+    // Safety: forwarded from the caller; mseal does not dereference addr.
+    let rc = unsafe { libc::syscall(nr, addr, len, flags) };
+    if rc == 0 {
+        return Ok(());
+    }
+    let err = io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::ENOSYS) => unsupported("mseal unsupported by this kernel"),
+        _ => Err(err),
+    }
+}
+
+/// Seal a memory mapping.
+///
+/// Platform:
+/// - This stub is compiled on non-Linux targets and always returns Unsupported.
+///
+/// # Safety
+/// No-op stub; provided for API parity.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+#[cfg_attr(docsrs, doc(cfg(not(any(target_os = "linux", target_os = "android")))))]
+pub unsafe fn mseal(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+    unsupported("mseal unsupported on this platform")
+}
+
+/// Access allowed to a page range by [`set_page_access`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PageAccess {
+    /// Any access faults (`PROT_NONE` / `PAGE_NOACCESS`).
+    NoAccess,
+    /// Reads only (`PROT_READ` / `PAGE_READONLY`).
+    ReadOnly,
+    /// Reads and writes (`PROT_READ | PROT_WRITE` / `PAGE_READWRITE`).
+    ReadWrite,
+}
+
+/// Change the access allowed to the pages of (addr, len), to gate a secret between uses.
+///
+/// Keeping a locked secret at [`PageAccess::NoAccess`] while idle turns stray reads
+/// (e.g. an out-of-bounds read elsewhere in the process) into a crash instead of a leak.
+/// Locks survive protection changes on both platforms. The child handler of
+/// [`register_fork_wipe`] makes gated secret buffers writable again in forked children
+/// before wiping them.
+///
+/// Platform:
+/// - Unix: `mprotect(2)`.
+/// - Windows: `VirtualProtect`.
+/// - Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(()) on success; zero-length regions are a no-op.
+/// - Err(io::Error) with last_os_error() otherwise, e.g. EINVAL on Unix for an
+///   unaligned `addr`.
+///
+/// # Safety
+/// (addr, len) must be a page-aligned range of whole pages owned by the caller (never
+/// heap memory that shares pages with other allocations), and no code may access it in
+/// a way the new protection forbids.
+pub unsafe fn set_page_access(addr: *mut c_void, len: usize, access: PageAccess) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        let prot = match access {
+            PageAccess::NoAccess => libc::PROT_NONE,
+            PageAccess::ReadOnly => libc::PROT_READ,
+            PageAccess::ReadWrite => libc::PROT_READ | libc::PROT_WRITE,
+        };
+        // Safety: forwarded from the caller.
+        if unsafe { libc::mprotect(addr, len, prot) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::System::Memory::{
+            PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE, VirtualProtect,
+        };
+
+        let protect = match access {
+            PageAccess::NoAccess => PAGE_NOACCESS,
+            PageAccess::ReadOnly => PAGE_READONLY,
+            PageAccess::ReadWrite => PAGE_READWRITE,
+        };
+        let mut previous = 0;
+        // Safety: forwarded from the caller; `previous` is a valid out-pointer.
+        if unsafe { VirtualProtect(addr, len, protect, &mut previous) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(any(unix, windows)))]
+    {
+        let _ = (addr, access);
+        unsupported("set_page_access unsupported on this platform")
+    }
+}
+
+// Re-export platform module functions at the crate root for a stable API.
+#[cfg(unix)]
+#[cfg_attr(docsrs, doc(cfg(unix)))]
+pub use unix::{
+    madvise, madvise_batch, madvise_dofork, madvise_dontdump, madvise_dontfork, madvise_hugepage,
+    madvise_nohugepage, madvise_wipeonfork, mlock, mlockall, munlock, munlockall,
+};
+
+#[cfg(windows)]
+mod windows {
+    use super::{c_void, io};
+    use crate::limits::{WORKING_SET_OVERHEAD_PAGES, WorkingSetLimits};
+    use std::ptr;
+    use std::sync::OnceLock;
+    use windows_sys::Win32::Foundation::{
+        CloseHandle, ERROR_ACCESS_DENIED, ERROR_NOT_ALL_ASSIGNED, ERROR_NOT_LOCKED,
+        ERROR_WORKING_SET_QUOTA, GetLastError, HANDLE, LUID, RtlNtStatusToDosError,
+    };
+    use windows_sys::Win32::Security::{
+        AdjustTokenPrivileges, GetTokenInformation, LUID_AND_ATTRIBUTES, LookupPrivilegeValueW,
+        SE_LOCK_MEMORY_NAME, SE_PRIVILEGE_ENABLED, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
+        TOKEN_QUERY, TokenIsAppContainer,
+    };
+    use windows_sys::Win32::System::Diagnostics::Debug::{SetErrorMode, SetThreadErrorMode};
+    use windows_sys::Win32::System::ErrorReporting::{
+        WER_FAULT_REPORTING_DISABLE_SNAPSHOT_CRASH, WER_FAULT_REPORTING_FLAG_NOHEAP,
+        WER_FAULT_REPORTING_NO_UI, WerGetFlags, WerRegisterExcludedMemoryBlock, WerSetFlags,
+        WerUnregisterExcludedMemoryBlock,
+    };
+    use windows_sys::Win32::System::Memory::{
+        GetLargePageMinimum, GetProcessWorkingSetSizeEx, QUOTA_LIMITS_HARDWS_MIN_ENABLE,
+        SetProcessWorkingSetSizeEx, VirtualLock, VirtualUnlock,
+    };
+    use windows_sys::Win32::System::SystemInformation::{GetSystemInfo, SYSTEM_INFO};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    /// Lock the pages containing the specified memory region to prevent paging on Windows.
+    ///
+    /// Returns:
+    /// - `Ok(())` on success
+    /// - `Err(OutOfMemory)` if the lock would exceed the process's minimum working set
+    ///   (`ERROR_WORKING_SET_QUOTA`), the Windows counterpart of `RLIMIT_MEMLOCK`
+    /// - `Err(...)` with `last_os_error()` on other failures
+    ///
+    /// # Safety
+    /// The caller must ensure that `(addr, len)` refers to a valid, non-null memory
+    /// region owned by this process for the duration of the call, and that the region
+    /// is not deallocated, unmapped, or remapped concurrently.
+    pub unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        if len == 0 {
+            return Ok(());
+        }
+        let ok = unsafe { VirtualLock(addr as *mut _, len) };
+        if ok != 0 { Ok(()) } else { Err(lock_error()) }
+    }
+
+    /// Unlock the pages containing the specified memory region on Windows.
+    ///
+    /// Returns:
+    /// - `Ok(())` on success
+    /// - `Err(InvalidInput)` carrying [`NotLocked`](crate::NotLocked) if the pages were
+    ///   not locked (`ERROR_NOT_LOCKED`); the pages are removed from the working set anyway
+    /// - `Err(...)` with `last_os_error()` on other failures
+    ///
+    /// # Safety
+    /// The caller must ensure that `(addr, len)` refers to a valid, non-null memory
+    /// region owned by this process for the duration of the call, and that the region
+    /// is not deallocated, unmapped, or remapped concurrently.
+    pub unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
         if len == 0 {
             return Ok(());
         }
         let ok = unsafe { VirtualUnlock(addr as *mut _, len) };
-        if ok != 0 {
-            Ok(())
+        if ok != 0 { Ok(()) } else { Err(lock_error()) }
+    }
+
+    /// The `GetSystemInfo` values the crate needs.
+    pub(crate) struct SystemInfo {
+        pub(crate) page_size: usize,
+        pub(crate) allocation_granularity: usize,
+    }
+
+    /// `GetSystemInfo`, queried once and cached.
+    pub(crate) fn system_info() -> &'static SystemInfo {
+        static INFO: OnceLock<SystemInfo> = OnceLock::new();
+        INFO.get_or_init(|| {
+            let mut info = SYSTEM_INFO::default();
+            // Safety: `info` is a valid out-pointer; GetSystemInfo cannot fail.
+            unsafe { GetSystemInfo(&mut info) };
+            let page_size = match info.dwPageSize as usize {
+                0 => 4096,
+                n => n,
+            };
+            SystemInfo {
+                page_size,
+                allocation_granularity: (info.dwAllocationGranularity as usize).max(page_size),
+            }
+        })
+    }
+
+    /// `GetLargePageMinimum`, or `None` if the processor has no large pages.
+    pub(crate) fn large_page_minimum() -> Option<usize> {
+        // Safety: no arguments.
+        match unsafe { GetLargePageMinimum() } {
+            0 => None,
+            n => Some(n),
+        }
+    }
+
+    pub(crate) fn working_set() -> io::Result<WorkingSetLimits> {
+        let mut ws = WorkingSetLimits {
+            minimum: 0,
+            maximum: 0,
+            flags: 0,
+        };
+        // Safety: the current-process pseudo handle and valid out-pointers.
+        let ok = unsafe {
+            GetProcessWorkingSetSizeEx(
+                GetCurrentProcess(),
+                &mut ws.minimum,
+                &mut ws.maximum,
+                &mut ws.flags,
+            )
+        };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(ws)
+    }
+
+    fn set_working_set(min: usize, max: usize, flags: u32) -> io::Result<()> {
+        // Safety: the current-process pseudo handle; sizes are plain values.
+        let ok = unsafe { SetProcessWorkingSetSizeEx(GetCurrentProcess(), min, max, flags) };
+        if ok == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// `VirtualLock`, raising the minimum (and maximum) working set by the range's size
+    /// and retrying once on `ERROR_WORKING_SET_QUOTA`.
+    ///
+    /// Returns the sizes in effect before growing, if the working set was grown.
+    ///
+    /// # Safety
+    /// As for [`mlock`].
+    pub(crate) unsafe fn lock_growing(
+        addr: *const c_void,
+        len: usize,
+        hard_min: bool,
+    ) -> io::Result<Option<WorkingSetLimits>> {
+        if len == 0 {
+            return Ok(None);
+        }
+        // Safety: forwarded from the caller.
+        if unsafe { VirtualLock(addr as *mut _, len) } != 0 {
+            return Ok(None);
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(ERROR_WORKING_SET_QUOTA as i32) {
+            return Err(map_lock_error(err));
+        }
+        let previous = working_set()?;
+        let page = crate::page_size();
+        let span = (addr as usize % page)
+            .checked_add(len)
+            .and_then(|end| end.checked_next_multiple_of(page))
+            .and_then(|span| span.checked_add(WORKING_SET_OVERHEAD_PAGES * page))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+        let min = previous.minimum.saturating_add(span);
+        let max = previous.maximum.saturating_add(span);
+        let flags = if hard_min {
+            QUOTA_LIMITS_HARDWS_MIN_ENABLE
         } else {
+            0
+        };
+        set_working_set(min, max, flags)?;
+        // Safety: forwarded from the caller.
+        if unsafe { VirtualLock(addr as *mut _, len) } == 0 {
+            let err = lock_error();
+            let _ = restore_working_set(&previous);
+            return Err(err);
+        }
+        Ok(Some(previous))
+    }
+
+    /// Put back working-set sizes returned by [`lock_growing`].
+    pub(crate) fn restore_working_set(previous: &WorkingSetLimits) -> io::Result<()> {
+        set_working_set(previous.minimum, previous.maximum, previous.flags)
+    }
+
+    // Native API without a windows-sys binding; ntdll is mapped into every process.
+    #[link(name = "ntdll")]
+    unsafe extern "system" {
+        fn NtLockVirtualMemory(
+            process: HANDLE,
+            base: *mut *mut c_void,
+            size: *mut usize,
+            map_type: u32,
+        ) -> i32;
+        fn NtUnlockVirtualMemory(
+            process: HANDLE,
+            base: *mut *mut c_void,
+            size: *mut usize,
+            map_type: u32,
+        ) -> i32;
+    }
+
+    /// `NtLockVirtualMemory` map type locking pages in physical memory, independent of
+    /// the working set. (`MAP_PROCESS`, 1, is what `VirtualLock` uses.)
+    const MAP_SYSTEM: u32 = 2;
+
+    /// Lock (`unlock == false`) or unlock (addr, len) with `MAP_SYSTEM`.
+    ///
+    /// # Safety
+    /// As for [`mlock`].
+    pub(crate) unsafe fn lock_system(
+        addr: *const c_void,
+        len: usize,
+        unlock: bool,
+    ) -> io::Result<()> {
+        let mut base = addr as *mut c_void;
+        let mut size = len;
+        let call = if unlock {
+            NtUnlockVirtualMemory
+        } else {
+            NtLockVirtualMemory
+        };
+        // Safety: forwarded from the caller; base and size are valid in/out pointers.
+        let status = unsafe { call(GetCurrentProcess(), &mut base, &mut size, MAP_SYSTEM) };
+        // Informational statuses such as STATUS_WAS_LOCKED are non-negative.
+        if status < 0 {
+            // Safety: pure conversion of a status code.
+            let code = unsafe { RtlNtStatusToDosError(status) };
+            return Err(io::Error::from_raw_os_error(code as i32));
+        }
+        Ok(())
+    }
+
+    /// Enable `SeLockMemoryPrivilege` in the process token, which large pages, AWE
+    /// physical pages and `MAP_SYSTEM` locks need. It must already be granted ("Lock pages in memory"); this
+    /// only turns it on.
+    pub(crate) fn enable_lock_memory_privilege() -> io::Result<()> {
+        let mut token: HANDLE = ptr::null_mut();
+        // Safety: the current-process pseudo handle and a valid out-pointer.
+        if unsafe {
+            OpenProcessToken(
+                GetCurrentProcess(),
+                TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+                &mut token,
+            )
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        let mut luid = LUID {
+            LowPart: 0,
+            HighPart: 0,
+        };
+        // Safety: a NUL-terminated privilege name and a valid out-pointer.
+        let result = if unsafe {
+            LookupPrivilegeValueW(ptr::null(), SE_LOCK_MEMORY_NAME, &mut luid)
+        } == 0
+        {
             Err(io::Error::last_os_error())
+        } else {
+            let privileges = TOKEN_PRIVILEGES {
+                PrivilegeCount: 1,
+                Privileges: [LUID_AND_ATTRIBUTES {
+                    Luid: luid,
+                    Attributes: SE_PRIVILEGE_ENABLED,
+                }],
+            };
+            // Safety: `token` was opened with TOKEN_ADJUST_PRIVILEGES; the previous
+            // state is not requested.
+            let ok = unsafe {
+                AdjustTokenPrivileges(token, 0, &privileges, 0, ptr::null_mut(), ptr::null_mut())
+            };
+            // AdjustTokenPrivileges succeeds even when the privilege is not held.
+            // Safety: reads the calling thread's last-error value.
+            match (ok, unsafe { GetLastError() }) {
+                (0, _) => Err(io::Error::last_os_error()),
+                (_, ERROR_NOT_ALL_ASSIGNED) => Err(io::Error::new(
+                    io::ErrorKind::PermissionDenied,
+                    "large and physical pages need SeLockMemoryPrivilege; grant the account the \
+                     \"Lock pages in memory\" user right and log in again",
+                )),
+                _ => Ok(()),
+            }
+        };
+        // Safety: the token handle opened above.
+        unsafe { CloseHandle(token) };
+        result
+    }
+
+    /// Whether the process runs in an AppContainer (a UWP or packaged-app sandbox),
+    /// queried once and cached.
+    pub(crate) fn is_app_container() -> bool {
+        static APP_CONTAINER: OnceLock<bool> = OnceLock::new();
+        *APP_CONTAINER.get_or_init(|| {
+            let mut token: HANDLE = ptr::null_mut();
+            // Safety: the current-process pseudo handle and a valid out-pointer.
+            if unsafe { OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) } == 0 {
+                return false;
+            }
+            let mut value = 0u32;
+            let mut returned = 0;
+            // Safety: `value` has room for the DWORD TokenIsAppContainer returns.
+            let ok = unsafe {
+                GetTokenInformation(
+                    token,
+                    TokenIsAppContainer,
+                    (&mut value as *mut u32).cast(),
+                    size_of::<u32>() as u32,
+                    &mut returned,
+                )
+            };
+            // Safety: the token handle opened above.
+            unsafe { CloseHandle(token) };
+            ok != 0 && value != 0
+        })
+    }
+
+    /// The error for `call`, which the AppContainer sandbox does not allow.
+    fn restricted(call: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "{call} is restricted for processes in an AppContainer (UWP or packaged app); \
+                 run outside the sandbox or skip this call"
+            ),
+        )
+    }
+
+    /// `err` from `call`, or [`restricted`] when it is the sandbox's ACCESS_DENIED.
+    fn app_container_denial(err: io::Error, call: &str) -> io::Error {
+        if err.raw_os_error() == Some(ERROR_ACCESS_DENIED as i32) && is_app_container() {
+            return restricted(call);
+        }
+        err
+    }
+
+    /// The `GetLastError` of a failed `VirtualLock`/`VirtualUnlock`, with the codes that
+    /// std leaves uncategorized given a kind and a hint.
+    fn lock_error() -> io::Error {
+        map_lock_error(io::Error::last_os_error())
+    }
+
+    fn map_lock_error(err: io::Error) -> io::Error {
+        let err = app_container_denial(err, "Locking memory");
+        match err.raw_os_error().map(|code| code as u32) {
+            Some(ERROR_WORKING_SET_QUOTA) => io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                "VirtualLock exceeded the process's minimum working set size \
+                 (ERROR_WORKING_SET_QUOTA); raise it with SetProcessWorkingSetSize",
+            ),
+            Some(ERROR_NOT_LOCKED) => io::Error::new(io::ErrorKind::InvalidInput, crate::NotLocked),
+            _ => err,
         }
     }
 
-    /// Windows has no per-region equivalent of `MADV_DONTDUMP`; return Unsupported.
+    /// Windows has no `madvise`; every advice returns Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_batch(
+        _regions: &[(*mut c_void, usize)],
+        _advice: super::Advice,
+    ) -> io::Result<()> {
+        super::unsupported("madvise unsupported on Windows")
+    }
+
+    /// Windows has no `madvise`; every advice returns Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise(
+        _addr: *mut c_void,
+        _len: usize,
+        _advice: super::Advice,
+    ) -> io::Result<()> {
+        super::unsupported("madvise unsupported on Windows")
+    }
+
+    /// Windows has no `madvise`; use [`crate::exclude_from_dumps`], which registers the
+    /// region with Windows Error Reporting instead. Returns Unsupported.
     ///
     /// # Safety
     /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
@@ -348,6 +1651,125 @@ mod windows {
         super::unsupported("madvise_dontdump unsupported on Windows")
     }
 
+    /// Windows has no `fork`, so there is nothing to wipe; return Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_wipeonfork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise_wipeonfork unsupported on Windows")
+    }
+
+    /// Windows has no `fork`; return Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_dontfork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise_dontfork unsupported on Windows")
+    }
+
+    /// Windows has no `fork`; return Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_dofork(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise_dofork unsupported on Windows")
+    }
+
+    /// Windows large pages are opt-in at allocation time (`MEM_LARGE_PAGES`); return
+    /// Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_nohugepage(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise_nohugepage unsupported on Windows")
+    }
+
+    /// Windows large pages are opt-in at allocation time (`MEM_LARGE_PAGES`); return
+    /// Unsupported.
+    ///
+    /// # Safety
+    /// Signature kept for cross-platform parity; always returns Unsupported on Windows.
+    pub unsafe fn madvise_hugepage(_addr: *mut c_void, _len: usize) -> io::Result<()> {
+        super::unsupported("madvise_hugepage unsupported on Windows")
+    }
+
+    /// Register (addr, len) with `WerRegisterExcludedMemoryBlock`.
+    ///
+    /// # Safety
+    /// See [`crate::exclude_from_dumps`].
+    pub(crate) unsafe fn wer_exclude(addr: *mut c_void, len: usize) -> io::Result<()> {
+        let size = u32::try_from(len).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "WER excludes blocks of at most 4 GiB",
+            )
+        })?;
+        // Safety: WER only records the range; it is read (and skipped) at dump time.
+        hresult(unsafe { WerRegisterExcludedMemoryBlock(addr, size) })
+    }
+
+    /// Remove a registration made by [`wer_exclude`].
+    ///
+    /// # Safety
+    /// See [`crate::include_in_dumps`].
+    pub(crate) unsafe fn wer_include(addr: *mut c_void) -> io::Result<()> {
+        // Safety: only looks up and removes the registration starting at `addr`.
+        hresult(unsafe { WerUnregisterExcludedMemoryBlock(addr) })
+    }
+
+    /// Process settings replaced by [`disable_wer_dumps`].
+    #[derive(Debug)]
+    pub(crate) struct WerState {
+        error_mode: u32,
+        flags: u32,
+    }
+
+    /// Keep Windows Error Reporting from collecting crash dumps of this process.
+    pub(crate) fn disable_wer_dumps() -> io::Result<WerState> {
+        // The error mode below cannot be set in an AppContainer; refuse before touching
+        // the WER flags, so a failure leaves the process as it was.
+        if is_app_container() {
+            return Err(restricted("SetErrorMode"));
+        }
+        let mut flags = 0;
+        // WerGetFlags fails when no flags were ever set, which leaves `flags` at 0.
+        // Safety: the current-process pseudo handle and a valid out-pointer.
+        let _ = unsafe { WerGetFlags(GetCurrentProcess(), &mut flags) };
+        let quiet = WER_FAULT_REPORTING_FLAG_NOHEAP
+            | WER_FAULT_REPORTING_NO_UI
+            | WER_FAULT_REPORTING_DISABLE_SNAPSHOT_CRASH;
+        // Safety: plain call with a flag argument.
+        hresult(unsafe { WerSetFlags(flags | quiet) })?;
+        // Without a GP fault box, unhandled exceptions never reach WER at all.
+        // Safety: plain call with a flag argument; it only reports the previous mode.
+        let error_mode = unsafe { SetErrorMode(SEM_NOGPFAULTERRORBOX) };
+        // Safety: as above; merges the flags that were already set back in.
+        unsafe { SetErrorMode(error_mode | SEM_NOGPFAULTERRORBOX) };
+        Ok(WerState { error_mode, flags })
+    }
+
+    /// Put back the settings [`disable_wer_dumps`] replaced.
+    pub(crate) fn restore_wer_dumps(state: &WerState) -> io::Result<()> {
+        // Safety: plain calls with flag arguments.
+        unsafe { SetErrorMode(state.error_mode) };
+        hresult(unsafe { WerSetFlags(state.flags) })
+    }
+
+    /// An HRESULT as an `io::Error`, unwrapping Win32 error codes (facility 7).
+    fn hresult(hr: i32) -> io::Result<()> {
+        if hr >= 0 {
+            return Ok(());
+        }
+        let hr = hr as u32;
+        if hr & 0xFFFF_0000 == 0x8007_0000 {
+            let err = io::Error::from_raw_os_error((hr & 0xFFFF) as i32);
+            return Err(app_container_denial(err, "Windows Error Reporting"));
+        }
+        Err(io::Error::other(format!(
+            "WER call failed with HRESULT 0x{hr:08x}"
+        )))
+    }
+
     // ------------------------------------------------------------------------
     // Windows process-level error mode helpers
     // ------------------------------------------------------------------------
@@ -368,10 +1790,6 @@ mod windows {
     /// See module-level docs for details.
     pub const SEM_NOOPENFILEERRORBOX: u32 = 0x8000;
 
-    unsafe extern "system" {
-        fn SetErrorMode(uMode: u32) -> u32;
-    }
-
     /// Set the Windows process error mode and return the previous mode.
     ///
     /// Platform:
@@ -383,7 +1801,11 @@ mod windows {
     ///
     /// Returns:
     /// - `Ok(previous_mode)` on success.
+    /// - `Err(Unsupported)` in an AppContainer, where `SetErrorMode` is not available.
     pub fn set_windows_error_mode(new_mode: u32) -> io::Result<u32> {
+        if is_app_container() {
+            return Err(restricted("SetErrorMode"));
+        }
         // SetErrorMode returns the previous mode; there is no explicit failure indicator.
         let previous = unsafe { SetErrorMode(new_mode) };
         Ok(previous)
@@ -391,28 +1813,80 @@ mod windows {
 
     /// Best-effort helper to suppress common Windows error dialogs for the current process.
     ///
-    /// Sets a combination of `SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX | SEM_NOOPENFILEERRORBOX`
-    /// via `SetErrorMode` and returns the previous mode so callers can restore it later.
+    /// Adds `SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX | SEM_NOOPENFILEERRORBOX` to the
+    /// current mode via `SetErrorMode`, keeping flags already set (e.g.
+    /// `SEM_NOALIGNMENTFAULTEXCEPT`, which cannot be cleared again), and returns the
+    /// previous mode so callers can restore it later.
     ///
     /// Returns:
     /// - `Ok(previous_mode)` on success.
+    /// - `Err(Unsupported)` in an AppContainer, where `SetErrorMode` is not available.
     ///
     /// Notes:
     /// - Process-wide effect; inherited by child processes at `CreateProcess`.
     /// - This does not influence what data is captured in crash dumps and is not a substitute
-    ///   for per-region dump exclusion (which Windows does not provide).
+    ///   for per-region dump exclusion (see [`crate::exclude_from_dumps`]).
     pub fn suppress_windows_error_dialogs_for_process() -> io::Result<u32> {
+        if is_app_container() {
+            return Err(restricted("SetErrorMode"));
+        }
         let desired = SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX | SEM_NOOPENFILEERRORBOX;
+        // SetErrorMode replaces the whole mode and only reports the old one, so set ours,
+        // then merge the old flags back in.
         let previous = unsafe { SetErrorMode(desired) };
+        unsafe { SetErrorMode(previous | desired) };
+        Ok(previous)
+    }
+
+    /// Set the calling thread's error mode and return its previous mode.
+    ///
+    /// Platform:
+    /// - Windows 7 and later. On non-Windows targets, use the cross-platform stub which returns `Unsupported`.
+    ///
+    /// Effect:
+    /// - Affects only the calling thread, which overrides the process mode for it; other
+    ///   threads and child processes are untouched.
+    ///
+    /// Returns:
+    /// - `Ok(previous_mode)` on success.
+    /// - `Err(...)` with `last_os_error()` if the mode has flags other than
+    ///   `SEM_FAILCRITICALERRORS`, `SEM_NOGPFAULTERRORBOX` and `SEM_NOOPENFILEERRORBOX`.
+    pub fn set_windows_thread_error_mode(new_mode: u32) -> io::Result<u32> {
+        let mut previous = 0;
+        // Safety: `previous` is a valid out-pointer for the duration of the call.
+        if unsafe { SetThreadErrorMode(new_mode, &mut previous) } == 0 {
+            return Err(app_container_denial(
+                io::Error::last_os_error(),
+                "SetThreadErrorMode",
+            ));
+        }
         Ok(previous)
     }
+
+    /// Suppress common Windows error dialogs for the calling thread only.
+    ///
+    /// Sets `SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX | SEM_NOOPENFILEERRORBOX` via
+    /// `SetThreadErrorMode` and returns the thread's previous mode, to be restored with
+    /// [`set_windows_thread_error_mode`]. Meant for libraries, which must not change
+    /// process-wide state owned by the host application.
+    ///
+    /// Returns:
+    /// - `Ok(previous_mode)` on success.
+    /// - `Err(...)` with `last_os_error()` on failure.
+    pub fn suppress_windows_error_dialogs_for_thread() -> io::Result<u32> {
+        set_windows_thread_error_mode(
+            SEM_FAILCRITICALERRORS | SEM_NOGPFAULTERRORBOX | SEM_NOOPENFILEERRORBOX,
+        )
+    }
 }
 
 #[cfg(windows)]
 #[cfg_attr(docsrs, doc(cfg(windows)))]
 pub use windows::{
-    SEM_FAILCRITICALERRORS, SEM_NOGPFAULTERRORBOX, SEM_NOOPENFILEERRORBOX, madvise_dontdump, mlock,
-    munlock, set_windows_error_mode, suppress_windows_error_dialogs_for_process,
+    SEM_FAILCRITICALERRORS, SEM_NOGPFAULTERRORBOX, SEM_NOOPENFILEERRORBOX, madvise, madvise_batch,
+    madvise_dofork, madvise_dontdump, madvise_dontfork, madvise_hugepage, madvise_nohugepage,
+    madvise_wipeonfork, mlock, munlock, set_windows_error_mode, set_windows_thread_error_mode,
+    suppress_windows_error_dialogs_for_process, suppress_windows_error_dialogs_for_thread,
 };
 
 #[cfg(not(windows))]
@@ -437,15 +1911,984 @@ pub fn suppress_windows_error_dialogs_for_process() -> io::Result<u32> {
     ))
 }
 
-#[cfg(all(not(unix), not(windows)))]
-#[cfg_attr(docsrs, doc(cfg(all(not(unix), not(windows)))))]
-pub use non_unix::{madvise_dontdump, mlock, munlock};
+#[cfg(not(windows))]
+/// Set the calling thread's Windows error mode (stub).
+///
+/// This stub is compiled on non-Windows targets and always returns `Unsupported`.
+pub fn set_windows_thread_error_mode(_new_mode: u32) -> io::Result<u32> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "set_windows_thread_error_mode unsupported on this platform",
+    ))
+}
 
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn smoke_disable_core_dumps_for_process() {
-        let _ = crate::disable_core_dumps_for_process();
-        let _ = crate::disable_core_dumps_with_guard();
+#[cfg(not(windows))]
+/// Suppress common Windows error dialogs for the calling thread (stub).
+///
+/// This stub is compiled on non-Windows targets and always returns `Unsupported`.
+pub fn suppress_windows_error_dialogs_for_thread() -> io::Result<u32> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "suppress_windows_error_dialogs_for_thread unsupported on this platform",
+    ))
+}
+
+/// Keep (addr, len) out of crash dumps: `madvise(MADV_DONTDUMP)` on Unix,
+/// `WerRegisterExcludedMemoryBlock` on Windows.
+///
+/// Platform:
+/// - Unix: same as [`madvise_dontdump`] (Linux, Android, FreeBSD, DragonFly; elsewhere
+///   Unsupported). OpenBSD can only exclude memory when it is mapped: use
+///   [`secure_mmap`], whose mappings are `MAP_CONCEAL`. NetBSD, illumos, Solaris and
+///   AIX cannot exclude single regions; [`disable_core_dumps_for_process`] is the fallback
+///   there.
+/// - Windows 10 and later: the region is left out of dumps collected by Windows Error
+///   Reporting (including LocalDumps). `len` must fit in 32 bits; dumps written by other
+///   tools (e.g. `MiniDumpWriteDump`) are unaffected, see `MiniDumpExclusions`.
+///
+/// Returns:
+/// - `Ok(())` on success
+/// - `Err(...)` with the OS error on failure, or `Unsupported` on other platforms
+///
+/// # Safety
+/// `(addr, len)` must lie within memory owned by this process. On Windows, the
+/// registration outlives the memory: call [`include_in_dumps`] with the same `addr`
+/// before freeing it. [`SecureBuf`] does so on drop.
+pub unsafe fn exclude_from_dumps(addr: *mut c_void, len: usize) -> io::Result<()> {
+    #[cfg(windows)]
+    // Safety: forwarded from the caller.
+    return unsafe { windows::wer_exclude(addr, len) };
+    #[cfg(not(windows))]
+    // Safety: forwarded from the caller.
+    unsafe {
+        madvise_dontdump(addr, len)
+    }
+}
+
+/// Undo [`exclude_from_dumps`]: `madvise(MADV_DODUMP)` on Unix,
+/// `WerUnregisterExcludedMemoryBlock` on Windows (where `len` is ignored; the
+/// registration starting at `addr` is removed).
+///
+/// Returns:
+/// - `Ok(())` on success
+/// - `Err(...)` with the OS error on failure, or `Unsupported` on other platforms
+///
+/// # Safety
+/// `(addr, len)` must lie within memory owned by this process.
+pub unsafe fn include_in_dumps(addr: *mut c_void, len: usize) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        let _ = len;
+        // Safety: forwarded from the caller.
+        unsafe { windows::wer_include(addr) }
+    }
+    #[cfg(not(windows))]
+    // Safety: forwarded from the caller.
+    unsafe {
+        madvise(addr, len, Advice::DoDump)
+    }
+}
+
+/// Lock (addr, len) like [`mlock`], growing the Windows working set when it is too small.
+///
+/// Platform:
+/// - Windows: when `VirtualLock` fails with `ERROR_WORKING_SET_QUOTA` (the default
+///   minimum working set allows only a few hundred KiB to be locked), raises the process's
+///   minimum and maximum working set by the range's size plus a small margin with
+///   `SetProcessWorkingSetSizeEx`, then retries once. With `hard_min`, the new minimum is
+///   also made a hard guarantee (`QUOTA_LIMITS_HARDWS_MIN_ENABLE`).
+/// - Elsewhere: the same as [`mlock`]; `hard_min` is ignored.
+///
+/// The raised sizes are process-wide and kept after the region is unlocked; use
+/// [`mlock_grow_working_set_with_guard`] to put them back.
+///
+/// Returns:
+/// - `Ok(())` once the range is locked
+/// - `Err(...)` with the OS error if growing the working set or the retry failed; a
+///   failed retry restores the previous sizes
+///
+/// # Safety
+/// As for [`mlock`].
+pub unsafe fn mlock_grow_working_set(
+    addr: *const c_void,
+    len: usize,
+    hard_min: bool,
+) -> io::Result<()> {
+    #[cfg(windows)]
+    // Safety: forwarded from the caller.
+    return unsafe { windows::lock_growing(addr, len, hard_min).map(drop) };
+    #[cfg(not(windows))]
+    {
+        let _ = hard_min;
+        // Safety: forwarded from the caller.
+        unsafe { mlock(addr, len) }
+    }
+}
+
+/// The error carried by [`munlock`] when the pages were not locked.
+///
+/// Windows' `VirtualUnlock` fails with `ERROR_NOT_LOCKED` on such pages (and still
+/// removes them from the working set), where Unix `munlock` simply succeeds. The
+/// `io::Error` has kind `InvalidInput`; cleanup code shared across platforms can look for
+/// this value and treat the range as already unlocked:
+///
+/// ```no_run
+/// # fn demo(ptr: *const std::os::raw::c_void, len: usize) -> std::io::Result<()> {
+/// match unsafe { os_memlock::munlock(ptr, len) } {
+///     Err(e) if os_memlock::NotLocked::find(&e).is_some() => {}
+///     other => other?,
+/// }
+/// # Ok(()) }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct NotLocked;
+
+impl NotLocked {
+    /// The marker carried by `err`, if it reports pages that were not locked.
+    pub fn find(err: &io::Error) -> Option<&Self> {
+        err.get_ref()?.downcast_ref()
+    }
+}
+
+impl std::fmt::Display for NotLocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("VirtualUnlock on pages that are not locked (ERROR_NOT_LOCKED)")
+    }
+}
+
+impl std::error::Error for NotLocked {}
+
+/// How [`lock_physical`] pinned a range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PhysicalLock {
+    /// Locked in physical memory with `NtLockVirtualMemory(MAP_SYSTEM)`: the pages stay
+    /// resident even if the working set is trimmed or the process is swapped out.
+    System,
+    /// The fallback, `VirtualLock` (`MAP_PROCESS`): the pages stay resident only as part
+    /// of the process's working set.
+    WorkingSet,
+}
+
+/// Whether [`lock_physical`] can use `MAP_SYSTEM` locks, i.e. whether the process holds
+/// `SeLockMemoryPrivilege` ("Lock pages in memory"). Enables the privilege if so.
+///
+/// Platform:
+/// - Windows. Elsewhere, always `false`.
+pub fn can_lock_physical() -> bool {
+    #[cfg(windows)]
+    return windows::enable_lock_memory_privilege().is_ok();
+    #[cfg(not(windows))]
+    false
+}
+
+/// Lock (addr, len) in physical memory, more strongly than [`mlock`], for services
+/// running with `SeLockMemoryPrivilege`.
+///
+/// `VirtualLock` is `NtLockVirtualMemory` with `MAP_PROCESS`: it only keeps pages
+/// resident while they are in the working set, and the whole process can still be
+/// swapped out. With the privilege, this uses `MAP_SYSTEM` instead, which pins the
+/// pages in physical memory. Without it, falls back to `VirtualLock`; the result says
+/// which one applies. Check up front with [`can_lock_physical`].
+///
+/// Platform:
+/// - Windows only. On other platforms, returns Unsupported; [`mlock`] already pins pages
+///   in RAM there.
+///
+/// Returns:
+/// - `Ok(kind)` once the range is locked; pass `kind` to [`unlock_physical`]
+/// - `Err(...)` with the OS error if locking failed
+///
+/// # Safety
+/// As for [`mlock`].
+pub unsafe fn lock_physical(addr: *const c_void, len: usize) -> io::Result<PhysicalLock> {
+    #[cfg(windows)]
+    {
+        if len == 0 {
+            return Ok(PhysicalLock::WorkingSet);
+        }
+        if can_lock_physical() {
+            // Safety: forwarded from the caller.
+            unsafe { windows::lock_system(addr, len, false)? };
+            return Ok(PhysicalLock::System);
+        }
+        // Safety: forwarded from the caller.
+        unsafe { mlock(addr, len)? };
+        Ok(PhysicalLock::WorkingSet)
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (addr, len);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "lock_physical unsupported on this platform",
+        ))
+    }
+}
+
+/// Unlock a range locked by [`lock_physical`], which returned `lock`.
+///
+/// Platform:
+/// - Windows only. On other platforms, returns Unsupported.
+///
+/// # Safety
+/// As for [`munlock`].
+pub unsafe fn unlock_physical(
+    addr: *const c_void,
+    len: usize,
+    lock: PhysicalLock,
+) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        if len == 0 {
+            return Ok(());
+        }
+        match lock {
+            // Safety: forwarded from the caller.
+            PhysicalLock::System => unsafe { windows::lock_system(addr, len, true) },
+            // Safety: forwarded from the caller.
+            PhysicalLock::WorkingSet => unsafe { munlock(addr, len) },
+        }
+    }
+    #[cfg(not(windows))]
+    {
+        let _ = (addr, len, lock);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unlock_physical unsupported on this platform",
+        ))
+    }
+}
+
+/// RAII guard that restores the working-set sizes in effect before
+/// [`mlock_grow_working_set_with_guard`] raised them.
+///
+/// Drop it after unlocking the region: shrinking the minimum working set below what is
+/// still locked leaves later locks failing. When the working set did not need growing
+/// (and on non-Windows platforms), dropping it does nothing.
+#[derive(Debug)]
+#[must_use = "dropping the guard restores the working-set sizes immediately"]
+pub struct WorkingSetGuard {
+    previous: Option<WorkingSetLimits>,
+}
+
+impl WorkingSetGuard {
+    /// The sizes before the working set was grown, restored on drop; `None` if it was
+    /// not grown.
+    pub fn previous_limits(&self) -> Option<WorkingSetLimits> {
+        self.previous
+    }
+}
+
+#[cfg(windows)]
+impl Drop for WorkingSetGuard {
+    fn drop(&mut self) {
+        if let Some(previous) = &self.previous
+            && let Err(e) = windows::restore_working_set(previous)
+        {
+            // Avoid panicking in Drop; emit a diagnostic.
+            diag!("failed to restore working-set sizes: {e}");
+        }
+    }
+}
+
+/// Lock (addr, len) as [`mlock_grow_working_set`] does and return a guard that restores
+/// the previous working-set sizes on drop, so a library does not permanently raise its
+/// host's working-set floor.
+///
+/// Returns:
+/// - `Ok(guard)` once the range is locked
+/// - `Err(...)` as for [`mlock_grow_working_set`]
+///
+/// # Safety
+/// As for [`mlock`].
+pub unsafe fn mlock_grow_working_set_with_guard(
+    addr: *const c_void,
+    len: usize,
+    hard_min: bool,
+) -> io::Result<WorkingSetGuard> {
+    #[cfg(windows)]
+    // Safety: forwarded from the caller.
+    let previous = unsafe { windows::lock_growing(addr, len, hard_min)? };
+    #[cfg(not(windows))]
+    let previous = {
+        let _ = hard_min;
+        // Safety: forwarded from the caller.
+        unsafe { mlock(addr, len)? };
+        None
+    };
+    Ok(WorkingSetGuard { previous })
+}
+
+/// Outcome of [`mlock_chunked`]: how much of the range, from its start, is locked.
+#[derive(Debug)]
+#[must_use = "the locked prefix must be unlocked with munlock"]
+pub struct ChunkedLock {
+    locked: usize,
+    len: usize,
+    error: Option<io::Error>,
+}
+
+impl ChunkedLock {
+    /// Bytes locked from the start of the range; unlock them with
+    /// `munlock(addr, locked_len())`.
+    pub fn locked_len(&self) -> usize {
+        self.locked
+    }
+
+    /// Whether the whole range is locked.
+    pub fn is_complete(&self) -> bool {
+        self.locked == self.len
+    }
+
+    /// The failure that stopped locking early, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+}
+
+/// Lock (addr, len) with one [`mlock`] call per `chunk` bytes instead of one call for
+/// the whole range.
+///
+/// A single `VirtualLock` over a huge region fails outright once the region no longer
+/// fits the working-set quota. Locking in page-aligned chunks finds out how much does
+/// fit, and keeps each call small enough for the memory manager to satisfy.
+///
+/// Behavior:
+/// - Chunks are `chunk` bytes rounded up to whole pages, and end on page boundaries, so
+///   no page is locked twice.
+/// - When a chunk fails and `allow_partial` is false, the chunks already locked are
+///   unlocked again and the error is returned.
+/// - With `allow_partial`, locking stops at the failing chunk and the result reports the
+///   locked prefix and the error; see [`ChunkedLock::is_complete`].
+///
+/// Returns:
+/// - `Ok(lock)` with the whole range locked, or with a locked prefix if `allow_partial`
+/// - `Err(InvalidInput)` if `chunk` is 0
+/// - `Err(...)` from [`mlock`] if the first chunk failed, or any chunk without
+///   `allow_partial`
+///
+/// # Safety
+/// As for [`mlock`].
+pub unsafe fn mlock_chunked(
+    addr: *const c_void,
+    len: usize,
+    chunk: usize,
+    allow_partial: bool,
+) -> io::Result<ChunkedLock> {
+    let page = page_size();
+    let step = chunk
+        .checked_next_multiple_of(page)
+        .filter(|&step| step != 0)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "chunk must be non-zero"))?;
+    let start = addr as usize;
+    let mut locked = 0;
+    while locked < len {
+        let from = start + locked;
+        let to = (from.saturating_add(step) / page * page).min(start + len);
+        // Safety: a subrange of the caller's range.
+        if let Err(e) = unsafe { mlock(from as *const c_void, to - from) } {
+            if locked == 0 {
+                return Err(e);
+            }
+            if !allow_partial {
+                // Safety: exactly the prefix locked above.
+                let _ = unsafe { munlock(addr, locked) };
+                return Err(e);
+            }
+            return Ok(ChunkedLock {
+                locked,
+                len,
+                error: Some(e),
+            });
+        }
+        locked = to - start;
+    }
+    Ok(ChunkedLock {
+        locked,
+        len,
+        error: None,
+    })
+}
+
+/// Keep Windows Error Reporting from collecting crash dumps of this process; the Windows
+/// counterpart of [`disable_core_dumps_for_process`].
+///
+/// Platform:
+/// - Windows only. On other platforms, this function returns Unsupported.
+///
+/// Behavior:
+/// - Adds `SEM_NOGPFAULTERRORBOX` to the process error mode, so unhandled exceptions are
+///   not handed to WER, which therefore writes neither a report nor a LocalDumps dump.
+/// - Sets `WER_FAULT_REPORTING_FLAG_NOHEAP`, `WER_FAULT_REPORTING_NO_UI` and
+///   `WER_FAULT_REPORTING_DISABLE_SNAPSHOT_CRASH` with `WerSetFlags`, so a report that is
+///   still produced (e.g. after other code resets the error mode) carries no heap.
+/// - Dumps taken by an attached debugger or `MiniDumpWriteDump` are unaffected; use
+///   [`exclude_from_dumps`] for the regions that matter.
+/// - Process-wide; the error mode is inherited by child processes.
+pub fn disable_wer_dumps_for_process() -> io::Result<()> {
+    #[cfg(windows)]
+    return windows::disable_wer_dumps().map(drop);
+    #[cfg(not(windows))]
+    unsupported("disable_wer_dumps_for_process unsupported on this platform")
+}
+
+/// RAII guard that disables WER crash dumps and restores the previous error mode and WER
+/// flags on drop.
+///
+/// On non-Windows platforms, this type is still defined to keep cross-platform signatures
+/// consistent, but creating it is not possible via this crate's API.
+#[derive(Debug)]
+pub struct WerDumpsDisabledGuard {
+    #[cfg(windows)]
+    previous: windows::WerState,
+}
+
+#[cfg(windows)]
+impl Drop for WerDumpsDisabledGuard {
+    fn drop(&mut self) {
+        if let Err(e) = windows::restore_wer_dumps(&self.previous) {
+            // Avoid panicking in Drop; emit a diagnostic.
+            diag!("failed to restore WER flags: {e}");
+        }
+    }
+}
+
+/// Disable WER crash dumps as [`disable_wer_dumps_for_process`] does and return a guard
+/// that restores the previous settings on drop.
+///
+/// Platform:
+/// - Windows only. On other platforms, this function returns Unsupported.
+pub fn disable_wer_dumps_with_guard() -> io::Result<WerDumpsDisabledGuard> {
+    #[cfg(windows)]
+    return Ok(WerDumpsDisabledGuard {
+        previous: windows::disable_wer_dumps()?,
+    });
+    #[cfg(not(windows))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "disable_wer_dumps_with_guard unsupported on this platform",
+    ))
+}
+
+/// RAII guard that suppresses Windows error dialogs and restores the previous process
+/// error mode on drop.
+///
+/// On non-Windows platforms, this type is still defined to keep cross-platform signatures
+/// consistent, but creating it is not possible via this crate's API.
+#[derive(Debug)]
+pub struct WindowsErrorModeGuard {
+    #[cfg(windows)]
+    previous: u32,
+}
+
+impl WindowsErrorModeGuard {
+    /// The error mode in effect before the guard was created, restored on drop.
+    #[cfg(windows)]
+    #[cfg_attr(docsrs, doc(cfg(windows)))]
+    pub fn previous_mode(&self) -> u32 {
+        self.previous
+    }
+}
+
+#[cfg(windows)]
+impl Drop for WindowsErrorModeGuard {
+    fn drop(&mut self) {
+        // SetErrorMode cannot fail; this only returns Ok.
+        let _ = windows::set_windows_error_mode(self.previous);
+    }
+}
+
+/// Suppress common Windows error dialogs and return a guard that restores the previous
+/// error mode on drop.
+///
+/// Platform:
+/// - Windows only. On other platforms, this function returns Unsupported.
+///
+/// Behavior:
+/// - Same flags as [`suppress_windows_error_dialogs_for_process`]; the guard restores the
+///   mode it replaced even on panic or early return.
+/// - The mode is process-wide: guards must be dropped in reverse order of creation, and
+///   changes made by other code while a guard is alive are undone by its drop.
+#[cfg(windows)]
+#[cfg_attr(docsrs, doc(cfg(windows)))]
+pub fn suppress_windows_error_dialogs_with_guard() -> io::Result<WindowsErrorModeGuard> {
+    let previous = windows::suppress_windows_error_dialogs_for_process()?;
+    Ok(WindowsErrorModeGuard { previous })
+}
+
+/// Suppress common Windows error dialogs and return a restoring guard.
+///
+/// Platform:
+/// - This stub is compiled on non-Windows targets and always returns Unsupported.
+#[cfg(not(windows))]
+pub fn suppress_windows_error_dialogs_with_guard() -> io::Result<WindowsErrorModeGuard> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "suppress_windows_error_dialogs_with_guard unsupported on this platform",
+    ))
+}
+
+#[cfg(all(not(unix), not(windows)))]
+#[cfg_attr(docsrs, doc(cfg(all(not(unix), not(windows)))))]
+pub use non_unix::{
+    madvise, madvise_batch, madvise_dofork, madvise_dontdump, madvise_dontfork, madvise_hugepage,
+    madvise_nohugepage, madvise_wipeonfork, mlock, munlock,
+};
+
+/// Memory-usage advice for [`madvise`].
+///
+/// Each variant maps to the platform's constant; advice without an equivalent on the
+/// current platform makes [`madvise`] return `Unsupported`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Advice {
+    /// No special treatment (`MADV_NORMAL`).
+    Normal,
+    /// Expect random access (`MADV_RANDOM`).
+    Random,
+    /// Expect sequential access (`MADV_SEQUENTIAL`).
+    Sequential,
+    /// Expect access soon (`MADV_WILLNEED`).
+    WillNeed,
+    /// Contents may be discarded; private anonymous pages read back as zero on Linux
+    /// (`MADV_DONTNEED`).
+    DontNeed,
+    /// Contents may be freed lazily under memory pressure (`MADV_FREE`; Linux, Android,
+    /// FreeBSD, DragonFly, NetBSD, Apple, illumos, Solaris).
+    Free,
+    /// Exclude from core dumps (Linux `MADV_DONTDUMP`, FreeBSD and DragonFly
+    /// `MADV_NOCORE`).
+    DontDump,
+    /// Undo `DontDump` (Linux `MADV_DODUMP`, FreeBSD and DragonFly `MADV_CORE`).
+    DoDump,
+    /// Zero the region in children after `fork` (Linux `MADV_WIPEONFORK`; OpenBSD and
+    /// NetBSD `minherit(MAP_INHERIT_ZERO)`).
+    WipeOnFork,
+    /// Undo `WipeOnFork` (Linux `MADV_KEEPONFORK`; OpenBSD and NetBSD
+    /// `minherit(MAP_INHERIT_COPY)`).
+    KeepOnFork,
+    /// Omit the region from children after `fork` (Linux `MADV_DONTFORK`).
+    DontFork,
+    /// Undo `DontFork` (Linux `MADV_DOFORK`).
+    DoFork,
+    /// Allow transparent huge pages (Linux `MADV_HUGEPAGE`).
+    Hugepage,
+    /// Disallow transparent huge pages (Linux `MADV_NOHUGEPAGE`).
+    NoHugepage,
+}
+
+/// Flags for [`mlockall`]; combine with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MclFlags(u32);
+
+impl MclFlags {
+    /// Lock all pages currently mapped (`MCL_CURRENT`).
+    pub const CURRENT: Self = Self(0x1);
+    /// Lock pages mapped in the future, e.g. new heap or stack growth (`MCL_FUTURE`).
+    pub const FUTURE: Self = Self(0x2);
+    /// With `CURRENT` and/or `FUTURE`, lock pages only once they are faulted in
+    /// (`MCL_ONFAULT`, Linux/Android only).
+    pub const ONFAULT: Self = Self(0x4);
+
+    /// No flags set.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Whether every flag in `other` is also set in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+impl std::ops::BitOr for MclFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for MclFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+#[cfg(not(unix))]
+/// Lock the whole address space (stub).
+///
+/// This stub is compiled on non-Unix targets and always returns `Unsupported`.
+pub fn mlockall(_flags: MclFlags) -> io::Result<()> {
+    unsupported("mlockall unsupported on this platform")
+}
+
+#[cfg(not(unix))]
+/// Unlock the whole address space (stub).
+///
+/// This stub is compiled on non-Unix targets and always returns `Unsupported`.
+pub fn munlockall() -> io::Result<()> {
+    unsupported("munlockall unsupported on this platform")
+}
+
+/// RAII guard that keeps the process memory locked and calls `munlockall` on drop.
+///
+/// Created by [`lock_all_memory`]. Call [`MlockAllGuard::leak`] to keep the memory
+/// pinned for the rest of the process lifetime.
+#[derive(Debug)]
+#[must_use = "memory is unlocked again as soon as the guard is dropped"]
+pub struct MlockAllGuard {
+    _private: (),
+}
+
+impl MlockAllGuard {
+    /// Keep the memory locked permanently by never running the guard's `Drop`.
+    pub fn leak(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for MlockAllGuard {
+    fn drop(&mut self) {
+        if let Err(e) = munlockall() {
+            // Avoid panicking in Drop; emit a diagnostic.
+            diag!("munlockall failed: {e}");
+        }
+    }
+}
+
+/// Lock the process address space with [`mlockall`] and return a guard that unlocks it.
+///
+/// Behavior:
+/// - Typical flags are `MclFlags::CURRENT | MclFlags::FUTURE`.
+/// - Dropping the guard calls [`munlockall`], which also unlocks regions locked
+///   individually (including this crate's containers).
+///
+/// Returns:
+/// - `Ok(guard)` on success.
+/// - `Err(...)` from [`mlockall`], including `Unsupported` on non-Unix targets.
+pub fn lock_all_memory(flags: MclFlags) -> io::Result<MlockAllGuard> {
+    mlockall(flags)?;
+    Ok(MlockAllGuard { _private: () })
+}
+
+/// Flag for [`mlock2`]: lock pages as they are faulted in instead of pre-faulting the
+/// whole range (Linux `MLOCK_ONFAULT`).
+pub const MLOCK_ONFAULT: u32 = 0x01;
+
+/// Lock a memory region with `mlock2(2)` flags such as [`MLOCK_ONFAULT`].
+///
+/// On-fault locking avoids populating every page up front, which makes large, sparsely
+/// used secret arenas much cheaper to lock.
+///
+/// Platform:
+/// - Linux/Android: issues the `mlock2` syscall. If the kernel predates it (`ENOSYS`),
+///   falls back to plain [`mlock`], which locks (and pre-faults) the whole range.
+/// - Elsewhere: `flags` are ignored and this is equivalent to [`mlock`].
+///
+/// Returns:
+/// - `Ok(())` on success; zero-length regions are a no-op.
+/// - `Err(...)` with `last_os_error()` on failure (e.g. `EINVAL` for unknown flags).
+/// - `Err(Unsupported)` where [`mlock`] is unsupported.
+///
+/// # Safety
+/// Same contract as [`mlock`]: `(addr, len)` must be a valid region owned by this
+/// process that is not deallocated, unmapped, or remapped concurrently.
+pub unsafe fn mlock2(addr: *const c_void, len: usize, flags: u32) -> io::Result<()> {
+    if len == 0 {
+        return Ok(());
+    }
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        // Safety: the kernel validates the range; we do not dereference addr.
+        let rc = unsafe { libc::syscall(libc::SYS_mlock2, addr, len, flags as libc::c_uint) };
+        if rc == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() != Some(libc::ENOSYS) {
+            return Err(denial::classify(err, "mlock2"));
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let _ = flags;
+    // Safety: forwarded from the caller.
+    unsafe { mlock(addr, len) }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn smoke_disable_core_dumps_for_process() {
+        let _ = crate::disable_core_dumps_for_process();
+        let _ = crate::disable_core_dumps_with_guard();
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn wer_dump_suppression_is_windows_only() {
+        use std::io::ErrorKind;
+        let err = crate::disable_wer_dumps_for_process().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+        let err = crate::disable_wer_dumps_with_guard().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn mlockall_flags_combine_and_empty_set_is_rejected() {
+        use crate::MclFlags;
+        let flags = MclFlags::CURRENT | MclFlags::ONFAULT;
+        assert!(flags.contains(MclFlags::ONFAULT));
+        assert!(!flags.contains(MclFlags::FUTURE));
+        assert!(crate::mlockall(MclFlags::empty()).is_err());
+        assert!(crate::lock_all_memory(MclFlags::empty()).is_err());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "openbsd", target_os = "netbsd"))]
+    #[test]
+    fn wipeonfork_zeroes_region_in_child() {
+        // A mapping of its own: advice on a heap page would outlive the buffer and wipe
+        // whatever malloc puts there next in other tests' children.
+        let mut buf = crate::secure_mmap(64).unwrap();
+        buf.as_mut_slice().fill(0xa5);
+        let ptr = buf.as_mut_slice().as_mut_ptr();
+        unsafe {
+            crate::madvise_wipeonfork(ptr.cast(), crate::page_size()).unwrap();
+            let pid = libc::fork();
+            assert!(pid >= 0);
+            if pid == 0 {
+                // Child: only async-signal-safe operations from here on.
+                libc::_exit(if *ptr == 0 { 0 } else { 1 });
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+        assert_eq!(buf.as_slice()[0], 0xa5);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn dumpable_round_trips() {
+        // While the process is not dumpable /proc/self belongs to root, so other tests'
+        // reads of it would fail.
+        if !crate::test_util::in_own_process("tests::dumpable_round_trips") {
+            return;
+        }
+        let before = crate::is_dumpable().unwrap();
+        crate::set_dumpable(false).unwrap();
+        assert!(!crate::is_dumpable().unwrap());
+        crate::set_dumpable(before).unwrap();
+        assert_eq!(crate::is_dumpable().unwrap(), before);
+
+        let guard = crate::disable_dumpable_with_guard().unwrap();
+        assert!(!crate::is_dumpable().unwrap());
+        drop(guard);
+        assert_eq!(crate::is_dumpable().unwrap(), before);
+
+        let err = crate::disable_tracing().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn madvise_maps_advice_or_reports_unsupported() {
+        use crate::Advice;
+        let mut buf = crate::SecureBuf::new(1).unwrap();
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        let page = crate::page_size();
+        unsafe {
+            crate::madvise(ptr, page, Advice::WillNeed).unwrap();
+            let dodump = crate::madvise(ptr, page, Advice::DoDump);
+            #[cfg(target_os = "linux")]
+            dodump.unwrap();
+            #[cfg(not(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "dragonfly"
+            )))]
+            assert_eq!(dodump.unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[cfg(any(target_os = "linux", windows))]
+    #[test]
+    fn dump_exclusion_round_trips() {
+        let mut buf = crate::SecureBuf::new(1).unwrap();
+        assert!(buf.is_dump_excluded());
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        let page = crate::page_size();
+        unsafe {
+            crate::include_in_dumps(ptr, page).unwrap();
+            crate::exclude_from_dumps(ptr, page).unwrap();
+        }
+    }
+
+    #[test]
+    fn page_access_round_trips_on_a_locked_buffer() {
+        use crate::PageAccess;
+
+        let mut buf = crate::SecureBuf::new(1).unwrap();
+        buf.as_mut_slice()[0] = 42;
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        let page = crate::page_size();
+        unsafe {
+            crate::set_page_access(ptr, page, PageAccess::NoAccess).unwrap();
+            crate::set_page_access(ptr, page, PageAccess::ReadOnly).unwrap();
+            assert_eq!(buf.as_slice()[0], 42);
+            crate::set_page_access(ptr, page, PageAccess::ReadWrite).unwrap();
+        }
+        assert!(buf.is_locked());
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn physical_locks_are_windows_only() {
+        let page = [0u8; 16];
+        assert!(!crate::can_lock_physical());
+        let err = unsafe { crate::lock_physical(page.as_ptr().cast(), page.len()) }.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn growing_lock_is_plain_mlock_off_windows() {
+        let page = vec![0u8; crate::page_size()];
+        let ptr = page.as_ptr().cast();
+        unsafe {
+            crate::mlock_grow_working_set(ptr, page.len(), true).unwrap();
+            crate::munlock(ptr, page.len()).unwrap();
+            let guard = crate::mlock_grow_working_set_with_guard(ptr, page.len(), false).unwrap();
+            assert!(guard.previous_limits().is_none());
+            crate::munlock(ptr, page.len()).unwrap();
+        }
+    }
+
+    #[test]
+    fn page_sizes_are_whole_pages() {
+        let page = crate::page_size();
+        assert!(page.is_power_of_two());
+        assert!(crate::allocation_granularity().is_multiple_of(page));
+        if let Some(large) = crate::large_page_size() {
+            assert!(large.is_multiple_of(page));
+        }
+    }
+
+    #[test]
+    fn not_locked_is_found_only_in_its_own_errors() {
+        use crate::NotLocked;
+        use std::io::{Error, ErrorKind};
+        let err = Error::new(ErrorKind::InvalidInput, NotLocked);
+        assert_eq!(NotLocked::find(&err), Some(&NotLocked));
+        assert!(NotLocked::find(&Error::from(ErrorKind::InvalidInput)).is_none());
+        assert!(NotLocked::find(&Error::other("not locked")).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn chunked_lock_covers_the_range_page_by_page() {
+        let page = crate::page_size();
+        let buf = vec![0u8; 3 * page + 1];
+        let ptr = buf.as_ptr().cast();
+        unsafe {
+            let err = crate::mlock_chunked(ptr, buf.len(), 0, false).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+            let lock = crate::mlock_chunked(ptr, buf.len(), 1, false).unwrap();
+            assert!(lock.is_complete());
+            assert_eq!(lock.locked_len(), buf.len());
+            assert!(lock.error().is_none());
+            crate::munlock(ptr, lock.locked_len()).unwrap();
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn posix_madvise_fallback_handles_posix_advice_only() {
+        use crate::Advice;
+        let mut buf = crate::SecureBuf::new(1).unwrap();
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        let page = crate::page_size();
+        unsafe {
+            super::unix::posix_madvise(ptr, page, Advice::Sequential).unwrap();
+            super::unix::posix_madvise(ptr, page, Advice::Normal).unwrap();
+            let err = super::unix::posix_madvise(ptr, page, Advice::DontDump).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn dontfork_and_dofork_round_trip() {
+        let mut buf = crate::SecureBuf::new(1).unwrap();
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        unsafe {
+            crate::madvise_dontfork(ptr, crate::page_size()).unwrap();
+            crate::madvise_dofork(ptr, crate::page_size()).unwrap();
+        }
+    }
+
+    #[test]
+    fn hugepage_advice_is_accepted_or_unsupported() {
+        // A mapping of its own: heap pages keep the advice after the buffer is freed.
+        let mut buf = crate::secure_mmap(1).unwrap();
+        let ptr = buf.as_mut_slice().as_mut_ptr().cast();
+        let page = crate::page_size();
+        for result in unsafe {
+            [
+                crate::madvise_nohugepage(ptr, page),
+                crate::madvise_hugepage(ptr, page),
+            ]
+        } {
+            // Linux kernels built without THP reject both with EINVAL.
+            if let Err(e) = result {
+                assert!(matches!(
+                    e.kind(),
+                    std::io::ErrorKind::Unsupported | std::io::ErrorKind::InvalidInput
+                ));
+            }
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn madvise_batch_covers_every_region() {
+        let bufs: Vec<_> = (0..4).map(|_| crate::secure_mmap(1).unwrap()).collect();
+        let page = crate::page_size();
+        let regions: Vec<_> = bufs
+            .iter()
+            .map(|b| (b.as_slice().as_ptr().cast_mut().cast(), page))
+            .collect();
+        unsafe {
+            crate::madvise_batch(&regions, crate::Advice::WillNeed).unwrap();
+            crate::madvise_batch(&[], crate::Advice::WillNeed).unwrap();
+        }
+        #[cfg(target_os = "linux")]
+        unsafe {
+            crate::madvise_batch(&regions, crate::Advice::DoDump).unwrap();
+            crate::madvise_batch(&regions, crate::Advice::DontDump).unwrap();
+            if let Some(pidfd) = crate::unix::batch::pidfd_self() {
+                // WillNeed has been valid for process_madvise since it was added.
+                let done =
+                    crate::unix::batch::advise_prefix(&pidfd, &regions, crate::Advice::WillNeed);
+                assert_eq!(done, regions.len());
+            }
+            // A hole in the middle is reported.
+            let mut holey = regions.clone();
+            holey.insert(2, (std::ptr::null_mut(), page));
+            assert!(crate::madvise_batch(&holey, crate::Advice::WillNeed).is_err());
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn mlock2_onfault_locks_and_rejects_unknown_flags() {
+        let buf = crate::SecureBuf::new(1).unwrap();
+        let ptr = buf.as_slice().as_ptr().cast();
+        let page = crate::page_size();
+        unsafe {
+            crate::mlock2(ptr, page, crate::MLOCK_ONFAULT).unwrap();
+            assert!(crate::mlock2(ptr, page, 0x8000_0000).is_err());
+        }
     }
 }
@@ -0,0 +1,1171 @@
+//! How much memory the process may lock, and why locking fails.
+
+use std::fmt;
+use std::io;
+
+/// The process's `RLIMIT_MEMLOCK`, as returned by [`memlock_limit`].
+///
+/// `None` means unlimited (`RLIM_INFINITY`). The limit covers all memory the process
+/// has locked at once, not each call. On Linux a process with `CAP_IPC_LOCK` may lock
+/// beyond it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemlockLimit {
+    /// Limit enforced for unprivileged processes, in bytes.
+    pub soft: Option<u64>,
+    /// Ceiling the soft limit can be raised to without privileges, in bytes.
+    pub hard: Option<u64>,
+}
+
+impl MemlockLimit {
+    /// Whether the soft limit allows `bytes` of locked memory in total.
+    pub fn allows(&self, bytes: u64) -> bool {
+        self.soft.is_none_or(|soft| bytes <= soft)
+    }
+
+    /// Check that `requested` bytes fit under the soft limit.
+    ///
+    /// Returns `Err(OutOfMemory)` with a message naming both sizes, e.g.
+    /// "requested 64 MiB of locked memory, but RLIMIT_MEMLOCK is 64 KiB (hard limit
+    /// 8 MiB)", which is more useful to surface than the bare ENOMEM from `mlock`.
+    pub fn check(&self, requested: u64) -> io::Result<()> {
+        if self.allows(requested) {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "requested {} of locked memory, but RLIMIT_MEMLOCK is {} (hard limit {})",
+                Bytes(requested),
+                Limit(self.soft),
+                Limit(self.hard),
+            ),
+        ))
+    }
+}
+
+/// Query the process's `RLIMIT_MEMLOCK` soft and hard limits.
+///
+/// Platform:
+/// - Linux, Android, macOS/iOS, the BSDs, Hurd, QNX and Redox.
+/// - Elsewhere (including Windows, where the working-set size plays this role),
+///   returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(limit) on success.
+/// - Err(io::Error) with last_os_error() on failure.
+pub fn memlock_limit() -> io::Result<MemlockLimit> {
+    imp::get()
+}
+
+/// Raise the `RLIMIT_MEMLOCK` soft limit so that `bytes` can be locked.
+///
+/// Most `mlock` failures outside containers are a low default soft limit (often 64 KiB
+/// or 8 MiB) under a higher or unlimited hard limit, which any process may raise itself.
+///
+/// Behavior:
+/// - Never lowers a limit; if the soft limit already allows `bytes`, nothing changes.
+/// - Up to the hard limit, only the soft limit is raised.
+/// - Beyond it, the hard limit is raised as well, which needs `CAP_SYS_RESOURCE` on
+///   Linux (root elsewhere). Without it, the soft limit is still raised to the hard limit.
+///
+/// Platform:
+/// - As for [`memlock_limit`]; elsewhere returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(limit) with the new limits once `bytes` is allowed.
+/// - Err(PermissionDenied) if `bytes` exceeds the hard limit and raising it was refused;
+///   the message names the limit reached and the missing privilege.
+/// - Err(io::Error) with last_os_error() on other failures.
+pub fn raise_memlock_limit(bytes: u64) -> io::Result<MemlockLimit> {
+    let current = imp::get()?;
+    if current.allows(bytes) {
+        return Ok(current);
+    }
+    let within_hard = current.hard.is_none_or(|hard| bytes <= hard);
+    if within_hard {
+        let raised = MemlockLimit {
+            soft: Some(bytes),
+            ..current
+        };
+        // Raising a soft limit up to the hard one is never refused by the kernel itself.
+        imp::set(raised).map_err(|e| crate::denial::classify(e, "setrlimit"))?;
+        return Ok(raised);
+    }
+
+    let raised = MemlockLimit {
+        soft: Some(bytes),
+        hard: Some(bytes),
+    };
+    match imp::set(raised) {
+        Ok(()) => Ok(raised),
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            let best = MemlockLimit {
+                soft: current.hard,
+                ..current
+            };
+            imp::set(best)?;
+            let fix = match memlock_limit_source() {
+                Ok(MemlockLimitSource::SystemdUnit(unit)) => {
+                    format!("LimitMEMLOCK={} in systemd unit {unit}", SystemdSize(bytes))
+                }
+                _ => "`ulimit -l` or LimitMEMLOCK= in a systemd unit".to_owned(),
+            };
+            Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "raised RLIMIT_MEMLOCK to its hard limit of {}; locking {} needs \
+                     CAP_SYS_RESOURCE (or a higher hard limit, e.g. {fix})",
+                    Limit(current.hard),
+                    Bytes(bytes),
+                ),
+            ))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Where the process's `RLIMIT_MEMLOCK` most likely comes from, as returned by
+/// [`memlock_limit_source`]; this is where to change it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum MemlockLimitSource {
+    /// The systemd service unit the process runs as, e.g. `"app.service"`: its
+    /// `LimitMEMLOCK=`, or `DefaultLimitMEMLOCK=` from `system.conf` when unset.
+    SystemdUnit(String),
+    /// The container runtime (`docker run --ulimit memlock=...`), with the evidence for
+    /// running in a container, as in [`LockPrivileges::container_hint`].
+    Container(&'static str),
+    /// Inherited from the parent process: the shell's `ulimit -l`, PAM's
+    /// `/etc/security/limits.conf` for login sessions, or the kernel default.
+    Inherited,
+}
+
+impl fmt::Display for MemlockLimitSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SystemdUnit(unit) => write!(f, "systemd unit {unit} (LimitMEMLOCK=)"),
+            Self::Container(hint) => write!(f, "container runtime ({hint})"),
+            Self::Inherited => f.write_str("inherited from the parent process (ulimit -l)"),
+        }
+    }
+}
+
+/// Find out what set the process's `RLIMIT_MEMLOCK`, so the fix can be applied there.
+///
+/// A process counts as a systemd service when `INVOCATION_ID` is set (systemd sets it
+/// for every unit it starts) and its cgroup names a `.service` unit. Container
+/// detection is the heuristic of [`lock_privileges`].
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(source); [`MemlockLimitSource::Inherited`] when nothing more specific is found.
+pub fn memlock_limit_source() -> io::Result<MemlockLimitSource> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        if let Some(unit) = systemd_unit() {
+            return Ok(MemlockLimitSource::SystemdUnit(unit));
+        }
+        Ok(detect_container().map_or(MemlockLimitSource::Inherited, MemlockLimitSource::Container))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "memlock_limit_source unsupported on this platform",
+    ))
+}
+
+/// Pages of the minimum working set that a process needs for itself and cannot lock.
+pub(crate) const WORKING_SET_OVERHEAD_PAGES: usize = 16;
+
+/// `QUOTA_LIMITS_HARDWS_MIN_ENABLE` and `QUOTA_LIMITS_HARDWS_MAX_ENABLE`.
+const HARD_MIN: u32 = 0x1;
+const HARD_MAX: u32 = 0x4;
+
+/// The process's working-set sizes, as returned by [`working_set_limits`].
+///
+/// On Windows the minimum working set plays the role of `RLIMIT_MEMLOCK`: `VirtualLock`
+/// can lock at most that much memory at once, less a few pages the process needs for
+/// itself, and fails with `ERROR_WORKING_SET_QUOTA` beyond it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WorkingSetLimits {
+    pub(crate) minimum: usize,
+    pub(crate) maximum: usize,
+    pub(crate) flags: u32,
+}
+
+impl WorkingSetLimits {
+    /// The minimum working set in bytes.
+    pub fn minimum(&self) -> usize {
+        self.minimum
+    }
+
+    /// The maximum working set in bytes.
+    pub fn maximum(&self) -> usize {
+        self.maximum
+    }
+
+    /// Whether the minimum is a hard guarantee (`QUOTA_LIMITS_HARDWS_MIN_ENABLE`).
+    pub fn is_minimum_hard(&self) -> bool {
+        self.flags & HARD_MIN != 0
+    }
+
+    /// Whether the maximum is a hard cap (`QUOTA_LIMITS_HARDWS_MAX_ENABLE`).
+    pub fn is_maximum_hard(&self) -> bool {
+        self.flags & HARD_MAX != 0
+    }
+
+    /// Roughly how many bytes the process can have locked at once: the minimum working
+    /// set less the pages the process needs for itself.
+    pub fn lockable(&self) -> usize {
+        self.minimum
+            .saturating_sub(WORKING_SET_OVERHEAD_PAGES * crate::page_size())
+    }
+
+    /// Whether `bytes` of locked memory in total fit in the minimum working set.
+    pub fn allows(&self, bytes: u64) -> bool {
+        bytes <= self.lockable() as u64
+    }
+
+    /// Check that `requested` bytes of locked memory in total fit in the minimum working
+    /// set.
+    ///
+    /// Returns `Err(OutOfMemory)` with a message naming both sizes, e.g. "requested
+    /// 1 MiB of locked memory, but the minimum working set of 200 KiB allows only
+    /// 136 KiB".
+    pub fn check(&self, requested: u64) -> io::Result<()> {
+        if self.allows(requested) {
+            return Ok(());
+        }
+        Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "requested {} of locked memory, but the minimum working set of {} allows only {}",
+                Bytes(requested),
+                Bytes(self.minimum as u64),
+                Bytes(self.lockable() as u64),
+            ),
+        ))
+    }
+}
+
+/// Query the process's working-set sizes (`GetProcessWorkingSetSizeEx`), to see how
+/// much memory can be locked before trying.
+///
+/// Platform:
+/// - Windows only. Elsewhere, returns `Unsupported`; see [`memlock_limit`].
+///
+/// Returns:
+/// - Ok(limits) on success.
+/// - Err(io::Error) with last_os_error() on failure.
+pub fn working_set_limits() -> io::Result<WorkingSetLimits> {
+    #[cfg(windows)]
+    return crate::windows::working_set();
+    #[cfg(not(windows))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "working_set_limits is Windows-only",
+    ))
+}
+
+/// The process's cgroup v2 memory limits and usage, as returned by [`cgroup_memory`].
+///
+/// Locked pages are charged to the cgroup like any other memory but can never be
+/// reclaimed, so locking close to `memory.max` leaves the kernel nothing to evict but
+/// other processes' memory, and ends in the cgroup's OOM killer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CgroupMemory {
+    current: u64,
+    high: Option<u64>,
+    max: Option<u64>,
+}
+
+/// What charging more memory would do to the cgroup; see [`CgroupMemory::pressure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CgroupPressure {
+    /// The allocation stays below `memory.high` and `memory.max`.
+    Fits,
+    /// The allocation crosses `memory.high`: the cgroup is throttled and reclaimed.
+    Reclaim,
+    /// The allocation crosses `memory.max`: reclaim cannot free locked pages, so the
+    /// cgroup's OOM killer runs.
+    Oom,
+}
+
+impl CgroupMemory {
+    /// Bytes currently charged to the cgroup (`memory.current`).
+    pub fn current(&self) -> u64 {
+        self.current
+    }
+
+    /// The throttling threshold (`memory.high`), the lowest along the cgroup's
+    /// ancestry; `None` if unlimited.
+    pub fn high(&self) -> Option<u64> {
+        self.high
+    }
+
+    /// The hard limit (`memory.max`), the lowest along the cgroup's ancestry; `None` if
+    /// unlimited.
+    pub fn max(&self) -> Option<u64> {
+        self.max
+    }
+
+    /// What charging `requested` more bytes would do.
+    pub fn pressure(&self, requested: u64) -> CgroupPressure {
+        let total = self.current.saturating_add(requested);
+        if self.max.is_some_and(|max| total > max) {
+            CgroupPressure::Oom
+        } else if self.high.is_some_and(|high| total > high) {
+            CgroupPressure::Reclaim
+        } else {
+            CgroupPressure::Fits
+        }
+    }
+
+    /// Check that locking `requested` more bytes keeps the cgroup below `memory.high`.
+    ///
+    /// Returns `Err(OutOfMemory)` with a message naming the sizes and the consequence,
+    /// e.g. "locking 512 MiB would bring the cgroup to 1.2 GiB, past memory.max of
+    /// 1 GiB; the cgroup would be OOM-killed".
+    pub fn check(&self, requested: u64) -> io::Result<()> {
+        let (name, limit, outcome) = match self.pressure(requested) {
+            CgroupPressure::Fits => return Ok(()),
+            CgroupPressure::Reclaim => (
+                "memory.high",
+                self.high,
+                "the cgroup would be throttled and reclaimed",
+            ),
+            CgroupPressure::Oom => ("memory.max", self.max, "the cgroup would be OOM-killed"),
+        };
+        Err(io::Error::new(
+            io::ErrorKind::OutOfMemory,
+            format!(
+                "locking {} would bring the cgroup to {}, past {name} of {}; {outcome}",
+                Bytes(requested),
+                Bytes(self.current.saturating_add(requested)),
+                Limit(limit),
+            ),
+        ))
+    }
+}
+
+impl fmt::Display for CgroupMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "cgroup memory {} used, memory.high {}, memory.max {}",
+            Bytes(self.current),
+            Limit(self.high),
+            Limit(self.max)
+        )
+    }
+}
+
+/// Read the memory limits of the process's cgroup v2 (`memory.max`, `memory.high`,
+/// `memory.current`).
+///
+/// Use [`CgroupMemory::check`] before large locked allocations in containers, where
+/// `RLIMIT_MEMLOCK` is often unlimited but the cgroup is not.
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(Some(memory)) when the process is in a cgroup v2 with the memory controller.
+/// - Ok(None) without cgroup v2 or its memory controller (e.g. cgroup v1 hosts).
+/// - Err(io::Error) if the cgroup files exist but could not be read or parsed.
+pub fn cgroup_memory() -> io::Result<Option<CgroupMemory>> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mountinfo = std::fs::read_to_string("/proc/self/mountinfo")?;
+        let cgroup = std::fs::read_to_string("/proc/self/cgroup")?;
+        let (Some(mount), Some(path)) = (cgroup2_mount(&mountinfo), cgroup2_path(&cgroup)) else {
+            return Ok(None);
+        };
+        cgroup_memory_at(std::path::Path::new(mount), path)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cgroup_memory unsupported on this platform",
+    ))
+}
+
+/// The memory files of cgroup `path` in the hierarchy mounted at `mount`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn cgroup_memory_at(mount: &std::path::Path, path: &str) -> io::Result<Option<CgroupMemory>> {
+    let mut dir = mount.to_path_buf();
+    dir.extend(path.split('/').filter(|part| !part.is_empty()));
+    let current = match std::fs::read_to_string(dir.join("memory.current")) {
+        Ok(text) => parse_memory_value(&text)?.unwrap_or(0),
+        // The root cgroup and cgroups without the controller have no memory files.
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut memory = CgroupMemory {
+        current,
+        high: None,
+        max: None,
+    };
+    // Every ancestor's limits apply too. The mount itself is read as well: in a cgroup
+    // namespace (`0::/`, as in Docker and Kubernetes) it is the container's own cgroup,
+    // while the real root cgroup simply has no limit files.
+    let mut at = Some(dir.as_path());
+    while let Some(dir) = at.filter(|dir| dir.starts_with(mount)) {
+        for (file, slot) in [
+            ("memory.high", &mut memory.high),
+            ("memory.max", &mut memory.max),
+        ] {
+            let text = match std::fs::read_to_string(dir.join(file)) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            if let Some(limit) = parse_memory_value(&text)? {
+                *slot = Some(slot.map_or(limit, |current| current.min(limit)));
+            }
+        }
+        at = dir.parent();
+    }
+    Ok(Some(memory))
+}
+
+/// Where the cgroup2 hierarchy is mounted, from `/proc/self/mountinfo`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn cgroup2_mount(mountinfo: &str) -> Option<&str> {
+    mountinfo.lines().find_map(|line| {
+        let (mount, fs) = line.split_once(" - ")?;
+        if fs.split_whitespace().next() != Some("cgroup2") {
+            return None;
+        }
+        mount.split_whitespace().nth(4)
+    })
+}
+
+/// The process's cgroup v2 path, from the `0::` line of `/proc/self/cgroup`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn cgroup2_path(cgroup: &str) -> Option<&str> {
+    cgroup.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+/// A `memory.*` value: bytes, or `max` for unlimited.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_memory_value(text: &str) -> io::Result<Option<u64>> {
+    match text.trim() {
+        "max" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+    }
+}
+
+/// What the process is allowed to lock, as reported by [`lock_privileges`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockPrivileges {
+    cap_ipc_lock: bool,
+    container: Option<&'static str>,
+    limit: Option<MemlockLimit>,
+    android_api_level: Option<u32>,
+    cgroup: Option<CgroupMemory>,
+}
+
+impl LockPrivileges {
+    /// Whether the process holds `CAP_IPC_LOCK` in its effective set, which lifts
+    /// `RLIMIT_MEMLOCK` entirely.
+    pub fn has_cap_ipc_lock(&self) -> bool {
+        self.cap_ipc_lock
+    }
+
+    /// Whether the process appears to run in a container.
+    pub fn in_container(&self) -> bool {
+        self.container.is_some()
+    }
+
+    /// The evidence for [`LockPrivileges::in_container`], e.g. `"/.dockerenv"` or
+    /// `"kubepods cgroup"`.
+    pub fn container_hint(&self) -> Option<&'static str> {
+        self.container
+    }
+
+    /// The `RLIMIT_MEMLOCK` limits, if they could be read.
+    pub fn memlock_limit(&self) -> Option<MemlockLimit> {
+        self.limit
+    }
+
+    /// The cgroup v2 memory limits, if the process is in a memory-limited cgroup v2.
+    pub fn cgroup_memory(&self) -> Option<CgroupMemory> {
+        self.cgroup
+    }
+
+    /// The Android API level (`ro.build.version.sdk`); `None` on other systems.
+    pub fn android_api_level(&self) -> Option<u32> {
+        self.android_api_level
+    }
+
+    /// Whether the process can lock as much memory as it likes: it holds `CAP_IPC_LOCK`,
+    /// or the hard limit is unlimited so [`raise_memlock_limit`] can lift the soft one.
+    pub fn can_lock_unlimited(&self) -> bool {
+        self.cap_ipc_lock || self.limit.is_some_and(|limit| limit.hard.is_none())
+    }
+}
+
+impl fmt::Display for LockPrivileges {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cap = if self.cap_ipc_lock {
+            "held"
+        } else {
+            "not held"
+        };
+        write!(f, "CAP_IPC_LOCK {cap}")?;
+        if let Some(limit) = self.limit {
+            write!(
+                f,
+                ", RLIMIT_MEMLOCK {} (hard limit {})",
+                Limit(limit.soft),
+                Limit(limit.hard)
+            )?;
+        }
+        if let Some(cgroup) = self.cgroup.filter(|c| c.high.is_some() || c.max.is_some()) {
+            write!(f, ", {cgroup}")?;
+        }
+        if let Some(level) = self.android_api_level {
+            write!(f, ", Android API {level}")?;
+            if !self.can_lock_unlimited() {
+                f.write_str(
+                    "; apps cannot raise the limit, so lock only small keys or use \
+                     SecureBuf::new_best_effort",
+                )?;
+            }
+        }
+        if let Some(hint) = self.container {
+            write!(f, ", running in a container ({hint})")?;
+            if !self.can_lock_unlimited() {
+                f.write_str(
+                    "; grant CAP_IPC_LOCK (docker run --cap-add IPC_LOCK, or \
+                     securityContext.capabilities.add in Kubernetes) or raise the \
+                     memlock ulimit (docker run --ulimit memlock=-1)",
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Diagnose why locking might fail: capabilities, container, `RLIMIT_MEMLOCK`, and the
+/// cgroup v2 memory limits.
+///
+/// EPERM or ENOMEM from `mlock` inside Docker or Kubernetes usually means the container
+/// has neither `CAP_IPC_LOCK` nor a raised memlock ulimit. Even when locking succeeds,
+/// the cgroup's `memory.max` bounds how much can be locked before the container is
+/// OOM-killed. The `Display` output is a one-line summary with a suggested fix,
+/// suitable for logs and error reports.
+///
+/// Container detection is a heuristic (`/.dockerenv`, `/run/.containerenv`,
+/// `KUBERNETES_SERVICE_HOST`, and docker/containerd/kubepods/libpod cgroups).
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(privileges) on success.
+/// - Err(io::Error) if /proc/self/status could not be read.
+pub fn lock_privileges() -> io::Result<LockPrivileges> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let status = std::fs::read_to_string("/proc/self/status")?;
+        Ok(LockPrivileges {
+            cap_ipc_lock: has_cap_ipc_lock(&status),
+            container: detect_container(),
+            limit: imp::get().ok(),
+            android_api_level: android_api_level(),
+            cgroup: cgroup_memory().ok().flatten(),
+        })
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "lock_privileges unsupported on this platform",
+    ))
+}
+
+/// Add the limit and what to do about it to an `mlock` failure of `requested` bytes.
+///
+/// Only on Android, where the small default limit that apps cannot raise makes ENOMEM
+/// the common case, for systemd services, whose unit sets the limit, on NetBSD, where
+/// EAGAIN means either RLIMIT_MEMLOCK or the system-wide wired page limit, on illumos
+/// and Solaris, where locking needs a privilege and is capped by a resource control
+/// rather than RLIMIT_MEMLOCK, and on AIX, which has no RLIMIT_MEMLOCK and lets only
+/// privileged users lock. Otherwise `err` is returned unchanged, keeping its OS error
+/// code.
+pub(crate) fn explain_lock_failure(err: io::Error, requested: usize) -> io::Error {
+    #[cfg(target_os = "android")]
+    if matches!(err.raw_os_error(), Some(libc::ENOMEM | libc::EPERM))
+        && let Ok(limit) = imp::get()
+    {
+        let api = android_api_level().map_or(String::new(), |level| format!(" (API {level})"));
+        return io::Error::new(
+            err.kind(),
+            format!(
+                "locking {} failed: RLIMIT_MEMLOCK is {} with {} already locked by this \
+                 crate, and Android{api} apps cannot raise it; lock only small keys or use \
+                 SecureBuf::new_best_effort",
+                Bytes(requested as u64),
+                Limit(limit.soft),
+                Bytes(crate::secure_buf::locked_bytes() as u64),
+            ),
+        );
+    }
+    #[cfg(target_os = "linux")]
+    if matches!(err.raw_os_error(), Some(libc::ENOMEM | libc::EPERM))
+        && let Ok(MemlockLimit {
+            soft: Some(soft), ..
+        }) = imp::get()
+        && let Some(unit) = systemd_unit()
+    {
+        let locked = crate::secure_buf::locked_bytes() as u64;
+        return io::Error::new(
+            err.kind(),
+            format!(
+                "locking {} failed: RLIMIT_MEMLOCK is {} with {} already locked by this \
+                 crate; the limit comes from systemd unit {unit}, so add \
+                 LimitMEMLOCK={} (or infinity) to its [Service] section",
+                Bytes(requested as u64),
+                Bytes(soft),
+                Bytes(locked),
+                SystemdSize(locked.saturating_add(requested as u64)),
+            ),
+        );
+    }
+    #[cfg(target_os = "netbsd")]
+    if err.raw_os_error() == Some(libc::EAGAIN)
+        && let Some((wired, wired_max)) = wired_pages()
+        && let Ok(limit) = imp::get()
+    {
+        let page = crate::page_size() as u64;
+        let pages = (requested as u64).div_ceil(page);
+        let reason = if wired.saturating_add(pages) > wired_max {
+            format!(
+                "{} of the system-wide limit of {} is already wired (vm.uvmexp2 wiredmax); \
+                 free wired memory elsewhere or lock less",
+                Bytes(wired.saturating_mul(page)),
+                Bytes(wired_max.saturating_mul(page)),
+            )
+        } else {
+            format!(
+                "RLIMIT_MEMLOCK is {} with {} already locked by this crate; raise it with \
+                 `ulimit -l` or raise_memlock_limit",
+                Limit(limit.soft),
+                Bytes(crate::secure_buf::locked_bytes() as u64),
+            )
+        };
+        return io::Error::new(
+            err.kind(),
+            format!("locking {} failed: {reason}", Bytes(requested as u64)),
+        );
+    }
+    #[cfg(any(target_os = "illumos", target_os = "solaris"))]
+    match err.raw_os_error() {
+        Some(libc::EPERM) if !has_privilege(c"proc_lock_memory") => {
+            return io::Error::new(
+                err.kind(),
+                format!(
+                    "locking {} failed: the process lacks the proc_lock_memory privilege; \
+                     grant it with `usermod -K defaultpriv=basic,proc_lock_memory` or the \
+                     service's privileges property",
+                    Bytes(requested as u64),
+                ),
+            );
+        }
+        Some(libc::EAGAIN) => {
+            let raise = if has_privilege(c"sys_resource") {
+                "this process holds sys_resource and may raise it"
+            } else {
+                "raising it needs the sys_resource privilege"
+            };
+            return io::Error::new(
+                err.kind(),
+                format!(
+                    "locking {} failed with {} already locked by this crate: the \
+                     project.max-locked-memory resource control is exhausted; raise it with \
+                     prctl(1) or the project's entry in project(5) ({raise})",
+                    Bytes(requested as u64),
+                    Bytes(crate::secure_buf::locked_bytes() as u64),
+                ),
+            );
+        }
+        _ => {}
+    }
+    #[cfg(target_os = "aix")]
+    match err.raw_os_error() {
+        // Safety: geteuid cannot fail.
+        Some(libc::EPERM) if unsafe { libc::geteuid() } != 0 => {
+            return io::Error::new(
+                err.kind(),
+                format!(
+                    "locking {} failed: AIX only lets root and users with the \
+                     CAP_BYPASS_RAC_VMM capability lock memory; grant it with \
+                     `chuser capabilities=CAP_BYPASS_RAC_VMM,CAP_PROPAGATE <user>`",
+                    Bytes(requested as u64),
+                ),
+            );
+        }
+        Some(libc::EAGAIN | libc::ENOMEM) => {
+            return io::Error::new(
+                err.kind(),
+                format!(
+                    "locking {} failed with {} already locked by this crate: AIX caps \
+                     pinned memory system-wide with the maxpin% tunable rather than \
+                     RLIMIT_MEMLOCK; check it with `vmo -o maxpin%`",
+                    Bytes(requested as u64),
+                    Bytes(crate::secure_buf::locked_bytes() as u64),
+                ),
+            );
+        }
+        _ => {}
+    }
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "netbsd",
+        target_os = "illumos",
+        target_os = "solaris",
+        target_os = "aix"
+    )))]
+    let _ = requested;
+    err
+}
+
+/// Pages wired system-wide and the most that may be, from the `vm.uvmexp2` sysctl.
+#[cfg(target_os = "netbsd")]
+fn wired_pages() -> Option<(u64, u64)> {
+    // `struct uvmexp_sysctl` is all int64_t; `wired` is the 9th field and `wiredmax` the
+    // 16th. The buffer is larger than the struct so the kernel copies all of it.
+    let mut fields = [0i64; 128];
+    let mut len = size_of_val(&fields);
+    // Safety: a NUL-terminated name and an out-buffer of `len` bytes; nothing is set.
+    let rc = unsafe {
+        libc::sysctlbyname(
+            c"vm.uvmexp2".as_ptr(),
+            fields.as_mut_ptr().cast(),
+            &mut len,
+            std::ptr::null(),
+            0,
+        )
+    };
+    if rc != 0 || len < 16 * size_of::<i64>() {
+        return None;
+    }
+    Some((
+        u64::try_from(fields[8]).ok()?,
+        u64::try_from(fields[15]).ok()?,
+    ))
+}
+
+/// Whether the named privilege is in the effective set, per priv_ineffect(3C).
+#[cfg(any(target_os = "illumos", target_os = "solaris"))]
+fn has_privilege(name: &std::ffi::CStr) -> bool {
+    unsafe extern "C" {
+        // Not bound by libc; returns a boolean_t.
+        fn priv_ineffect(name: *const libc::c_char) -> libc::c_int;
+    }
+    // Safety: a NUL-terminated privilege name; unknown names report false.
+    unsafe { priv_ineffect(name.as_ptr()) != 0 }
+}
+
+/// The systemd service unit this process runs as, if any.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn systemd_unit() -> Option<String> {
+    std::env::var_os("INVOCATION_ID")?;
+    let cgroup = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    service_unit(&cgroup).map(str::to_owned)
+}
+
+/// The innermost `.service` unit in the systemd hierarchy of `/proc/self/cgroup`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn service_unit(cgroup: &str) -> Option<&str> {
+    // The unified hierarchy ("0::") on cgroup v2, systemd's named one on v1.
+    let path = cgroup.lines().find_map(|line| {
+        line.strip_prefix("0::")
+            .or_else(|| line.split_once(":name=systemd:").map(|(_, path)| path))
+    })?;
+    path.rsplit('/').find(|unit| unit.ends_with(".service"))
+}
+
+/// The `ro.build.version.sdk` system property.
+#[cfg(target_os = "android")]
+fn android_api_level() -> Option<u32> {
+    let mut value = [0 as libc::c_char; libc::PROP_VALUE_MAX as usize];
+    // Safety: a NUL-terminated name and a PROP_VALUE_MAX buffer, as the API requires;
+    // the value written back is NUL-terminated.
+    let value = unsafe {
+        libc::__system_property_get(c"ro.build.version.sdk".as_ptr(), value.as_mut_ptr());
+        std::ffi::CStr::from_ptr(value.as_ptr())
+    };
+    value.to_str().ok()?.parse().ok()
+}
+
+#[cfg(target_os = "linux")]
+fn android_api_level() -> Option<u32> {
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn has_cap_ipc_lock(status: &str) -> bool {
+    const CAP_IPC_LOCK: u32 = 14;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .is_some_and(|mask| mask & (1 << CAP_IPC_LOCK) != 0)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn detect_container() -> Option<&'static str> {
+    use std::path::Path;
+
+    if Path::new("/.dockerenv").exists() {
+        return Some("/.dockerenv");
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("/run/.containerenv");
+    }
+    if std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        return Some("KUBERNETES_SERVICE_HOST");
+    }
+    let cgroup = std::fs::read_to_string("/proc/self/cgroup").ok()?;
+    container_cgroup(&cgroup)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn container_cgroup(cgroup: &str) -> Option<&'static str> {
+    [
+        ("kubepods", "kubepods cgroup"),
+        ("docker", "docker cgroup"),
+        ("containerd", "containerd cgroup"),
+        ("libpod", "libpod cgroup"),
+    ]
+    .into_iter()
+    .find(|(needle, _)| cgroup.contains(needle))
+    .map(|(_, hint)| hint)
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "emscripten",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "hurd",
+    target_os = "nto",
+    target_os = "redox"
+))]
+mod imp {
+    use std::io;
+
+    use super::MemlockLimit;
+
+    pub(super) fn get() -> io::Result<MemlockLimit> {
+        let mut lim = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // Safety: `lim` is a valid, writable rlimit.
+        let rc = unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut lim) };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(MemlockLimit {
+            soft: from_rlim(lim.rlim_cur),
+            hard: from_rlim(lim.rlim_max),
+        })
+    }
+
+    pub(super) fn set(limit: MemlockLimit) -> io::Result<()> {
+        let lim = libc::rlimit {
+            rlim_cur: to_rlim(limit.soft),
+            rlim_max: to_rlim(limit.hard),
+        };
+        // Safety: `lim` is a valid rlimit.
+        let rc = unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &lim) };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    fn to_rlim(value: Option<u64>) -> libc::rlim_t {
+        value
+            .and_then(|v| libc::rlim_t::try_from(v).ok())
+            .unwrap_or(libc::RLIM_INFINITY)
+    }
+
+    // rlim_t is u64 on Linux but not everywhere.
+    #[allow(clippy::useless_conversion)]
+    fn from_rlim(value: libc::rlim_t) -> Option<u64> {
+        // Negative values (signed rlim_t) are treated as unlimited too.
+        if value == libc::RLIM_INFINITY {
+            None
+        } else {
+            u64::try_from(value).ok()
+        }
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "emscripten",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "hurd",
+    target_os = "nto",
+    target_os = "redox"
+)))]
+mod imp {
+    use std::io;
+
+    use super::MemlockLimit;
+
+    pub(super) fn get() -> io::Result<MemlockLimit> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memlock_limit unsupported on this platform",
+        ))
+    }
+
+    pub(super) fn set(_limit: MemlockLimit) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "raise_memlock_limit unsupported on this platform",
+        ))
+    }
+}
+
+/// Human-readable byte count in binary units ("64 KiB", "1.5 MiB").
+struct Bytes(u64);
+
+impl fmt::Display for Bytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+        if self.0 < 1024 {
+            return write!(f, "{} bytes", self.0);
+        }
+        let mut value = self.0 as f64;
+        let mut unit = "";
+        for name in UNITS {
+            if value < 1024.0 {
+                break;
+            }
+            value /= 1024.0;
+            unit = name;
+        }
+        if value.fract() == 0.0 {
+            write!(f, "{value} {unit}")
+        } else {
+            write!(f, "{value:.1} {unit}")
+        }
+    }
+}
+
+/// A byte count as a systemd resource limit, rounded up to whole MiB ("64M").
+struct SystemdSize(u64);
+
+impl fmt::Display for SystemdSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}M", self.0.div_ceil(1024 * 1024).max(1))
+    }
+}
+
+/// A limit that may be unlimited.
+struct Limit(Option<u64>);
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(bytes) => Bytes(bytes).fmt(f),
+            None => f.write_str("unlimited"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bytes, MemlockLimit, SystemdSize, WorkingSetLimits};
+
+    #[test]
+    fn check_reports_both_sizes() {
+        let limit = MemlockLimit {
+            soft: Some(64 * 1024),
+            hard: None,
+        };
+        assert!(limit.check(4096).is_ok());
+        let err = limit.check(64 * 1024 * 1024).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+        assert_eq!(
+            err.to_string(),
+            "requested 64 MiB of locked memory, but RLIMIT_MEMLOCK is 64 KiB (hard limit unlimited)"
+        );
+        assert_eq!(Bytes(1536 * 1024).to_string(), "1.5 MiB");
+        assert_eq!(Bytes(100).to_string(), "100 bytes");
+        assert_eq!(SystemdSize(64 * 1024 * 1024 + 1).to_string(), "65M");
+        assert_eq!(SystemdSize(4096).to_string(), "1M");
+    }
+
+    #[test]
+    fn working_set_check_leaves_room_for_overhead() {
+        let page = crate::page_size();
+        let limits = WorkingSetLimits {
+            minimum: 64 * page,
+            maximum: 128 * page,
+            flags: 0x1,
+        };
+        assert!(limits.is_minimum_hard());
+        assert!(!limits.is_maximum_hard());
+        assert_eq!(limits.lockable(), 48 * page);
+        assert!(limits.check(48 * page as u64).is_ok());
+        let err = limits.check(64 * page as u64).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+        assert!(err.to_string().contains("minimum working set"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn limit_source_names_the_service_unit() {
+        use super::{memlock_limit_source, service_unit};
+
+        assert_eq!(
+            service_unit("0::/system.slice/vault.service\n"),
+            Some("vault.service")
+        );
+        assert_eq!(
+            service_unit(
+                "12:memory:/system.slice/app.service\n\
+                 1:name=systemd:/system.slice/app.service\n"
+            ),
+            Some("app.service")
+        );
+        assert_eq!(
+            service_unit("0::/user.slice/user-1000.slice/user@1000.service/app.slice/x.service\n"),
+            Some("x.service")
+        );
+        assert_eq!(service_unit("0::/user.slice/session-1.scope\n"), None);
+        memlock_limit_source().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn memlock_limit_is_queryable() {
+        let limit = super::memlock_limit().unwrap();
+        if let (Some(soft), Some(hard)) = (limit.soft, limit.hard) {
+            assert!(soft <= hard);
+        }
+        // Asking for what is already allowed is a no-op.
+        let same = super::raise_memlock_limit(limit.soft.unwrap_or(u64::MAX)).unwrap();
+        assert_eq!(same, limit);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn cgroup_memory_parses_and_classifies() {
+        use super::{
+            CgroupMemory, CgroupPressure, cgroup_memory, cgroup2_mount, cgroup2_path,
+            parse_memory_value,
+        };
+
+        let mountinfo = "25 1 0:22 / /sys rw - sysfs sysfs rw\n\
+                         42 32 0:38 / /sys/fs/cgroup/unified rw,relatime - cgroup2 cgroup2 rw\n";
+        assert_eq!(cgroup2_mount(mountinfo), Some("/sys/fs/cgroup/unified"));
+        assert_eq!(
+            cgroup2_path("4:memory:/a\n0::/system.slice/app.service\n"),
+            Some("/system.slice/app.service")
+        );
+        assert_eq!(parse_memory_value("max\n").unwrap(), None);
+        assert_eq!(parse_memory_value("1073741824\n").unwrap(), Some(1 << 30));
+
+        let memory = CgroupMemory {
+            current: 768 << 20,
+            high: Some(900 << 20),
+            max: Some(1 << 30),
+        };
+        assert_eq!(memory.pressure(64 << 20), CgroupPressure::Fits);
+        assert_eq!(memory.pressure(200 << 20), CgroupPressure::Reclaim);
+        assert_eq!(memory.pressure(512 << 20), CgroupPressure::Oom);
+        assert_eq!(
+            memory.check(512 << 20).unwrap_err().to_string(),
+            "locking 512 MiB would bring the cgroup to 1.2 GiB, past memory.max of 1 GiB; \
+             the cgroup would be OOM-killed"
+        );
+        cgroup_memory().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn namespaced_cgroup_limits_are_read_at_the_mount() {
+        use super::{CgroupMemory, cgroup_memory_at, cgroup2_path};
+
+        // Inside a cgroup namespace the process's cgroup is the root of the mount.
+        let mount = std::env::temp_dir().join(format!("os-memlock-cgroup-{}", std::process::id()));
+        std::fs::create_dir_all(&mount).unwrap();
+        for (file, value) in [
+            ("memory.current", "268435456\n"),
+            ("memory.high", "max\n"),
+            ("memory.max", "536870912\n"),
+        ] {
+            std::fs::write(mount.join(file), value).unwrap();
+        }
+        let path = cgroup2_path("0::/\n").unwrap();
+        let memory = cgroup_memory_at(&mount, path);
+        std::fs::remove_dir_all(&mount).unwrap();
+        assert_eq!(
+            memory.unwrap(),
+            Some(CgroupMemory {
+                current: 256 << 20,
+                high: None,
+                max: Some(512 << 20),
+            })
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn privileges_parse_caps_and_cgroups() {
+        use super::{container_cgroup, has_cap_ipc_lock, lock_privileges};
+
+        assert!(has_cap_ipc_lock("CapPrm:\t0\nCapEff:\t0000000000004000\n"));
+        assert!(!has_cap_ipc_lock("CapEff:\t000001fffeffbfff\n"));
+        assert_eq!(
+            container_cgroup(
+                "0::/kubepods.slice/kubepods-burstable.slice/cri-containerd-1.scope\n"
+            ),
+            Some("kubepods cgroup")
+        );
+        assert_eq!(container_cgroup("0::/user.slice/session-1.scope\n"), None);
+
+        let privileges = lock_privileges().unwrap();
+        assert!(privileges.to_string().starts_with("CAP_IPC_LOCK"));
+        assert_eq!(privileges.android_api_level(), None);
+
+        let android = super::LockPrivileges {
+            cap_ipc_lock: false,
+            container: None,
+            limit: Some(MemlockLimit {
+                soft: Some(64 * 1024),
+                hard: Some(64 * 1024),
+            }),
+            android_api_level: Some(34),
+            cgroup: None,
+        };
+        assert!(android.to_string().ends_with(
+            "Android API 34; apps cannot raise the limit, so lock only small keys or use \
+             SecureBuf::new_best_effort"
+        ));
+    }
+}
@@ -0,0 +1,95 @@
+//! Shared, reference-counted locked buffer.
+
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+use crate::SecureBuf;
+
+/// A cheaply clonable, read-only handle to a [`SecureBuf`].
+///
+/// Clones share one locked allocation. The last handle to be dropped zeroizes and
+/// unlocks it, so a key can be fanned out to many workers without copying the secret.
+#[derive(Clone)]
+pub struct LockedArc {
+    inner: Arc<SecureBuf>,
+}
+
+impl LockedArc {
+    /// Allocate a locked buffer, copy `bytes` into it, and wrap it for sharing.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        SecureBuf::from_slice(bytes).map(Self::from)
+    }
+
+    /// Borrow the shared contents.
+    pub fn as_slice(&self) -> &[u8] {
+        self.inner.as_slice()
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Whether the buffer has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Whether `mlock` succeeded for the underlying buffer.
+    pub fn is_locked(&self) -> bool {
+        self.inner.is_locked()
+    }
+
+    /// Number of live handles sharing this buffer.
+    pub fn strong_count(this: &Self) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+
+    /// Whether two handles share the same allocation.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.inner, &other.inner)
+    }
+
+    /// Recover exclusive ownership if this is the only handle.
+    ///
+    /// Returns the handle unchanged in `Err` when other clones are still alive.
+    pub fn try_unwrap(this: Self) -> Result<SecureBuf, Self> {
+        Arc::try_unwrap(this.inner).map_err(|inner| Self { inner })
+    }
+}
+
+impl From<SecureBuf> for LockedArc {
+    fn from(buf: SecureBuf) -> Self {
+        Self {
+            inner: Arc::new(buf),
+        }
+    }
+}
+
+impl fmt::Debug for LockedArc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedArc")
+            .field("buf", &*self.inner)
+            .field("strong_count", &Arc::strong_count(&self.inner))
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LockedArc;
+
+    #[test]
+    fn clones_share_one_allocation_until_last_owner() {
+        let a = LockedArc::from_slice(b"shared-key").unwrap();
+        let b = a.clone();
+        assert!(LockedArc::ptr_eq(&a, &b));
+        assert_eq!(LockedArc::strong_count(&a), 2);
+
+        let a = LockedArc::try_unwrap(a).unwrap_err();
+        drop(b);
+        let owned = LockedArc::try_unwrap(a).unwrap();
+        assert_eq!(owned.as_slice(), b"shared-key");
+    }
+}
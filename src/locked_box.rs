@@ -0,0 +1,130 @@
+//! Typed value stored inside a locked, page-aligned allocation.
+
+use std::fmt;
+use std::io;
+use std::mem::{self, ManuallyDrop};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+
+use crate::SecureBuf;
+
+/// Owns a `T` placed in a [`SecureBuf`] so its bytes live in locked memory.
+///
+/// The value is dropped in place first; the backing buffer then zeroizes the
+/// raw bytes before unlocking and freeing them. Only the inline bytes of `T` are
+/// protected: heap data owned by `T` (e.g., a `Vec` field) lives elsewhere, so key
+/// material should be stored inline, such as in fixed-size arrays.
+///
+/// This is the storage used by `#[derive(LockedSecret)]` containers.
+pub struct LockedBox<T> {
+    ptr: NonNull<T>,
+    // Keeps the allocation alive; `ptr` points into it (or is dangling for ZSTs).
+    _storage: SecureBuf,
+}
+
+// Safety: LockedBox<T> owns its T like Box<T>.
+unsafe impl<T: Send> Send for LockedBox<T> {}
+// Safety: shared access only hands out `&T`.
+unsafe impl<T: Sync> Sync for LockedBox<T> {}
+
+/// A type with a generated locked container, usually via `#[derive(LockedSecret)]`.
+///
+/// With the `derive` feature, `#[derive(LockedSecret)]` on `struct Keys` generates a
+/// `LockedKeys` wrapper around [`LockedBox<Keys>`] that derefs to `Keys`, hides its
+/// contents from `Debug`, and zeroizes and unlocks on drop.
+pub trait LockedSecret: Sized {
+    /// The locked container type for `Self`.
+    type Locked;
+
+    /// Move `self` into a freshly allocated locked container.
+    fn into_locked(self) -> io::Result<Self::Locked>;
+}
+
+impl<T> LockedBox<T> {
+    /// Allocate locked storage sized for `T` without initializing it.
+    pub(crate) fn storage() -> io::Result<SecureBuf> {
+        if mem::align_of::<T>() > crate::page_size() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "type alignment exceeds the page size",
+            ));
+        }
+        SecureBuf::new(mem::size_of::<T>())
+    }
+
+    /// Move `value` into previously allocated `storage` from [`LockedBox::storage`].
+    pub(crate) fn with_storage(mut storage: SecureBuf, value: T) -> Self {
+        debug_assert_eq!(storage.len(), mem::size_of::<T>());
+        let ptr = if mem::size_of::<T>() == 0 {
+            NonNull::dangling()
+        } else {
+            // Page-aligned storage satisfies any alignment checked in `storage`.
+            NonNull::new(storage.as_mut_slice().as_mut_ptr().cast::<T>())
+                .expect("non-empty SecureBuf has a non-null pointer")
+        };
+        // Safety: ptr is valid and suitably aligned for a T and currently uninitialized.
+        unsafe { ptr.as_ptr().write(value) };
+        Self {
+            ptr,
+            _storage: storage,
+        }
+    }
+
+    /// Allocate locked storage and move `value` into it.
+    ///
+    /// `value` passes through the caller's stack on the way in; construct it as close
+    /// to this call as possible.
+    pub fn new(value: T) -> io::Result<Self> {
+        Ok(Self::with_storage(Self::storage()?, value))
+    }
+
+    /// Whether the backing storage is locked in RAM.
+    pub fn is_locked(this: &Self) -> bool {
+        this._storage.is_locked()
+    }
+
+    /// Move the value out of locked memory; the storage is zeroized and released.
+    pub fn into_inner(this: Self) -> T {
+        let this = ManuallyDrop::new(this);
+        // Safety: the value is initialized and `this` will not drop it again;
+        // the storage is read out exactly once and dropped here.
+        unsafe {
+            let value = this.ptr.as_ptr().read();
+            drop(ptr::read(&this._storage));
+            value
+        }
+    }
+}
+
+impl<T> Deref for LockedBox<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: ptr holds an initialized T for the lifetime of self.
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for LockedBox<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: as above, and &mut self guarantees exclusive access.
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> fmt::Debug for LockedBox<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the value.
+        f.debug_struct("LockedBox")
+            .field("storage", &self._storage)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Drop for LockedBox<T> {
+    fn drop(&mut self) {
+        // Safety: the value is initialized and never used again; the storage field
+        // is dropped afterwards and zeroizes the bytes.
+        unsafe { ptr::drop_in_place(self.ptr.as_ptr()) };
+    }
+}
@@ -0,0 +1,294 @@
+//! Many small secrets in one locked, dump-excluded private heap (Windows).
+
+use std::fmt;
+use std::io;
+use std::ptr::NonNull;
+use std::slice;
+
+use crate::zeroize::zeroize;
+
+/// A private heap (`HeapCreate`) for many small secrets, without one `VirtualAlloc` per
+/// secret.
+///
+/// Behavior:
+/// - The heap has a fixed size, reserved up front and registered with
+///   [`exclude_from_dumps`](crate::exclude_from_dumps) as a whole.
+/// - Pages are committed as allocations need them; each allocation `VirtualLock`s the
+///   pages under it before it is handed out, so every secret is locked, and pages stay
+///   locked until the heap is destroyed.
+/// - [`HeapSecret`]s are zeroized and freed on drop; they borrow the heap, so it outlives
+///   them.
+///
+/// Locked heap pages are not counted by [`locked_bytes`](crate::locked_bytes), since
+/// allocations share pages.
+///
+/// Platform:
+/// - Windows. Elsewhere, [`LockedHeap::new`] returns `Unsupported`; use
+///   [`SecureBuf`](crate::SecureBuf) there.
+pub struct LockedHeap {
+    heap: imp::Heap,
+}
+
+impl LockedHeap {
+    /// Create a heap able to hold `capacity` bytes of secrets, plus the heap's own
+    /// bookkeeping.
+    ///
+    /// Returns:
+    /// - `Ok(heap)` once the heap is reserved and dump-excluded
+    /// - `Err(Unsupported)` on platforms other than Windows
+    /// - `Err(...)` with the OS error if the heap could not be created
+    pub fn new(capacity: usize) -> io::Result<Self> {
+        Ok(Self {
+            heap: imp::Heap::create(capacity)?,
+        })
+    }
+
+    /// Allocate `len` zeroed, locked bytes from the heap.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` once the allocation is locked
+    /// - `Err(OutOfMemory)` if the heap is full, or if locking exceeded the working-set
+    ///   quota (see [`mlock_grow_working_set`](crate::mlock_grow_working_set))
+    /// - `Err(...)` with the OS error if locking failed otherwise
+    pub fn alloc(&self, len: usize) -> io::Result<HeapSecret<'_>> {
+        let ptr = self.heap.alloc(len)?;
+        // Safety: the block just allocated, `len` bytes long.
+        if let Err(e) = unsafe { crate::mlock(ptr.as_ptr() as *const _, len) } {
+            // Safety: as above; never handed out.
+            unsafe { self.heap.free(ptr) };
+            return Err(e);
+        }
+        Ok(HeapSecret {
+            heap: self,
+            ptr,
+            len,
+        })
+    }
+
+    /// Allocate from the heap and copy `bytes` into it.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    pub fn alloc_from_slice(&self, bytes: &[u8]) -> io::Result<HeapSecret<'_>> {
+        let mut secret = self.alloc(bytes.len())?;
+        secret.as_mut_slice().copy_from_slice(bytes);
+        Ok(secret)
+    }
+}
+
+impl fmt::Debug for LockedHeap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedHeap").finish_non_exhaustive()
+    }
+}
+
+/// A secret allocated from a [`LockedHeap`]; zeroized and freed on drop.
+pub struct HeapSecret<'heap> {
+    heap: &'heap LockedHeap,
+    ptr: NonNull<u8>,
+    len: usize,
+}
+
+// Safety: HeapSecret uniquely owns its block, and the heap serializes its own calls.
+unsafe impl Send for HeapSecret<'_> {}
+// Safety: shared access only hands out `&[u8]`.
+unsafe impl Sync for HeapSecret<'_> {}
+
+impl HeapSecret<'_> {
+    /// Borrow the contents.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid for `len` bytes while `self` lives.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Mutably borrow the contents.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // Safety: as above, and `&mut self` is exclusive.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the secret has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl Drop for HeapSecret<'_> {
+    fn drop(&mut self) {
+        zeroize(self.as_mut_slice());
+        // Safety: the block allocated in `LockedHeap::alloc`, no longer referenced.
+        unsafe { self.heap.heap.free(self.ptr) };
+    }
+}
+
+impl fmt::Debug for HeapSecret<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("HeapSecret")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr::NonNull;
+
+    use windows_sys::Win32::Foundation::HANDLE;
+    use windows_sys::Win32::System::Memory::{
+        HEAP_ZERO_MEMORY, HeapAlloc, HeapCreate, HeapDestroy, HeapFree, MEMORY_BASIC_INFORMATION,
+        VirtualQuery,
+    };
+
+    pub(super) struct Heap {
+        handle: HANDLE,
+        /// The heap's reservation, if it could be found.
+        region: Option<(*mut c_void, usize)>,
+        /// Whether `region` is registered with `exclude_from_dumps`.
+        excluded: bool,
+    }
+
+    // Safety: heap calls are serialized by the heap itself (no HEAP_NO_SERIALIZE).
+    unsafe impl Send for Heap {}
+    // Safety: as above.
+    unsafe impl Sync for Heap {}
+
+    /// The whole reservation containing `addr`: its base and length.
+    fn reservation(addr: *const c_void) -> io::Result<(*mut c_void, usize)> {
+        let mut info = MEMORY_BASIC_INFORMATION::default();
+        let size = size_of::<MEMORY_BASIC_INFORMATION>();
+        // Safety: `info` is a valid out-buffer of `size` bytes.
+        if unsafe { VirtualQuery(addr, &mut info, size) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let base = info.AllocationBase;
+        let mut end = base as usize;
+        // Safety: as above; querying any address is allowed.
+        while unsafe { VirtualQuery(end as *const c_void, &mut info, size) } != 0
+            && info.AllocationBase == base
+        {
+            end += info.RegionSize;
+        }
+        Ok((base, end - base as usize))
+    }
+
+    impl Heap {
+        pub(super) fn create(capacity: usize) -> io::Result<Self> {
+            // A non-zero maximum makes the heap fixed-size: one reservation that never
+            // grows, so one dump exclusion covers it.
+            let max = capacity
+                .checked_add(capacity / 8 + 64 * 1024)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+            // Safety: plain call; the heap is owned by the returned value.
+            let handle = unsafe { HeapCreate(0, 0, max) };
+            if handle.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let region = reservation(handle).ok();
+            let excluded = region.is_some_and(|(base, len)| {
+                crate::fork::track(base.cast(), len);
+                // Safety: the heap's own reservation, unregistered before it is destroyed.
+                unsafe { crate::exclude_from_dumps(base, len) }.is_ok()
+            });
+            Ok(Self {
+                handle,
+                region,
+                excluded,
+            })
+        }
+
+        pub(super) fn alloc(&self, len: usize) -> io::Result<NonNull<u8>> {
+            // Safety: a live heap handle.
+            let ptr = unsafe { HeapAlloc(self.handle, HEAP_ZERO_MEMORY, len) };
+            NonNull::new(ptr.cast()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::OutOfMemory,
+                    format!("LockedHeap has no room for {len} more bytes"),
+                )
+            })
+        }
+
+        /// # Safety
+        /// `ptr` must come from [`Heap::alloc`] on this heap and no longer be referenced.
+        pub(super) unsafe fn free(&self, ptr: NonNull<u8>) {
+            // Safety: guaranteed by the caller.
+            unsafe { HeapFree(self.handle, 0, ptr.as_ptr().cast()) };
+        }
+    }
+
+    impl Drop for Heap {
+        fn drop(&mut self) {
+            if let Some((base, len)) = self.region {
+                crate::fork::untrack(base.cast());
+                if self.excluded {
+                    // Safety: registered in `create`; the memory is still mapped.
+                    let _ = unsafe { crate::include_in_dumps(base, len) };
+                }
+            }
+            // Safety: every HeapSecret borrowed the heap and is gone. Destroying the heap
+            // releases its pages, locked or not.
+            if unsafe { HeapDestroy(self.handle) } == 0 {
+                diag!(
+                    "destroying the locked heap failed: {}",
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+    use std::ptr::NonNull;
+
+    pub(super) struct Heap;
+
+    impl Heap {
+        pub(super) fn create(_capacity: usize) -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "LockedHeap is Windows-only; use SecureBuf",
+            ))
+        }
+
+        pub(super) fn alloc(&self, _len: usize) -> io::Result<NonNull<u8>> {
+            unreachable!("create fails first")
+        }
+
+        /// # Safety
+        /// Never called: no heaps are created on this platform.
+        pub(super) unsafe fn free(&self, _ptr: NonNull<u8>) {
+            unreachable!("create fails first")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::LockedHeap;
+
+    #[test]
+    fn heap_holds_many_secrets_or_is_unsupported() {
+        let heap = match LockedHeap::new(64 * 1024) {
+            Ok(heap) => heap,
+            Err(e) if e.kind() == ErrorKind::Unsupported => return,
+            Err(e) => panic!("{e}"),
+        };
+        let secrets: Vec<_> = (0u8..32)
+            .map(|i| heap.alloc_from_slice(&[i; 32]).unwrap())
+            .collect();
+        for (i, secret) in secrets.iter().enumerate() {
+            assert_eq!(secret.as_slice(), [i as u8; 32]);
+        }
+        assert!(!format!("{:?}", secrets[1]).contains('1'));
+    }
+}
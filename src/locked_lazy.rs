@@ -0,0 +1,96 @@
+//! Lazily-initialized locked statics and the `locked_static!` macro.
+
+use std::fmt;
+use std::io;
+use std::ops::Deref;
+
+use crate::LockedOnce;
+
+/// A `LazyLock`-style value in locked memory, initialized on first access.
+///
+/// Usually declared through [`locked_static!`](crate::locked_static) rather than by hand.
+/// Dereferencing forces initialization; the storage is page-aligned, locked, and
+/// dump-excluded where the platform allows.
+pub struct LockedLazy<T, F = fn() -> T> {
+    once: LockedOnce<T>,
+    init: F,
+}
+
+impl<T, F: Fn() -> T> LockedLazy<T, F> {
+    /// Create a lazy value that runs `init` on first access.
+    pub const fn new(init: F) -> Self {
+        Self {
+            once: LockedOnce::new(),
+            init,
+        }
+    }
+
+    /// Force initialization and return the value, reporting allocation failures.
+    pub fn try_force(this: &Self) -> io::Result<&T> {
+        this.once.get_or_try_init(&this.init)
+    }
+
+    /// Force initialization and return the value.
+    ///
+    /// # Panics
+    /// Panics if locked storage cannot be allocated; see [`LockedLazy::try_force`].
+    pub fn force(this: &Self) -> &T {
+        this.once.get_or_init(&this.init)
+    }
+}
+
+impl<T, F: Fn() -> T> Deref for LockedLazy<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        Self::force(self)
+    }
+}
+
+impl<T, F> fmt::Debug for LockedLazy<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LockedLazy")
+            .field("once", &self.once)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Declare statics whose values live in locked, dump-excluded memory.
+///
+/// Each item expands to a [`LockedLazy`] static that allocates a page-aligned,
+/// locked region and runs its initializer on first access.
+///
+/// ```no_run
+/// os_memlock::locked_static! {
+///     /// Process-wide master key.
+///     static MASTER_KEY: [u8; 32] = [0u8; 32];
+/// }
+///
+/// let key: &[u8; 32] = &MASTER_KEY;
+/// # let _ = key;
+/// ```
+#[macro_export]
+macro_rules! locked_static {
+    () => {};
+    ($(#[$attr:meta])* $vis:vis static $name:ident : $ty:ty = $init:expr; $($rest:tt)*) => {
+        $(#[$attr])*
+        $vis static $name: $crate::LockedLazy<$ty> = $crate::LockedLazy::new(|| $init);
+        $crate::locked_static!($($rest)*);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LockedLazy;
+
+    crate::locked_static! {
+        static SESSION_KEY: [u8; 16] = [3u8; 16];
+        pub(crate) static COUNTER_SEED: u64 = 42;
+    }
+
+    #[test]
+    fn statics_initialize_on_first_access() {
+        assert_eq!(*SESSION_KEY, [3u8; 16]);
+        assert_eq!(*LockedLazy::try_force(&COUNTER_SEED).unwrap(), 42);
+    }
+}
@@ -0,0 +1,109 @@
+//! Lazily-initialized value in locked memory, usable from statics.
+
+use std::fmt;
+use std::io;
+use std::sync::OnceLock;
+
+use crate::locked_box::LockedBox;
+
+/// A `OnceLock`-style cell whose value lives in locked, dump-excluded memory.
+///
+/// Storage is allocated and locked on first initialization, so a `LockedOnce` can be
+/// declared as a `static` for a process-global key without `static mut`.
+///
+/// Statics are never dropped: the value stays locked for the lifetime of the process.
+/// A non-static `LockedOnce` zeroizes and unlocks its storage on drop.
+pub struct LockedOnce<T> {
+    cell: OnceLock<LockedBox<T>>,
+}
+
+impl<T> LockedOnce<T> {
+    /// Create an empty cell. No memory is allocated until first initialization.
+    pub const fn new() -> Self {
+        Self {
+            cell: OnceLock::new(),
+        }
+    }
+
+    /// Get the value if the cell has been initialized.
+    pub fn get(&self) -> Option<&T> {
+        self.cell.get().map(|b| &**b)
+    }
+
+    /// Get the value, initializing it with `f` if the cell is empty.
+    ///
+    /// Locked storage is allocated before `f` runs. If another thread wins the race,
+    /// the spare storage is released and `f` is not called.
+    ///
+    /// Returns:
+    /// - `Ok(&T)` once the cell holds a value.
+    /// - `Err(...)` if locked storage could not be allocated; the cell stays empty.
+    pub fn get_or_try_init<F>(&self, f: F) -> io::Result<&T>
+    where
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        let storage = LockedBox::<T>::storage()?;
+        Ok(self
+            .cell
+            .get_or_init(|| LockedBox::with_storage(storage, f())))
+    }
+
+    /// Get the value, initializing it with `f` if the cell is empty.
+    ///
+    /// # Panics
+    /// Panics if locked storage cannot be allocated; use
+    /// [`LockedOnce::get_or_try_init`] to handle that case.
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        match self.get_or_try_init(f) {
+            Ok(value) => value,
+            Err(e) => panic!("os-memlock: failed to allocate locked storage: {e}"),
+        }
+    }
+
+    /// Initialize the cell with `value`.
+    ///
+    /// Returns `Ok(Err(value))` if the cell was already initialized, and `Err(...)`
+    /// if locked storage could not be allocated.
+    pub fn set(&self, value: T) -> io::Result<Result<(), T>> {
+        if self.get().is_some() {
+            return Ok(Err(value));
+        }
+        let boxed = LockedBox::new(value)?;
+        Ok(self.cell.set(boxed).map_err(LockedBox::into_inner))
+    }
+}
+
+impl<T> Default for LockedOnce<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for LockedOnce<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the value.
+        f.debug_struct("LockedOnce")
+            .field("initialized", &self.get().is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LockedOnce;
+
+    static MASTER_KEY: LockedOnce<[u8; 32]> = LockedOnce::new();
+
+    #[test]
+    fn static_cell_initializes_once() {
+        assert_eq!(MASTER_KEY.get_or_init(|| [7; 32]), &[7; 32]);
+        assert_eq!(MASTER_KEY.get_or_init(|| [9; 32]), &[7; 32]);
+        assert_eq!(MASTER_KEY.set([1; 32]).unwrap(), Err([1; 32]));
+    }
+}
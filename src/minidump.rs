@@ -0,0 +1,193 @@
+//! Keeping secret regions out of minidumps written with `MiniDumpWriteDump` (Windows).
+//!
+//! [`exclude_from_dumps`](crate::exclude_from_dumps) only reaches dumps collected by
+//! Windows Error Reporting. Crash reporters that call `MiniDumpWriteDump` themselves pass
+//! [`MiniDumpExclusions`] as the dump callback to get the same protection.
+
+use std::ffi::c_void;
+use std::fmt;
+
+use windows_sys::Win32::System::Diagnostics::Debug::{
+    IncludeModuleCallback, IncludeThreadCallback, MINIDUMP_CALLBACK_INFORMATION,
+    MINIDUMP_CALLBACK_INPUT, MINIDUMP_CALLBACK_OUTPUT, MINIDUMP_CALLBACK_OUTPUT_0_0,
+    MINIDUMP_CALLBACK_ROUTINE, ModuleCallback, RemoveMemoryCallback, ThreadCallback,
+    ThreadExCallback,
+};
+use windows_sys::core::BOOL;
+
+/// Largest range handed to dbghelp at once; `MemorySize` is a 32-bit field.
+const MAX_RANGE: usize = 1 << 31;
+
+/// A `MiniDumpWriteDump` callback that removes every live secret region from the dump.
+///
+/// The regions are those owned by [`SecureBuf`](crate::SecureBuf) (and every container
+/// built on it), [`PhysicalBuf`](crate::PhysicalBuf) and [`LockedHeap`](crate::LockedHeap),
+/// copied when the value is created with [`snapshot`](Self::snapshot).
+///
+/// Behavior:
+/// - Answers `RemoveMemoryCallback` with one region per call until all are removed.
+/// - Other callbacks go to the routine set with [`chain`](Self::chain). Without one,
+///   threads and modules are kept with dbghelp's default flags and every other callback
+///   is declined, as in the `MiniDumpCallback` documentation sample.
+/// - Regions are removed from the memory dbghelp collects for the dump type; check that
+///   dumps with `MiniDumpWithFullMemory` leave them out on the dbghelp version you ship.
+///
+/// ```ignore
+/// let mut exclusions = os_memlock::MiniDumpExclusions::snapshot();
+/// let callback = exclusions.callback_information();
+/// MiniDumpWriteDump(process, pid, file, MiniDumpNormal, exception, ptr::null(), &callback);
+/// ```
+pub struct MiniDumpExclusions {
+    ranges: Vec<(u64, u32)>,
+    next: usize,
+    chained: MINIDUMP_CALLBACK_ROUTINE,
+    chained_param: *mut c_void,
+}
+
+impl MiniDumpExclusions {
+    /// Record the secret regions alive right now.
+    ///
+    /// Buffers created later are not covered; take the snapshot right before writing the
+    /// dump. It briefly takes the same lock as buffer creation and drop.
+    pub fn snapshot() -> Self {
+        let ranges = crate::fork::with_regions(|regions| {
+            regions
+                .iter()
+                .flat_map(|region| split(region.addr, region.len))
+                .collect()
+        });
+        Self {
+            ranges,
+            next: 0,
+            chained: None,
+            chained_param: std::ptr::null_mut(),
+        }
+    }
+
+    /// Forward callbacks to `routine`, called with `param`, once the secret regions are
+    /// removed; `RemoveMemoryCallback` reaches it after the last region.
+    pub fn chain(mut self, routine: MINIDUMP_CALLBACK_ROUTINE, param: *mut c_void) -> Self {
+        self.chained = routine;
+        self.chained_param = param;
+        self
+    }
+
+    /// The `CallbackParam` argument for `MiniDumpWriteDump`.
+    ///
+    /// The result points at `self`: keep `self` alive and in place until
+    /// `MiniDumpWriteDump` returns. Each call restarts the list of regions, so one
+    /// snapshot can serve several dumps.
+    pub fn callback_information(&mut self) -> MINIDUMP_CALLBACK_INFORMATION {
+        self.next = 0;
+        MINIDUMP_CALLBACK_INFORMATION {
+            CallbackRoutine: Some(callback),
+            CallbackParam: (self as *mut Self).cast(),
+        }
+    }
+
+    /// Number of ranges that will be removed; regions over 2 GiB count once per 2 GiB.
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    /// Whether no secret region was alive at the snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+impl fmt::Debug for MiniDumpExclusions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MiniDumpExclusions")
+            .field("ranges", &self.ranges.len())
+            .field("chained", &self.chained.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+/// `(addr, len)` as dbghelp ranges of at most [`MAX_RANGE`] bytes.
+fn split(addr: usize, len: usize) -> impl Iterator<Item = (u64, u32)> {
+    (0..len)
+        .step_by(MAX_RANGE)
+        .map(move |offset| ((addr + offset) as u64, (len - offset).min(MAX_RANGE) as u32))
+}
+
+/// # Safety
+/// `param` must come from [`MiniDumpExclusions::callback_information`] and dbghelp must
+/// pass valid input and output records.
+unsafe extern "system" fn callback(
+    param: *mut c_void,
+    input: *const MINIDUMP_CALLBACK_INPUT,
+    output: *mut MINIDUMP_CALLBACK_OUTPUT,
+) -> BOOL {
+    // Safety: guaranteed by the caller; dbghelp calls back on the writing thread only.
+    let this = unsafe { &mut *param.cast::<MiniDumpExclusions>() };
+    // Safety: as above.
+    let kind = unsafe { (*input).CallbackType } as i32;
+    if kind == RemoveMemoryCallback
+        && let Some(&(base, size)) = this.ranges.get(this.next)
+    {
+        this.next += 1;
+        // Safety: as above; the memory fields are the ones read for this callback.
+        unsafe {
+            (*output).Anonymous.Anonymous1 = MINIDUMP_CALLBACK_OUTPUT_0_0 {
+                MemoryBase: base,
+                MemorySize: size,
+            };
+        }
+        return 1;
+    }
+    if let Some(routine) = this.chained {
+        // Safety: the chained routine was handed to us for exactly these calls.
+        return unsafe { routine(this.chained_param, input, output) };
+    }
+    matches!(
+        kind,
+        IncludeThreadCallback
+            | IncludeModuleCallback
+            | ThreadCallback
+            | ThreadExCallback
+            | ModuleCallback
+    ) as BOOL
+}
+
+#[cfg(test)]
+mod tests {
+    use windows_sys::Win32::System::Diagnostics::Debug::{
+        MINIDUMP_CALLBACK_INPUT, MINIDUMP_CALLBACK_OUTPUT, RemoveMemoryCallback,
+    };
+
+    use super::{MAX_RANGE, MiniDumpExclusions, split};
+    use crate::SecureBuf;
+
+    #[test]
+    fn callback_removes_every_live_buffer() {
+        let buf = SecureBuf::from_slice(b"dump-me-not").unwrap();
+        let addr = buf.as_slice().as_ptr() as u64;
+        let mut exclusions = MiniDumpExclusions::snapshot();
+        let info = exclusions.callback_information();
+        let routine = info.CallbackRoutine.unwrap();
+
+        let input = MINIDUMP_CALLBACK_INPUT {
+            CallbackType: RemoveMemoryCallback as u32,
+            ..Default::default()
+        };
+        let mut removed = Vec::new();
+        loop {
+            let mut output = MINIDUMP_CALLBACK_OUTPUT::default();
+            // Safety: `info` points at `exclusions`, alive for the whole loop.
+            if unsafe { routine(info.CallbackParam, &input, &mut output) } == 0 {
+                break;
+            }
+            // Safety: the callback filled in the memory fields.
+            let range = unsafe { output.Anonymous.Anonymous1 };
+            removed.push(range.MemoryBase);
+        }
+        assert!(removed.contains(&addr));
+        assert!(!format!("{exclusions:?}").contains("dump-me-not"));
+
+        assert_eq!(split(0x1000, 0).count(), 0);
+        let halves: Vec<_> = split(0, MAX_RANGE + 1).collect();
+        assert_eq!(halves, [(0, MAX_RANGE as u32), (MAX_RANGE as u64, 1)]);
+    }
+}
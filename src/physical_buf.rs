@@ -0,0 +1,253 @@
+//! Secrets in AWE physical pages, which Windows never pages out.
+
+use std::fmt;
+use std::io;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::Ordering;
+
+use crate::secure_buf::LOCKED_BYTES;
+use crate::zeroize::zeroize;
+
+/// A buffer in physical pages allocated with Address Windowing Extensions
+/// (`AllocateUserPhysicalPages`) and mapped with `MapUserPhysicalPages`.
+///
+/// Behavior:
+/// - The pages belong to the process, not to its working set: the memory manager cannot
+///   page, trim or move them, regardless of working-set quotas. `VirtualLock` only
+///   promises to keep pages in the working set while the process is running.
+/// - The region is rounded up to whole pages; the whole region is zeroized on drop,
+///   before the pages are unmapped and freed.
+///
+/// Platform:
+/// - Windows, for accounts with the "Lock pages in memory" user right
+///   (`SeLockMemoryPrivilege`, enabled by [`PhysicalBuf::new`]). Elsewhere,
+///   [`PhysicalBuf::new`] returns `Unsupported`.
+pub struct PhysicalBuf {
+    ptr: NonNull<u8>,
+    len: usize,
+    cap: usize,
+    pages: imp::Pages,
+}
+
+// Safety: PhysicalBuf uniquely owns its mapping and pages, like Box<[u8]>.
+unsafe impl Send for PhysicalBuf {}
+// Safety: shared access only hands out `&[u8]`.
+unsafe impl Sync for PhysicalBuf {}
+
+impl PhysicalBuf {
+    /// Allocate a zero-filled buffer of `len` bytes in physical pages.
+    ///
+    /// Returns:
+    /// - `Ok(buf)` once every page is allocated and mapped.
+    /// - `Err(PermissionDenied)` if the account lacks `SeLockMemoryPrivilege`.
+    /// - `Err(OutOfMemory)` if fewer physical pages than needed were available.
+    /// - `Err(Unsupported)` on platforms other than Windows.
+    /// - `Err(...)` with the OS error if allocating or mapping failed.
+    pub fn new(len: usize) -> io::Result<Self> {
+        let cap = len
+            .max(1)
+            .checked_next_multiple_of(crate::page_size())
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        let (ptr, pages) = imp::allocate(cap)?;
+        LOCKED_BYTES.fetch_add(cap, Ordering::Relaxed);
+        crate::fork::track(ptr.as_ptr(), cap);
+        let mut buf = Self {
+            ptr,
+            len,
+            cap,
+            pages,
+        };
+        // Fresh physical pages are not guaranteed to be zeroed.
+        zeroize(buf.region_mut());
+        Ok(buf)
+    }
+
+    /// Allocate a buffer in physical pages and copy `bytes` into it.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        let mut buf = Self::new(bytes.len())?;
+        buf.as_mut_slice().copy_from_slice(bytes);
+        Ok(buf)
+    }
+
+    /// Borrow the contents.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid for `cap >= len` bytes while `self` lives.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Mutably borrow the contents.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // Safety: as above, and `&mut self` is exclusive.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn region_mut(&mut self) -> &mut [u8] {
+        // Safety: ptr is valid for `cap` bytes and exclusively owned.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.cap) }
+    }
+}
+
+impl Drop for PhysicalBuf {
+    fn drop(&mut self) {
+        crate::fork::untrack(self.ptr.as_ptr());
+        zeroize(self.region_mut());
+        LOCKED_BYTES.fetch_sub(self.cap, Ordering::Relaxed);
+        // Safety: the mapping and pages created in `new`, no longer referenced.
+        if let Err(e) = unsafe { imp::release(self.ptr, &mut self.pages) } {
+            diag!("freeing physical pages failed: {e}");
+        }
+    }
+}
+
+impl fmt::Debug for PhysicalBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("PhysicalBuf")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr::{self, NonNull};
+
+    use windows_sys::Win32::System::Memory::{
+        AllocateUserPhysicalPages, FreeUserPhysicalPages, MEM_RELEASE, MEM_RESERVE,
+        MapUserPhysicalPages, PAGE_READWRITE, VirtualAlloc, VirtualFree,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    /// `MEM_PHYSICAL` from <winnt.h>. windows-sys has it only under
+    /// `Win32::System::SystemServices`, a large feature not worth enabling for one flag.
+    const MEM_PHYSICAL: u32 = 0x0040_0000;
+
+    /// The page frame numbers backing a buffer.
+    pub(super) struct Pages(Vec<usize>);
+
+    fn free_pages(pages: &Pages) -> io::Result<()> {
+        let mut count = pages.0.len();
+        // Safety: frame numbers returned by AllocateUserPhysicalPages, now unmapped.
+        if unsafe { FreeUserPhysicalPages(GetCurrentProcess(), &mut count, pages.0.as_ptr()) } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(super) fn allocate(cap: usize) -> io::Result<(NonNull<u8>, Pages)> {
+        crate::windows::enable_lock_memory_privilege()?;
+        let wanted = cap / crate::page_size();
+        let mut count = wanted;
+        let mut frames = vec![0usize; wanted];
+        // Safety: `frames` has room for `count` frame numbers.
+        if unsafe {
+            AllocateUserPhysicalPages(GetCurrentProcess(), &mut count, frames.as_mut_ptr())
+        } == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        frames.truncate(count);
+        let pages = Pages(frames);
+        if count < wanted {
+            let _ = free_pages(&pages);
+            return Err(io::Error::new(
+                io::ErrorKind::OutOfMemory,
+                format!("only {count} of {wanted} physical pages were available"),
+            ));
+        }
+        // Safety: a new reservation; no existing memory is touched.
+        let addr =
+            unsafe { VirtualAlloc(ptr::null(), cap, MEM_RESERVE | MEM_PHYSICAL, PAGE_READWRITE) };
+        let Some(ptr) = NonNull::new(addr.cast::<u8>()) else {
+            let err = io::Error::last_os_error();
+            let _ = free_pages(&pages);
+            return Err(err);
+        };
+        // Safety: the reservation holds exactly `count` pages.
+        if unsafe { MapUserPhysicalPages(addr, count, pages.0.as_ptr()) } == 0 {
+            let err = io::Error::last_os_error();
+            // Safety: the reservation created above, with nothing mapped.
+            unsafe { VirtualFree(addr, 0, MEM_RELEASE) };
+            let _ = free_pages(&pages);
+            return Err(err);
+        }
+        Ok((ptr, pages))
+    }
+
+    /// # Safety
+    /// `ptr` and `pages` must come from one [`allocate`] call, no longer referenced.
+    pub(super) unsafe fn release(ptr: NonNull<u8>, pages: &mut Pages) -> io::Result<()> {
+        let addr: *mut c_void = ptr.as_ptr().cast();
+        // Safety: guaranteed by the caller; a null array unmaps the pages.
+        if unsafe { MapUserPhysicalPages(addr, pages.0.len(), ptr::null()) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Safety: as above; MEM_RELEASE frees the whole reservation.
+        if unsafe { VirtualFree(addr, 0, MEM_RELEASE) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        free_pages(pages)
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+    use std::ptr::NonNull;
+
+    pub(super) struct Pages;
+
+    pub(super) fn allocate(_cap: usize) -> io::Result<(NonNull<u8>, Pages)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "physical (AWE) pages are Windows-only",
+        ))
+    }
+
+    /// # Safety
+    /// Never called: no buffers are created on this platform.
+    pub(super) unsafe fn release(_ptr: NonNull<u8>, _pages: &mut Pages) -> io::Result<()> {
+        unreachable!("allocate fails first")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::PhysicalBuf;
+
+    #[test]
+    fn physical_buf_holds_a_secret_or_is_refused() {
+        let buf = match PhysicalBuf::from_slice(b"master-key") {
+            Ok(buf) => buf,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::Unsupported | ErrorKind::PermissionDenied
+                ) =>
+            {
+                return;
+            }
+            Err(e) => panic!("{e}"),
+        };
+        assert_eq!(buf.as_slice(), b"master-key");
+        assert!(!format!("{buf:?}").contains("master-key"));
+    }
+}
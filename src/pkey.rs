@@ -0,0 +1,294 @@
+//! Secrets gated by x86 memory protection keys.
+
+use std::fmt;
+use std::io;
+use std::mem::ManuallyDrop;
+
+use crate::SecureBuf;
+
+/// A locked buffer tagged with its own memory protection key, inaccessible outside
+/// [`PkeyBuf::with_access`] and [`PkeyBuf::with_access_mut`].
+///
+/// Behavior:
+/// - The buffer is a [`secure_mmap`](crate::secure_mmap) mapping assigned a freshly
+///   allocated key with `pkey_mprotect(2)`. Rights for a key live in the per-thread
+///   PKRU register, so granting and revoking access is one unprivileged `WRPKRU`
+///   instruction instead of an `mprotect` syscall and TLB shootdown.
+/// - Access is granted only to the thread running a `with_access*` closure, and only
+///   for its duration (including when it panics). Other threads start with every key
+///   but the default one denied, so any stray read or write faults with `SIGSEGV`.
+/// - On drop, access is granted once more to zeroize the buffer, then the key is freed.
+///
+/// This contains accidental or gadget-driven reads elsewhere in the process; it does
+/// not stop an attacker who can execute `WRPKRU` themselves.
+///
+/// Platform:
+/// - Linux on x86_64 CPUs with protection keys (PKU). Elsewhere,
+///   [`PkeyBuf::new`] returns `Unsupported`.
+pub struct PkeyBuf {
+    buf: ManuallyDrop<SecureBuf>,
+    pkey: i32,
+}
+
+impl PkeyBuf {
+    /// Allocate a zero-filled, locked buffer of `len` bytes under a new protection key.
+    ///
+    /// Returns:
+    /// - `Ok(buf)` with access denied to every thread.
+    /// - `Err(Unsupported)` if the CPU or kernel lacks protection keys.
+    /// - `Err(...)` with the OS error otherwise, e.g. ENOSPC once all 15 usable keys are
+    ///   allocated, or a mapping or locking failure.
+    pub fn new(len: usize) -> io::Result<Self> {
+        imp::check_supported()?;
+        let mut buf = crate::secure_mmap(len.max(1))?;
+        buf.truncate(len);
+        let pkey = imp::alloc()?;
+        let this = Self {
+            buf: ManuallyDrop::new(buf),
+            pkey,
+        };
+        // Safety: the mapping is owned by `this.buf` and is not moved or unmapped while
+        // tagged; on error `this` is dropped, which frees the key.
+        unsafe { imp::assign(&this.buf, pkey)? };
+        Ok(this)
+    }
+
+    /// Allocate a buffer under a new protection key and copy `bytes` into it.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        let mut this = Self::new(bytes.len())?;
+        this.with_access_mut(|buf| buf.copy_from_slice(bytes));
+        Ok(this)
+    }
+
+    /// Run `f` with read access to the contents in the calling thread.
+    pub fn with_access<R>(&self, f: impl FnOnce(&[u8]) -> R) -> R {
+        let _grant = imp::Grant::new(self.pkey, false);
+        f(self.buf.as_slice())
+    }
+
+    /// Run `f` with read and write access to the contents in the calling thread.
+    pub fn with_access_mut<R>(&mut self, f: impl FnOnce(&mut [u8]) -> R) -> R {
+        let _grant = imp::Grant::new(self.pkey, true);
+        f(self.buf.as_mut_slice())
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Whether the buffer has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+}
+
+impl Drop for PkeyBuf {
+    fn drop(&mut self) {
+        {
+            let _grant = imp::Grant::new(self.pkey, true);
+            // Safety: dropped exactly once, here; zeroizing needs write access.
+            unsafe { ManuallyDrop::drop(&mut self.buf) };
+        }
+        // Freed only after the mapping is gone, so a reallocated key cannot reach it.
+        imp::free(self.pkey);
+    }
+}
+
+impl fmt::Debug for PkeyBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("PkeyBuf")
+            .field("len", &self.len())
+            .field("pkey", &self.pkey)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether the CPU and kernel support protection keys.
+pub(crate) fn supported() -> bool {
+    imp::check_supported().is_ok()
+}
+
+/// Run `f` with every protection key accessible in the calling thread, e.g. to wipe
+/// [`PkeyBuf`] regions in a forked child.
+pub(crate) fn with_all_keys<R>(f: impl FnOnce() -> R) -> R {
+    let _grant = imp::Grant::all();
+    f()
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+mod imp {
+    use std::arch::asm;
+    use std::arch::x86_64::__cpuid_count;
+    use std::io;
+    use std::os::raw::{c_long, c_uint};
+
+    use crate::SecureBuf;
+
+    /// PKRU holds two bits per key: access-disable, then write-disable.
+    const ACCESS_DISABLE: u32 = 0b01;
+    const WRITE_DISABLE: u32 = 0b10;
+    /// `PKEY_DISABLE_ACCESS` from <sys/mman.h>; not exported by libc.
+    const PKEY_DISABLE_ACCESS: c_long = 1;
+
+    pub(super) fn check_supported() -> io::Result<()> {
+        // CPUID.(EAX=7,ECX=0):ECX.OSPKE[bit 4]: PKU present and enabled by the kernel.
+        let leaf = __cpuid_count(7, 0);
+        if leaf.ecx & (1 << 4) == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "memory protection keys unsupported on this CPU or kernel",
+            ));
+        }
+        Ok(())
+    }
+
+    pub(super) fn alloc() -> io::Result<i32> {
+        let flags: c_uint = 0;
+        // Safety: pkey_alloc takes no pointers; the initial rights deny access.
+        let rc = unsafe { libc::syscall(libc::SYS_pkey_alloc, flags, PKEY_DISABLE_ACCESS) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(rc as i32)
+    }
+
+    /// Tag the buffer's whole mapping with `pkey`.
+    ///
+    /// # Safety
+    /// `buf` must own its mapping (see `secure_mmap`).
+    pub(super) unsafe fn assign(buf: &SecureBuf, pkey: i32) -> io::Result<()> {
+        let (addr, cap) = buf.region();
+        let prot = c_long::from(libc::PROT_READ | libc::PROT_WRITE);
+        // Safety: guaranteed by the caller; the protection is unchanged.
+        let rc =
+            unsafe { libc::syscall(libc::SYS_pkey_mprotect, addr, cap, prot, c_long::from(pkey)) };
+        if rc != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    pub(super) fn free(pkey: i32) {
+        // Safety: pkey_free takes no pointers.
+        unsafe { libc::syscall(libc::SYS_pkey_free, c_long::from(pkey)) };
+    }
+
+    /// Temporary rights for one key in the calling thread; the previous PKRU value is
+    /// restored on drop, so grants nest.
+    pub(super) struct Grant {
+        previous: u32,
+    }
+
+    impl Grant {
+        pub(super) fn new(pkey: i32, write: bool) -> Self {
+            let previous = rdpkru();
+            let bits = if write { 0 } else { WRITE_DISABLE };
+            let shift = 2 * pkey as u32;
+            wrpkru(previous & !((ACCESS_DISABLE | WRITE_DISABLE) << shift) | bits << shift);
+            Self { previous }
+        }
+
+        /// Full rights to every key, if the CPU supports keys at all.
+        pub(super) fn all() -> Option<Self> {
+            check_supported().ok()?;
+            let previous = rdpkru();
+            wrpkru(0);
+            Some(Self { previous })
+        }
+    }
+
+    impl Drop for Grant {
+        fn drop(&mut self) {
+            wrpkru(self.previous);
+        }
+    }
+
+    fn rdpkru() -> u32 {
+        let pkru: u32;
+        // Safety: OSPKE was checked before any key was allocated.
+        unsafe {
+            asm!("rdpkru", in("ecx") 0, out("eax") pkru, out("edx") _, options(nostack, preserves_flags));
+        }
+        pkru
+    }
+
+    fn wrpkru(pkru: u32) {
+        // Safety: as above. Not `nomem`: this changes which memory is accessible.
+        unsafe {
+            asm!("wrpkru", in("eax") pkru, in("ecx") 0, in("edx") 0, options(nostack, preserves_flags));
+        }
+    }
+
+    /// Whether the calling thread is currently denied all access to `pkey`.
+    #[cfg(test)]
+    pub(super) fn access_denied(pkey: i32) -> bool {
+        rdpkru() >> (2 * pkey as u32) & ACCESS_DISABLE != 0
+    }
+}
+
+#[cfg(not(all(target_os = "linux", target_arch = "x86_64")))]
+mod imp {
+    use std::io;
+
+    use crate::SecureBuf;
+
+    pub(super) fn check_supported() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory protection keys unsupported on this platform",
+        ))
+    }
+
+    pub(super) fn alloc() -> io::Result<i32> {
+        unreachable!("check_supported fails first")
+    }
+
+    /// # Safety
+    /// Never called on this platform.
+    pub(super) unsafe fn assign(_buf: &SecureBuf, _pkey: i32) -> io::Result<()> {
+        unreachable!("check_supported fails first")
+    }
+
+    pub(super) fn free(_pkey: i32) {}
+
+    pub(super) struct Grant;
+
+    impl Grant {
+        pub(super) fn new(_pkey: i32, _write: bool) -> Self {
+            Self
+        }
+
+        pub(super) fn all() -> Option<Self> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PkeyBuf;
+
+    #[test]
+    fn access_is_granted_only_inside_closures() {
+        let mut buf = match PkeyBuf::new(32) {
+            Ok(buf) => buf,
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => return,
+            Err(e) => panic!("{e}"),
+        };
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        assert!(super::imp::access_denied(buf.pkey));
+        buf.with_access_mut(|bytes| bytes.fill(7));
+        let sum = buf.with_access(|bytes| {
+            #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+            assert!(!super::imp::access_denied(buf.pkey));
+            bytes.iter().map(|&b| u32::from(b)).sum::<u32>()
+        });
+        assert_eq!(sum, 7 * 32);
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+        assert!(super::imp::access_denied(buf.pkey));
+    }
+}
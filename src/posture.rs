@@ -0,0 +1,447 @@
+//! System and process settings that decide who else can read this process's memory.
+
+use std::fmt;
+use std::io;
+
+/// How well the process is shielded from debugger attachment by processes of the same
+/// user, as reported by [`ptrace_protection`].
+///
+/// Reflects the Yama LSM's `kernel.yama.ptrace_scope` sysctl. Independently of it,
+/// clearing the dumpable attribute with [`set_dumpable`](crate::set_dumpable) blocks
+/// unprivileged attachment to this process alone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PtraceProtection {
+    /// Yama is not loaded: classic ptrace rules, any process of the same user may attach.
+    NoYama,
+    /// Scope 0: classic ptrace rules, any process of the same user may attach.
+    Classic,
+    /// Scope 1: only ancestors (e.g. the shell that started it, or a debugger that
+    /// launched it) and processes named with `PR_SET_PTRACER` may attach.
+    Restricted,
+    /// Scope 2: only processes with `CAP_SYS_PTRACE` may attach.
+    AdminOnly,
+    /// Scope 3: no process may attach, and the setting cannot be lowered until reboot.
+    NoAttach,
+}
+
+impl PtraceProtection {
+    /// Whether an unrelated process of the same user, such as malware running as the
+    /// user, is refused attachment (and `/proc/<pid>/mem` access).
+    pub fn blocks_same_user(&self) -> bool {
+        !matches!(self, Self::NoYama | Self::Classic)
+    }
+
+    fn from_scope(scope: &str) -> io::Result<Self> {
+        match scope.trim() {
+            "0" => Ok(Self::Classic),
+            "1" => Ok(Self::Restricted),
+            "2" => Ok(Self::AdminOnly),
+            "3" => Ok(Self::NoAttach),
+            other => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unexpected ptrace_scope value {other:?}"),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PtraceProtection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NoYama => "Yama not loaded; same-user processes can attach",
+            Self::Classic => "ptrace_scope 0; same-user processes can attach",
+            Self::Restricted => "ptrace_scope 1; only ancestors can attach",
+            Self::AdminOnly => "ptrace_scope 2; only CAP_SYS_PTRACE can attach",
+            Self::NoAttach => "ptrace_scope 3; attaching is disabled",
+        })
+    }
+}
+
+/// Read `/proc/sys/kernel/yama/ptrace_scope` to tell how protected the process is from
+/// same-user debugger attachment.
+///
+/// Complements [`set_dumpable`](crate::set_dumpable) and the core-dump controls when
+/// auditing whether another process could read secrets out of this one.
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(PtraceProtection::NoYama) if the sysctl does not exist.
+/// - Ok(protection) with the current scope otherwise.
+/// - Err(io::Error) if the file could not be read (e.g. denied by SELinux on Android) or
+///   held an unknown value.
+pub fn ptrace_protection() -> io::Result<PtraceProtection> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        match std::fs::read_to_string("/proc/sys/kernel/yama/ptrace_scope") {
+            Ok(scope) => PtraceProtection::from_scope(&scope),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(PtraceProtection::NoYama),
+            Err(e) => Err(e),
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "ptrace_protection unsupported on this platform",
+    ))
+}
+
+/// Where the kernel would send a core dump of this process, as reported by
+/// [`core_dump_destination`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CoreDumpDestination {
+    /// No dump is produced: the process is not dumpable, `core_pattern` names a file
+    /// and `RLIMIT_CORE` is below one page, or it names a pipe and `RLIMIT_CORE` is
+    /// exactly 1.
+    Disabled,
+    /// Written to a file named by `pattern` (with `%` specifiers unexpanded), relative
+    /// to the crashing process's working directory unless absolute.
+    File {
+        /// The `core_pattern` value.
+        pattern: String,
+    },
+    /// Piped to a user-space handler, which receives the full dump whatever
+    /// `RLIMIT_CORE` is, except exactly 1: the kernel gives handlers that limit so a
+    /// crashing handler does not recurse, and refuses to pipe anything with it.
+    Pipe {
+        /// The recognized handler, if any.
+        handler: CoreDumpHandler,
+        /// The handler's command line, without the leading `|`.
+        command: String,
+    },
+    /// Sent to a user-space handler listening on a Unix socket (Linux 6.16 and later),
+    /// which receives the full dump like a piped one.
+    Socket {
+        /// The socket's path, without the leading `@` (or `@@`, for handlers that agree
+        /// on the dump format with the kernel first).
+        path: String,
+    },
+}
+
+/// Well-known core dump handlers; see [`CoreDumpDestination::Pipe`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CoreDumpHandler {
+    /// `systemd-coredump`, which stores dumps in the journal or
+    /// `/var/lib/systemd/coredump` according to `coredump.conf`.
+    SystemdCoredump,
+    /// Ubuntu's `apport`.
+    Apport,
+    /// Fedora/RHEL's `abrt-hook-ccpp`.
+    Abrt,
+    /// Any other program.
+    Other,
+}
+
+impl CoreDumpDestination {
+    fn parse(pattern: &str, core_limit: Option<u64>, dumpable: bool) -> Self {
+        let pattern = pattern.trim_end_matches('\n');
+        if !dumpable {
+            return Self::Disabled;
+        }
+        if let Some(path) = pattern.strip_prefix('@') {
+            return Self::Socket {
+                path: path.strip_prefix('@').unwrap_or(path).to_owned(),
+            };
+        }
+        if let Some(command) = pattern.strip_prefix('|') {
+            if core_limit == Some(1) {
+                return Self::Disabled;
+            }
+            let program = command.split_whitespace().next().unwrap_or("");
+            let name = program.rsplit('/').next().unwrap_or(program);
+            let handler = if name.contains("systemd-coredump") {
+                CoreDumpHandler::SystemdCoredump
+            } else if name.contains("apport") {
+                CoreDumpHandler::Apport
+            } else if name.contains("abrt") {
+                CoreDumpHandler::Abrt
+            } else {
+                CoreDumpHandler::Other
+            };
+            return Self::Pipe {
+                handler,
+                command: command.to_owned(),
+            };
+        }
+        if core_limit.is_some_and(|limit| limit < crate::page_size() as u64) {
+            return Self::Disabled;
+        }
+        Self::File {
+            pattern: pattern.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for CoreDumpDestination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Disabled => f.write_str("core dumps disabled"),
+            Self::File { pattern } => write!(f, "core dumps written to files matching {pattern:?}"),
+            Self::Pipe { handler, command } => {
+                let name = match handler {
+                    CoreDumpHandler::SystemdCoredump => "systemd-coredump",
+                    CoreDumpHandler::Apport => "apport",
+                    CoreDumpHandler::Abrt => "abrt",
+                    CoreDumpHandler::Other => "a handler",
+                };
+                write!(f, "core dumps piped to {name} ({command})")
+            }
+            Self::Socket { path } => write!(f, "core dumps sent to a handler on socket {path}"),
+        }
+    }
+}
+
+/// Inspect `/proc/sys/kernel/core_pattern` to tell where a crash of this process would
+/// be dumped.
+///
+/// An `RLIMIT_CORE` of 0 stops file dumps but not piped ones (only a limit of exactly 1
+/// does): handlers such as `systemd-coredump` or `apport` get the whole memory image and
+/// decide themselves what to keep. Use this to check that secrets cannot leave the
+/// process on a crash, and clear the dumpable attribute
+/// ([`set_dumpable`](crate::set_dumpable)) where they could.
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(destination), taking the dumpable attribute and `RLIMIT_CORE` into account.
+/// - Err(io::Error) if `core_pattern`, the attribute, or the limit could not be read.
+pub fn core_dump_destination() -> io::Result<CoreDumpDestination> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let pattern = std::fs::read_to_string("/proc/sys/kernel/core_pattern")?;
+        let mut lim = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // Safety: `lim` is a valid, writable rlimit.
+        if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut lim) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // rlim_t is u64 on Linux but not on every Android target.
+        #[allow(clippy::useless_conversion)]
+        let limit = (lim.rlim_cur != libc::RLIM_INFINITY).then(|| u64::from(lim.rlim_cur));
+        Ok(CoreDumpDestination::parse(
+            &pattern,
+            limit,
+            crate::is_dumpable()?,
+        ))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "core_dump_destination unsupported on this platform",
+    ))
+}
+
+/// Which kinds of memory a core dump includes, as in `/proc/<pid>/coredump_filter`;
+/// combine with `|`.
+///
+/// Set with [`set_coredump_filter`]. A process-wide complement to per-region
+/// [`madvise_dontdump`](crate::madvise_dontdump) that also covers memory the crate
+/// never sees, such as heap copies made by other libraries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CoredumpFilter(u32);
+
+impl CoredumpFilter {
+    /// Anonymous private memory: the heap, stacks, and `secure_mmap` buffers.
+    pub const ANON_PRIVATE: Self = Self(1 << 0);
+    /// Anonymous shared memory, including `memfd_secret` and shared `memfd` mappings.
+    pub const ANON_SHARED: Self = Self(1 << 1);
+    /// File-backed private mappings.
+    pub const FILE_PRIVATE: Self = Self(1 << 2);
+    /// File-backed shared mappings.
+    pub const FILE_SHARED: Self = Self(1 << 3);
+    /// ELF headers of file-backed mappings (needed to symbolize a dump).
+    pub const ELF_HEADERS: Self = Self(1 << 4);
+    /// Private hugetlb memory.
+    pub const HUGETLB_PRIVATE: Self = Self(1 << 5);
+    /// Shared hugetlb memory.
+    pub const HUGETLB_SHARED: Self = Self(1 << 6);
+    /// Private DAX mappings.
+    pub const DAX_PRIVATE: Self = Self(1 << 7);
+    /// Shared DAX mappings.
+    pub const DAX_SHARED: Self = Self(1 << 8);
+
+    /// No memory included: a dump keeps only registers and metadata.
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// The raw bit mask.
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    /// Build a filter from a raw bit mask, keeping bits this type has no name for.
+    pub const fn from_bits_retain(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    /// Whether every flag in `other` is also set in `self`.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// `self` with every flag in `other` cleared.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+impl std::ops::BitOr for CoredumpFilter {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for CoredumpFilter {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// Read the process's core dump filter from `/proc/self/coredump_filter`.
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(filter) on success.
+/// - Err(io::Error) if the file could not be read or parsed.
+pub fn get_coredump_filter() -> io::Result<CoredumpFilter> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let text = std::fs::read_to_string("/proc/self/coredump_filter")?;
+        u32::from_str_radix(text.trim(), 16)
+            .map(CoredumpFilter)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "get_coredump_filter unsupported on this platform",
+    ))
+}
+
+/// Choose which kinds of memory any core dump of this process includes.
+///
+/// For example, `get_coredump_filter()?.difference(CoredumpFilter::ANON_PRIVATE |
+/// CoredumpFilter::ANON_SHARED)` keeps dumps useful for symbolized backtraces while
+/// leaving out the heap and stacks where secrets and their stray copies live.
+///
+/// Behavior:
+/// - Applies to the whole process (all threads) and is inherited across `fork` and
+///   `execve`.
+/// - Honored by file and piped dumps alike, since the kernel filters before writing.
+///
+/// Platform:
+/// - Linux and Android. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(()) on success.
+/// - Err(io::Error) if `/proc/self/coredump_filter` could not be written.
+pub fn set_coredump_filter(filter: CoredumpFilter) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        std::fs::write("/proc/self/coredump_filter", format!("{:#x}", filter.0))
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = filter;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "set_coredump_filter unsupported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PtraceProtection;
+
+    #[test]
+    fn scopes_parse_and_rank() {
+        assert_eq!(
+            PtraceProtection::from_scope("1\n").unwrap(),
+            PtraceProtection::Restricted
+        );
+        assert!(PtraceProtection::from_scope("7").is_err());
+        assert!(!PtraceProtection::Classic.blocks_same_user());
+        assert!(PtraceProtection::AdminOnly.blocks_same_user());
+        assert_eq!(
+            PtraceProtection::NoAttach.to_string(),
+            "ptrace_scope 3; attaching is disabled"
+        );
+    }
+
+    #[test]
+    fn core_patterns_classify() {
+        use super::{CoreDumpDestination as Dest, CoreDumpHandler};
+
+        let systemd = "|/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h\n";
+        assert_eq!(
+            Dest::parse(systemd, Some(0), true),
+            Dest::Pipe {
+                handler: CoreDumpHandler::SystemdCoredump,
+                command: "/usr/lib/systemd/systemd-coredump %P %u %g %s %t %c %h".into(),
+            }
+        );
+        assert!(matches!(
+            Dest::parse("|/usr/share/apport/apport -p%p", None, true),
+            Dest::Pipe {
+                handler: CoreDumpHandler::Apport,
+                ..
+            }
+        ));
+        assert_eq!(Dest::parse(systemd, Some(1), true), Dest::Disabled);
+        for pattern in ["@/run/coredump.socket\n", "@@/run/coredump.socket\n"] {
+            assert_eq!(
+                Dest::parse(pattern, Some(0), true),
+                Dest::Socket {
+                    path: "/run/coredump.socket".into()
+                }
+            );
+        }
+        assert_eq!(Dest::parse("core\n", Some(0), true), Dest::Disabled);
+        assert_eq!(Dest::parse(systemd, None, false), Dest::Disabled);
+        let file = Dest::parse("/var/crash/core.%p\n", None, true);
+        assert_eq!(
+            file.to_string(),
+            "core dumps written to files matching \"/var/crash/core.%p\""
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn coredump_filter_round_trips() {
+        use super::{CoredumpFilter, get_coredump_filter, set_coredump_filter};
+
+        // The filter is process-wide.
+        if !crate::test_util::in_own_process("posture::tests::coredump_filter_round_trips") {
+            return;
+        }
+        let original = get_coredump_filter().unwrap();
+        let anon = CoredumpFilter::ANON_PRIVATE | CoredumpFilter::ANON_SHARED;
+        let reduced = original.difference(anon);
+        set_coredump_filter(reduced).unwrap();
+        let read_back = get_coredump_filter().unwrap();
+        set_coredump_filter(original).unwrap();
+        assert_eq!(read_back, reduced);
+        assert!(!read_back.contains(CoredumpFilter::ANON_PRIVATE));
+        assert_eq!(get_coredump_filter().unwrap(), original);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn posture_is_readable() {
+        super::core_dump_destination().unwrap();
+        super::ptrace_protection().unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+//! Reading passwords from the terminal straight into locked memory.
+
+use std::io::{self, Write};
+
+use crate::SecureBuf;
+
+/// Longest secret accepted by [`read_secret_from_stdin`], in bytes.
+pub const MAX_PROMPT_SECRET_LEN: usize = 4096;
+
+/// Print `prompt` to stderr and read one line from stdin into a locked buffer.
+///
+/// Behavior:
+/// - When stdin is a terminal (console on Windows), echo is disabled while reading and
+///   restored afterwards, including on error.
+/// - Bytes are read one at a time with the raw OS read call directly into a locked,
+///   dump-excluded buffer. `std::io::stdin()` is deliberately not used, as its internal
+///   buffer would keep a pageable copy of the password.
+/// - The trailing `\n` (and `\r` before it) is stripped. End of input also ends the line.
+///
+/// Returns:
+/// - `Ok(buf)` with the entered secret.
+/// - `Err(InvalidInput)` if the line exceeds [`MAX_PROMPT_SECRET_LEN`] bytes.
+/// - `Err(Unsupported)` on platforms without a terminal implementation.
+pub fn read_secret_from_stdin(prompt: &str) -> io::Result<SecureBuf> {
+    let mut stderr = io::stderr().lock();
+    stderr.write_all(prompt.as_bytes())?;
+    stderr.flush()?;
+
+    let echo = imp::EchoOff::new()?;
+    // One spare byte for the terminating newline, which is read in place and wiped
+    // by `truncate` below; no byte of the secret ever passes through a stack copy.
+    let mut buf = SecureBuf::new(MAX_PROMPT_SECRET_LEN + 1)?;
+    let mut len = 0;
+    loop {
+        if len == buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "secret exceeds MAX_PROMPT_SECRET_LEN bytes",
+            ));
+        }
+        if imp::read_byte(&mut buf.as_mut_slice()[len])? == 0 || buf.as_slice()[len] == b'\n' {
+            break;
+        }
+        len += 1;
+    }
+    if len > 0 && buf.as_slice()[len - 1] == b'\r' {
+        len -= 1;
+    }
+    buf.truncate(len);
+
+    // The user's Enter key was not echoed on every platform; end the prompt line.
+    if echo.changed_terminal() {
+        let _ = stderr.write_all(imp::NEWLINE_AFTER_PROMPT);
+    }
+    drop(echo);
+    Ok(buf)
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+    use std::mem::MaybeUninit;
+
+    #[cfg(not(any(target_os = "redox", target_os = "haiku")))]
+    use libc::STDIN_FILENO;
+    // The libc crate does not bind it for Redox or Haiku.
+    #[cfg(any(target_os = "redox", target_os = "haiku"))]
+    const STDIN_FILENO: libc::c_int = 0;
+
+    /// ECHONL keeps the newline visible, so nothing extra needs to be printed.
+    pub(super) const NEWLINE_AFTER_PROMPT: &[u8] = b"";
+
+    /// Clears `ECHO` on stdin's terminal and restores the saved settings on drop.
+    pub(super) struct EchoOff {
+        saved: Option<libc::termios>,
+    }
+
+    impl EchoOff {
+        pub(super) fn new() -> io::Result<Self> {
+            if unsafe { libc::isatty(STDIN_FILENO) } != 1 {
+                return Ok(Self { saved: None });
+            }
+            let mut term = MaybeUninit::<libc::termios>::uninit();
+            // Safety: tcgetattr fully initializes `term` on success.
+            if unsafe { libc::tcgetattr(STDIN_FILENO, term.as_mut_ptr()) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let saved = unsafe { term.assume_init() };
+            let mut quiet = saved;
+            quiet.c_lflag &= !libc::ECHO;
+            quiet.c_lflag |= libc::ECHONL;
+            if unsafe { libc::tcsetattr(STDIN_FILENO, libc::TCSANOW, &quiet) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { saved: Some(saved) })
+        }
+
+        pub(super) fn changed_terminal(&self) -> bool {
+            self.saved.is_some()
+        }
+    }
+
+    impl Drop for EchoOff {
+        fn drop(&mut self) {
+            if let Some(saved) = &self.saved
+                && unsafe { libc::tcsetattr(STDIN_FILENO, libc::TCSANOW, saved) } != 0
+            {
+                // Avoid panicking in Drop; emit a diagnostic.
+                diag!(
+                    "failed to restore terminal echo: {}",
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+
+    /// Read a single byte from stdin, retrying on EINTR. Returns 0 at end of input.
+    pub(super) fn read_byte(byte: &mut u8) -> io::Result<usize> {
+        loop {
+            let n = unsafe { libc::read(STDIN_FILENO, (byte as *mut u8).cast(), 1) };
+            if n >= 0 {
+                return Ok(n as usize);
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::{ERROR_BROKEN_PIPE, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::ReadFile;
+    use windows_sys::Win32::System::Console::{
+        CONSOLE_MODE, ENABLE_ECHO_INPUT, GetConsoleMode, GetStdHandle, STD_INPUT_HANDLE,
+        SetConsoleMode,
+    };
+
+    /// The console does not echo Enter while echo is off; finish the prompt line.
+    pub(super) const NEWLINE_AFTER_PROMPT: &[u8] = b"\n";
+
+    fn stdin_handle() -> io::Result<HANDLE> {
+        let handle = unsafe { GetStdHandle(STD_INPUT_HANDLE) };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(handle)
+    }
+
+    /// Clears `ENABLE_ECHO_INPUT` on the console and restores the saved mode on drop.
+    pub(super) struct EchoOff {
+        handle: HANDLE,
+        saved: Option<CONSOLE_MODE>,
+    }
+
+    impl EchoOff {
+        pub(super) fn new() -> io::Result<Self> {
+            let handle = stdin_handle()?;
+            let mut mode: CONSOLE_MODE = 0;
+            // Not a console (e.g. redirected input): nothing to change.
+            if unsafe { GetConsoleMode(handle, &mut mode) } == 0 {
+                return Ok(Self {
+                    handle,
+                    saved: None,
+                });
+            }
+            if unsafe { SetConsoleMode(handle, mode & !ENABLE_ECHO_INPUT) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self {
+                handle,
+                saved: Some(mode),
+            })
+        }
+
+        pub(super) fn changed_terminal(&self) -> bool {
+            self.saved.is_some()
+        }
+    }
+
+    impl Drop for EchoOff {
+        fn drop(&mut self) {
+            if let Some(mode) = self.saved
+                && unsafe { SetConsoleMode(self.handle, mode) } == 0
+            {
+                // Avoid panicking in Drop; emit a diagnostic.
+                diag!(
+                    "failed to restore console echo: {}",
+                    io::Error::last_os_error()
+                );
+            }
+        }
+    }
+
+    /// Read a single byte from stdin. Returns 0 at end of input.
+    pub(super) fn read_byte(byte: &mut u8) -> io::Result<usize> {
+        let handle = stdin_handle()?;
+        let mut read = 0u32;
+        if unsafe { ReadFile(handle, byte, 1, &mut read, ptr::null_mut()) } == 0 {
+            let err = io::Error::last_os_error();
+            // A closed pipe on the other end is end of input, not a failure.
+            if err.raw_os_error() == Some(ERROR_BROKEN_PIPE as i32) {
+                return Ok(0);
+            }
+            return Err(err);
+        }
+        Ok(read as usize)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use std::io;
+
+    pub(super) const NEWLINE_AFTER_PROMPT: &[u8] = b"";
+
+    pub(super) struct EchoOff;
+
+    impl EchoOff {
+        pub(super) fn new() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "read_secret_from_stdin unsupported on this platform",
+            ))
+        }
+
+        pub(super) fn changed_terminal(&self) -> bool {
+            false
+        }
+    }
+
+    pub(super) fn read_byte(_byte: &mut u8) -> io::Result<usize> {
+        Ok(0)
+    }
+}
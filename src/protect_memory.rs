@@ -0,0 +1,171 @@
+//! Encrypting secrets in place while idle with `CryptProtectMemory` (Windows).
+//!
+//! The Windows counterpart of the idle-encryption mode (`IdleSealed`): the system holds
+//! the key, so nothing but the ciphertext has to stay in the process's memory.
+
+use std::io;
+
+/// Granularity of [`protect_memory`] and [`unprotect_memory`]: buffers must be a
+/// multiple of this many bytes (`CRYPTPROTECTMEMORY_BLOCK_SIZE`).
+pub const PROTECT_MEMORY_BLOCK_SIZE: usize = 16;
+
+/// Who can decrypt memory encrypted by [`protect_memory`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ProtectScope {
+    /// Only this process (`CRYPTPROTECTMEMORY_SAME_PROCESS`).
+    SameProcess,
+    /// Any process on the machine (`CRYPTPROTECTMEMORY_CROSS_PROCESS`); the key does
+    /// not survive a reboot.
+    CrossProcess,
+    /// Processes running under the same logon session
+    /// (`CRYPTPROTECTMEMORY_SAME_LOGON`).
+    SameLogon,
+}
+
+/// Encrypt `buf` in place with `CryptProtectMemory`.
+///
+/// Decrypt it with [`unprotect_memory`] and the same scope before use. A common pattern
+/// is to keep a [`SecureBuf`](crate::SecureBuf) of a length rounded up with
+/// [`protected_len`] encrypted between uses.
+///
+/// Platform:
+/// - Windows only. On other platforms, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(()) once `buf` holds the ciphertext
+/// - Err(InvalidInput) if `buf.len()` is not a multiple of
+///   [`PROTECT_MEMORY_BLOCK_SIZE`]; the message names the padded length
+/// - Err(...) with last_os_error() if the call failed
+pub fn protect_memory(buf: &mut [u8], scope: ProtectScope) -> io::Result<()> {
+    check_block_size(buf.len())?;
+    imp::protect(buf, scope)
+}
+
+/// Decrypt `buf` in place with `CryptUnprotectMemory`, reversing [`protect_memory`].
+///
+/// `scope` must match the one `buf` was encrypted with; a mismatch, or a buffer that
+/// was never encrypted, yields garbage rather than an error.
+///
+/// Platform:
+/// - Windows only. On other platforms, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(()) once `buf` holds the plaintext
+/// - Err(InvalidInput) if `buf.len()` is not a multiple of
+///   [`PROTECT_MEMORY_BLOCK_SIZE`]
+/// - Err(...) with last_os_error() if the call failed
+pub fn unprotect_memory(buf: &mut [u8], scope: ProtectScope) -> io::Result<()> {
+    check_block_size(buf.len())?;
+    imp::unprotect(buf, scope)
+}
+
+/// `len` rounded up to a multiple of [`PROTECT_MEMORY_BLOCK_SIZE`], the buffer size to
+/// allocate for a secret of `len` bytes.
+pub fn protected_len(len: usize) -> usize {
+    len.next_multiple_of(PROTECT_MEMORY_BLOCK_SIZE)
+}
+
+fn check_block_size(len: usize) -> io::Result<()> {
+    if len.is_multiple_of(PROTECT_MEMORY_BLOCK_SIZE) {
+        return Ok(());
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+            "CryptProtectMemory needs a multiple of {PROTECT_MEMORY_BLOCK_SIZE} bytes, got {len}; \
+             pad the buffer to {}",
+            protected_len(len)
+        ),
+    ))
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+
+    use windows_sys::Win32::Security::Cryptography::{
+        CRYPTPROTECTMEMORY_CROSS_PROCESS, CRYPTPROTECTMEMORY_SAME_LOGON,
+        CRYPTPROTECTMEMORY_SAME_PROCESS, CryptProtectMemory, CryptUnprotectMemory,
+    };
+
+    use super::ProtectScope;
+
+    fn flags(scope: ProtectScope) -> u32 {
+        match scope {
+            ProtectScope::SameProcess => CRYPTPROTECTMEMORY_SAME_PROCESS,
+            ProtectScope::CrossProcess => CRYPTPROTECTMEMORY_CROSS_PROCESS,
+            ProtectScope::SameLogon => CRYPTPROTECTMEMORY_SAME_LOGON,
+        }
+    }
+
+    fn len(buf: &[u8]) -> io::Result<u32> {
+        u32::try_from(buf.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))
+    }
+
+    pub(super) fn protect(buf: &mut [u8], scope: ProtectScope) -> io::Result<()> {
+        // Safety: `buf` is valid for reads and writes of its whole length.
+        let ok = unsafe { CryptProtectMemory(buf.as_mut_ptr().cast(), len(buf)?, flags(scope)) };
+        if ok != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    pub(super) fn unprotect(buf: &mut [u8], scope: ProtectScope) -> io::Result<()> {
+        // Safety: `buf` is valid for reads and writes of its whole length.
+        let ok = unsafe { CryptUnprotectMemory(buf.as_mut_ptr().cast(), len(buf)?, flags(scope)) };
+        if ok != 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+
+    use super::ProtectScope;
+
+    pub(super) fn protect(_buf: &mut [u8], _scope: ProtectScope) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "protect_memory is Windows-only",
+        ))
+    }
+
+    pub(super) fn unprotect(_buf: &mut [u8], _scope: ProtectScope) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "unprotect_memory is Windows-only",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::{ProtectScope, protect_memory, protected_len, unprotect_memory};
+
+    #[test]
+    fn protect_round_trips_whole_blocks() {
+        let mut odd = [0u8; 20];
+        let err = protect_memory(&mut odd, ProtectScope::SameProcess).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(protected_len(20), 32);
+
+        let mut buf = [7u8; 32];
+        match protect_memory(&mut buf, ProtectScope::SameProcess) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::Unsupported => return,
+            Err(e) => panic!("{e}"),
+        }
+        assert_ne!(buf, [7u8; 32]);
+        unprotect_memory(&mut buf, ProtectScope::SameProcess).unwrap();
+        assert_eq!(buf, [7u8; 32]);
+    }
+}
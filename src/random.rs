@@ -0,0 +1,37 @@
+//! Filling locked buffers from the operating system's RNG.
+
+use std::io;
+
+use crate::SecureBuf;
+
+impl SecureBuf {
+    /// Allocate a locked buffer of `len` bytes filled from the OS random number generator.
+    ///
+    /// The random bytes are written directly into locked memory, so freshly generated
+    /// key material never exists anywhere else in the address space.
+    #[cfg_attr(docsrs, doc(cfg(feature = "getrandom")))]
+    pub fn random(len: usize) -> io::Result<Self> {
+        let mut buf = Self::new(len)?;
+        buf.fill_random()?;
+        Ok(buf)
+    }
+
+    /// Overwrite the whole buffer with bytes from the OS random number generator.
+    #[cfg_attr(docsrs, doc(cfg(feature = "getrandom")))]
+    pub fn fill_random(&mut self) -> io::Result<()> {
+        getrandom::fill(self.as_mut_slice())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SecureBuf;
+
+    #[test]
+    fn random_buffers_differ() {
+        let a = SecureBuf::random(32).unwrap();
+        let b = SecureBuf::random(32).unwrap();
+        assert_ne!(a.as_slice(), b.as_slice());
+    }
+}
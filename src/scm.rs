@@ -0,0 +1,143 @@
+//! Passing a secret's descriptor and length over a Unix socket (`SCM_RIGHTS`).
+//!
+//! Each message carries exactly one descriptor and the secret's length as 8 native-endian
+//! bytes; the contents never travel through the socket.
+
+use std::io;
+use std::mem;
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::raw::{c_int, c_uint, c_void};
+use std::ptr;
+
+/// Room for one descriptor, aligned for `cmsghdr`.
+type Control = [u64; 4];
+
+/// Send `fd` and `len` as one message on `socket`.
+pub(crate) fn send_fd(socket: BorrowedFd<'_>, fd: BorrowedFd<'_>, len: u64) -> io::Result<()> {
+    let payload = len.to_ne_bytes();
+    let mut iov = libc::iovec {
+        iov_base: payload.as_ptr() as *mut c_void,
+        iov_len: payload.len(),
+    };
+    let mut control: Control = [0; 4];
+    // Safety: msghdr is plain data; an all-zero value is an empty message.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    // Safety: pure size arithmetic.
+    msg.msg_controllen = unsafe { libc::CMSG_SPACE(mem::size_of::<c_int>() as c_uint) } as _;
+    // Safety: the control buffer is large enough for one header and one descriptor, and
+    // aligned for `cmsghdr`; the data pointer may be unaligned, hence write_unaligned.
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<c_int>() as c_uint) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg).cast::<c_int>(), fd.as_raw_fd());
+    }
+    let sent = loop {
+        // Safety: `msg` points at `payload` and `control`, both live for the call.
+        let rc = unsafe { libc::sendmsg(socket.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+        if rc >= 0 {
+            break rc as usize;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    };
+    if sent != payload.len() {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            "socket accepted only part of the secret's length",
+        ));
+    }
+    Ok(())
+}
+
+/// Receive one descriptor and length sent by [`send_fd`].
+///
+/// The descriptor is close-on-exec. Messages with no descriptor, several, or a
+/// truncated length are rejected, closing whatever arrived.
+pub(crate) fn recv_fd(socket: BorrowedFd<'_>) -> io::Result<(OwnedFd, u64)> {
+    let mut payload = [0u8; 8];
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr().cast(),
+        iov_len: payload.len(),
+    };
+    let mut control: Control = [0; 4];
+    // Safety: msghdr is plain data; an all-zero value is an empty message.
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = mem::size_of::<Control>() as _;
+    let received = loop {
+        // Safety: `msg` points at `payload` and `control`, both live for the call.
+        let rc = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+        if rc >= 0 {
+            break rc as usize;
+        }
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted {
+            return Err(err);
+        }
+    };
+
+    // Take ownership of every descriptor first, so the rejected ones get closed.
+    let mut fds = Vec::new();
+    // Safety: the kernel filled `control` with well-formed headers up to
+    // `msg_controllen`; descriptors are read unaligned from each header's data.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg).cast::<c_int>();
+                let bytes = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                for i in 0..bytes / mem::size_of::<c_int>() {
+                    fds.push(OwnedFd::from_raw_fd(ptr::read_unaligned(data.add(i))));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if received == 0 && fds.is_empty() {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    if received != payload.len() || fds.len() != 1 || msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "expected one descriptor and an 8-byte length",
+        ));
+    }
+    let fd = fds.pop().expect("checked above");
+    Ok((fd, u64::from_ne_bytes(payload)))
+}
+
+/// The secret's length as received, checked against the size of the object behind `fd`.
+///
+/// Returns the length and the page-rounded size to map.
+pub(crate) fn checked_len(fd: &OwnedFd, len: u64) -> io::Result<(usize, usize)> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "received length exceeds the size of the descriptor's object",
+        )
+    };
+    // Safety: stat is plain data, filled in by fstat.
+    let mut stat: libc::stat = unsafe { mem::zeroed() };
+    // Safety: plain syscall on a descriptor we own.
+    if unsafe { libc::fstat(fd.as_raw_fd(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let len = usize::try_from(len).map_err(|_| invalid())?;
+    let cap = len
+        .checked_next_multiple_of(crate::page_size())
+        .ok_or_else(invalid)?;
+    // Mapping past the end of the object would fault with SIGBUS on access.
+    if u64::try_from(stat.st_size).map_or(true, |size| size < cap as u64) {
+        return Err(invalid());
+    }
+    Ok((len, cap))
+}
@@ -0,0 +1,327 @@
+//! Immutable secrets in sealed, locked memfd memory.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use std::os::raw::c_void;
+use std::ptr::{self, NonNull};
+use std::slice;
+use std::sync::atomic::Ordering;
+
+use crate::secure_buf::LOCKED_BYTES;
+
+/// Seals applied once the contents are written.
+const SEALS: libc::c_int =
+    libc::F_SEAL_WRITE | libc::F_SEAL_GROW | libc::F_SEAL_SHRINK | libc::F_SEAL_SEAL;
+
+/// A read-only secret in a sealed `memfd_create(2)` object, locked in RAM.
+///
+/// Behavior:
+/// - The contents are written once through a temporary writable mapping, which is
+///   unmapped before the memfd is sealed with `F_SEAL_WRITE | F_SEAL_GROW |
+///   F_SEAL_SHRINK | F_SEAL_SEAL`. From then on neither this process nor anyone holding
+///   the fd (leaked, inherited, or passed over a socket) can modify, resize, or unseal
+///   the backing object.
+/// - The secret is read through a locked, read-only shared mapping that is excluded
+///   from core dumps where supported. The fd this keeps (see [`AsFd`]) is a read-only,
+///   close-on-exec reopen of the memfd.
+/// - Sealed memory cannot be zeroized. On drop the mapping is unmapped and the fd
+///   closed, and once no other fd or mapping refers to the object the kernel frees its
+///   pages without wiping them (unless booted with `init_on_free=1`). Prefer
+///   [`SecureBuf`](crate::SecureBuf) where wipe-on-drop matters more than immutability.
+///
+/// Platform:
+/// - Linux and Android only; construction reopens the memfd through `/proc/self/fd`, so
+///   procfs must be mounted.
+pub struct SealedMemfd {
+    ptr: NonNull<u8>,
+    len: usize,
+    cap: usize,
+    fd: OwnedFd,
+}
+
+// Safety: the mapping is read-only and uniquely owned, like Box<[u8]>.
+unsafe impl Send for SealedMemfd {}
+// Safety: shared access only hands out `&[u8]`.
+unsafe impl Sync for SealedMemfd {}
+
+impl SealedMemfd {
+    /// Create a sealed secret of `len` bytes, filled by `init`.
+    ///
+    /// `init` receives a zeroed, locked, writable view of the contents; it is the only
+    /// chance to write them.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` once the contents are locked and the memfd is sealed.
+    /// - `Err(...)` with the OS error if creating, mapping, locking, or sealing the
+    ///   memfd failed (e.g. `RLIMIT_MEMLOCK` exhausted).
+    pub fn new(len: usize, init: impl FnOnce(&mut [u8])) -> io::Result<Self> {
+        // Safety: a valid NUL-terminated name; the flags are supported since Linux 3.17.
+        let raw = unsafe {
+            libc::memfd_create(
+                c"os-memlock-sealed".as_ptr(),
+                libc::MFD_CLOEXEC | libc::MFD_ALLOW_SEALING,
+            )
+        };
+        if raw < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Safety: a new fd owned by nothing else.
+        let writer = unsafe { OwnedFd::from_raw_fd(raw) };
+        // A shared mapping of a read-write fd counts as writable even with PROT_READ
+        // (mprotect could upgrade it) and would make F_SEAL_WRITE fail with EBUSY, so
+        // the lasting mapping and the fd we keep come from a read-only reopen.
+        let reader = reopen_read_only(&writer)?;
+
+        let cap = len.next_multiple_of(crate::page_size());
+        if cap != 0 {
+            let size = libc::off_t::try_from(cap)
+                .map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+            // Safety: plain syscall on an fd we own.
+            if unsafe { libc::ftruncate(writer.as_raw_fd(), size) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        // Lock through the read-only mapping first, so the pages stay locked after the
+        // writable one is gone.
+        let sealed = Self::locked_view(reader, len, cap)?;
+        if cap != 0 {
+            let writable = Mapping(map(&writer, cap, libc::PROT_READ | libc::PROT_WRITE)?, cap);
+            // Safety: the writable mapping is `cap` bytes, zero-filled, and unaliased
+            // by any live reference.
+            init(unsafe { slice::from_raw_parts_mut(writable.0.as_ptr(), len) });
+        }
+        // Safety: plain fcntl on an fd we own; no writable mapping remains.
+        if unsafe { libc::fcntl(writer.as_raw_fd(), libc::F_ADD_SEALS, SEALS) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sealed)
+    }
+
+    /// Map `cap` bytes of the memfd behind `fd` read-only and lock them, as the view of a
+    /// `len`-byte secret.
+    fn locked_view(fd: OwnedFd, len: usize, cap: usize) -> io::Result<Self> {
+        let mut view = Self {
+            ptr: NonNull::dangling(),
+            len,
+            cap: 0,
+            fd,
+        };
+        if cap != 0 {
+            let readable = Mapping(map(&view.fd, cap, libc::PROT_READ)?, cap);
+            let addr = readable.0.as_ptr() as *mut c_void;
+            // Safety: (addr, cap) is the mapping just created; on error it is unmapped.
+            unsafe { crate::mlock(addr, cap)? };
+            LOCKED_BYTES.fetch_add(cap, Ordering::Relaxed);
+            view.ptr = readable.0;
+            view.cap = cap;
+            mem::forget(readable);
+            // Safety: (addr, cap) is now owned by `view`.
+            let _ = unsafe { crate::madvise_dontdump(addr, cap) };
+        }
+        Ok(view)
+    }
+
+    /// Send the secret to another process over a Unix socket.
+    ///
+    /// Behavior:
+    /// - Sends the sealed memfd with `SCM_RIGHTS`, together with the secret's length;
+    ///   the contents never pass through the socket. The peer maps it with
+    ///   [`SealedMemfd::receive`].
+    /// - The object is shared, not copied: both sides read the same locked pages, and
+    ///   the seals keep either side from modifying them.
+    ///
+    /// Returns:
+    /// - `Ok(())` once the message is queued on the socket.
+    /// - `Err(...)` with the OS error from `sendmsg` otherwise.
+    pub fn send(&self, socket: impl AsFd) -> io::Result<()> {
+        crate::scm::send_fd(socket.as_fd(), self.fd.as_fd(), self.len as u64)
+    }
+
+    /// Receive a secret sent with [`SealedMemfd::send`] and map it locked.
+    ///
+    /// The descriptor is checked before anything is mapped: it must be a memfd carrying
+    /// every seal [`SealedMemfd::new`] applies, and large enough for the length sent,
+    /// so a misbehaving peer cannot pass memory it could still modify.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` with the same contents as the sender's.
+    /// - `Err(UnexpectedEof)` if the peer closed the socket.
+    /// - `Err(InvalidData)` if the message or descriptor is not what `send` produces.
+    /// - `Err(...)` with the OS error if receiving, mapping or locking failed.
+    pub fn receive(socket: impl AsFd) -> io::Result<Self> {
+        let (fd, len) = crate::scm::recv_fd(socket.as_fd())?;
+        // Safety: plain fcntl on an fd we own.
+        let seals = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_GET_SEALS) };
+        if seals < 0 || seals & SEALS != SEALS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "received descriptor is not a sealed memfd",
+            ));
+        }
+        let (len, cap) = crate::scm::checked_len(&fd, len)?;
+        Self::locked_view(reopen_read_only(&fd)?, len, cap)
+    }
+
+    /// Create a sealed secret holding a copy of `bytes`.
+    ///
+    /// The caller is responsible for zeroizing `bytes` afterwards.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        Self::new(bytes.len(), |buf| buf.copy_from_slice(bytes))
+    }
+
+    /// Borrow the contents.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid for `len` readable bytes (or dangling with len 0).
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the secret has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the memfd carries every seal applied at construction.
+    ///
+    /// Returns `Err(...)` with the OS error if `F_GET_SEALS` fails.
+    pub fn is_sealed(&self) -> io::Result<bool> {
+        // Safety: plain fcntl on an fd we own.
+        let seals = unsafe { libc::fcntl(self.fd.as_raw_fd(), libc::F_GET_SEALS) };
+        if seals < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(seals & SEALS == SEALS)
+    }
+}
+
+impl AsFd for SealedMemfd {
+    /// The sealed memfd, e.g. for handing the secret to a child process.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for SealedMemfd {
+    fn drop(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+        LOCKED_BYTES.fetch_sub(self.cap, Ordering::Relaxed);
+        // Unmapping also drops the lock; the fd closes afterwards.
+        // Safety: the mapping created in `new`, no longer referenced.
+        if unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.cap) } != 0 {
+            diag!("munmap failed: {}", io::Error::last_os_error());
+        }
+    }
+}
+
+impl fmt::Debug for SealedMemfd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("SealedMemfd")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A mapping that is unmapped on scope exit, including when `init` panics.
+pub(crate) struct Mapping(pub(crate) NonNull<u8>, pub(crate) usize);
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        // Safety: a mapping created by `map`, no longer referenced.
+        unsafe { libc::munmap(self.0.as_ptr().cast(), self.1) };
+    }
+}
+
+/// A read-only, close-on-exec reopen of the memfd behind `fd`.
+fn reopen_read_only(fd: &OwnedFd) -> io::Result<OwnedFd> {
+    Ok(OwnedFd::from(File::open(format!(
+        "/proc/self/fd/{}",
+        fd.as_raw_fd()
+    ))?))
+}
+
+/// Map `cap` bytes of `fd`, shared, with protection `prot`.
+pub(crate) fn map(fd: &OwnedFd, cap: usize, prot: libc::c_int) -> io::Result<NonNull<u8>> {
+    // Safety: a new mapping of an fd we own, not overlapping existing memory.
+    let addr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            cap,
+            prot,
+            libc::MAP_SHARED,
+            fd.as_raw_fd(),
+            0,
+        )
+    };
+    if addr == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    NonNull::new(addr.cast()).ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::{ErrorKind, Write};
+    use std::os::fd::{AsFd, AsRawFd, FromRawFd};
+
+    use super::SealedMemfd;
+
+    #[test]
+    fn contents_are_sealed_against_writes_and_resizes() {
+        let secret = SealedMemfd::from_slice(b"api-token").unwrap();
+        assert_eq!(secret.as_slice(), b"api-token");
+        assert!(secret.is_sealed().unwrap());
+        assert!(!format!("{secret:?}").contains("api-token"));
+
+        // Even a read-write reopen of the fd cannot change the sealed object.
+        let path = format!("/proc/self/fd/{}", secret.as_fd().as_raw_fd());
+        let mut file = OpenOptions::new().write(true).open(path).unwrap();
+        let err = file.write_all(b"x").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert!(file.set_len(0).is_err());
+        assert!(file.set_len(1 << 20).is_err());
+        assert_eq!(secret.as_slice(), b"api-token");
+    }
+
+    #[test]
+    fn secret_is_passed_over_a_socket() {
+        use std::os::unix::net::UnixStream;
+
+        let (broker, worker) = UnixStream::pair().unwrap();
+        let secret = SealedMemfd::from_slice(b"db-password").unwrap();
+        secret.send(&broker).unwrap();
+        drop(secret);
+        let received = SealedMemfd::receive(&worker).unwrap();
+        assert_eq!(received.as_slice(), b"db-password");
+        assert!(received.is_sealed().unwrap());
+
+        // An unsealed memfd is refused before it is mapped.
+        let raw = unsafe { libc::memfd_create(c"plain".as_ptr(), libc::MFD_CLOEXEC) };
+        let plain = unsafe { std::os::fd::OwnedFd::from_raw_fd(raw) };
+        assert_eq!(unsafe { libc::ftruncate(raw, 4096) }, 0);
+        crate::scm::send_fd(broker.as_fd(), plain.as_fd(), 16).unwrap();
+        let err = SealedMemfd::receive(&worker).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        drop(broker);
+        let err = SealedMemfd::receive(&worker).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn empty_secret_is_sealed() {
+        let secret = SealedMemfd::new(0, |_| {}).unwrap();
+        assert!(secret.is_empty());
+        assert!(secret.is_sealed().unwrap());
+    }
+}
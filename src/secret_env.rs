@@ -0,0 +1,177 @@
+//! Moving secrets out of environment variables into locked memory.
+
+use std::io;
+
+use crate::SecureBuf;
+
+/// Copy the value of environment variable `name` into a locked buffer and scrub the original.
+///
+/// Platform:
+/// - Unix: the value is found by walking `environ` directly (no `String` copy is made),
+///   copied into locked memory, and then overwritten with zeros in place. The variable
+///   remains defined with an empty value, and `/proc/<pid>/environ` no longer shows it
+///   for variables inherited at exec time.
+/// - Windows: the value is read with `GetEnvironmentVariableW` into locked memory,
+///   converted to UTF-8 there, and the variable is then removed. The OS may leave the old
+///   environment block in freed heap memory, so this is best-effort.
+/// - Elsewhere: returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(Some(buf))` with the value if the variable was set.
+/// - `Ok(None)` if it was not set.
+/// - `Err(InvalidInput)` if `name` is empty or contains `=` or NUL.
+/// - `Err(InvalidData)` on Windows if the value is not valid UTF-16.
+/// - Other `io::Error`s from allocating or locking the buffer.
+///
+/// # Safety
+/// No other thread may read or modify the environment while this runs, including via
+/// `std::env`, `getenv`, or `setenv`: the value is overwritten in place without the
+/// standard library's environment lock. Call it early in `main`, before spawning threads.
+pub unsafe fn secret_from_env(name: &str) -> io::Result<Option<SecureBuf>> {
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "environment variable name is empty or contains '=' or NUL",
+        ));
+    }
+    // Safety: forwarded to the caller.
+    unsafe { imp::take(name) }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::ffi::CStr;
+    use std::io;
+    use std::os::raw::c_char;
+    use std::slice;
+
+    use crate::SecureBuf;
+    use crate::zeroize::zeroize;
+
+    #[cfg(not(target_vendor = "apple"))]
+    unsafe extern "C" {
+        static mut environ: *const *mut c_char;
+    }
+
+    #[cfg(not(target_vendor = "apple"))]
+    unsafe fn environ_ptr() -> *const *mut c_char {
+        unsafe { environ }
+    }
+
+    #[cfg(target_vendor = "apple")]
+    unsafe fn environ_ptr() -> *const *mut c_char {
+        // `environ` is not directly accessible from shared libraries on Apple platforms.
+        unsafe { *libc::_NSGetEnviron() }
+    }
+
+    pub(super) unsafe fn take(name: &str) -> io::Result<Option<SecureBuf>> {
+        let name = name.as_bytes();
+        // Safety: the caller guarantees exclusive access to the environment; each entry
+        // is a NUL-terminated "NAME=value" string owned by the process.
+        unsafe {
+            let mut entry = environ_ptr();
+            if entry.is_null() {
+                return Ok(None);
+            }
+            while !(*entry).is_null() {
+                let raw = *entry;
+                let bytes = CStr::from_ptr(raw).to_bytes();
+                if bytes.len() > name.len() && bytes.starts_with(name) && bytes[name.len()] == b'='
+                {
+                    let value_len = bytes.len() - name.len() - 1;
+                    let value =
+                        slice::from_raw_parts_mut(raw.add(name.len() + 1).cast::<u8>(), value_len);
+                    let buf = SecureBuf::from_slice(value)?;
+                    zeroize(value);
+                    return Ok(Some(buf));
+                }
+                entry = entry.add(1);
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::{ERROR_ENVVAR_NOT_FOUND, GetLastError};
+    use windows_sys::Win32::System::Environment::{
+        GetEnvironmentVariableW, SetEnvironmentVariableW,
+    };
+
+    use crate::SecureBuf;
+
+    pub(super) unsafe fn take(name: &str) -> io::Result<Option<SecureBuf>> {
+        let name: Vec<u16> = name.encode_utf16().chain(Some(0)).collect();
+
+        // The value can change between the size query and the copy; retry until it fits.
+        let wide = loop {
+            let needed = unsafe { GetEnvironmentVariableW(name.as_ptr(), ptr::null_mut(), 0) };
+            if needed == 0 {
+                if unsafe { GetLastError() } == ERROR_ENVVAR_NOT_FOUND {
+                    return Ok(None);
+                }
+                return Err(io::Error::last_os_error());
+            }
+            let mut wide = SecureBuf::new(needed as usize * 2)?;
+            // Page-aligned storage is suitably aligned for u16.
+            let dst = wide.as_mut_slice().as_mut_ptr().cast::<u16>();
+            let copied = unsafe { GetEnvironmentVariableW(name.as_ptr(), dst, needed) };
+            if copied < needed {
+                wide.truncate(copied as usize * 2);
+                break wide;
+            }
+        };
+
+        let buf = crate::secure_buf::utf8_from_utf16(
+            wide.as_slice()
+                .chunks_exact(2)
+                .map(|pair| u16::from_ne_bytes([pair[0], pair[1]])),
+        )?;
+        drop(wide);
+
+        if unsafe { SetEnvironmentVariableW(name.as_ptr(), ptr::null()) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Some(buf))
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod imp {
+    use std::io;
+
+    use crate::SecureBuf;
+
+    pub(super) unsafe fn take(_name: &str) -> io::Result<Option<SecureBuf>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "secret_from_env unsupported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::secret_from_env;
+
+    #[test]
+    fn moves_value_into_locked_buffer_and_scrubs_environment() {
+        let name = "OS_MEMLOCK_TEST_SECRET_FROM_ENV";
+        // Safety: no other test touches this variable or iterates the environment.
+        unsafe { std::env::set_var(name, "s3cret") };
+
+        let buf = unsafe { secret_from_env(name) }.unwrap().unwrap();
+        assert_eq!(buf.as_slice(), b"s3cret");
+        assert!(std::env::var(name).unwrap_or_default().is_empty());
+        assert!(
+            unsafe { secret_from_env("OS_MEMLOCK_TEST_UNSET_VAR") }
+                .unwrap()
+                .is_none()
+        );
+        assert!(unsafe { secret_from_env("BAD=NAME") }.is_err());
+    }
+}
@@ -0,0 +1,176 @@
+//! Loading key files straight into locked memory.
+
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::SecureBuf;
+use crate::zeroize::zeroize;
+
+/// Read a whole file into a locked [`SecureBuf`].
+///
+/// The buffer is sized from the file's metadata and locked before any bytes are read;
+/// the file is then read in page-sized chunks directly into it, so the contents never
+/// land in a plain `Vec` or a `BufReader`.
+///
+/// Pipes, FIFOs, `/proc` files and `<(cmd)` substitutions report a size of zero, and
+/// regular files may grow while being read. The buffer then grows with
+/// [`SecureBuf::grow`], up to [`MAX_SECRET_FILE_GROWTH`] bytes past the reported size.
+///
+/// Returns:
+/// - `Ok(buf)` holding exactly the bytes read.
+/// - `Err(InvalidData)` if there was more than `MAX_SECRET_FILE_GROWTH` bytes beyond
+///   the reported size.
+/// - Other `io::Error`s from opening, reading, or locking.
+pub fn read_secret_file(path: impl AsRef<Path>) -> io::Result<SecureBuf> {
+    read_file(File::open(path)?)
+}
+
+/// Like [`read_secret_file`], but first refuses files other users could read.
+///
+/// Platform:
+/// - Unix: fails with `PermissionDenied` if any group or other permission bit is set
+///   (i.e. the mode is not `0600` or stricter).
+/// - Elsewhere: returns `Unsupported`, as there are no mode bits to verify.
+pub fn read_secret_file_private(path: impl AsRef<Path>) -> io::Result<SecureBuf> {
+    let file = File::open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = file.metadata()?.permissions().mode();
+        if mode & 0o077 != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!(
+                    "secret file is accessible by group or others (mode {:04o}, expected 0600 or stricter)",
+                    mode & 0o7777
+                ),
+            ));
+        }
+        read_file(file)
+    }
+    #[cfg(not(unix))]
+    {
+        drop(file);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "read_secret_file_private permission checks unsupported on this platform",
+        ))
+    }
+}
+
+/// How many bytes [`read_secret_file`] reads past the size a file's metadata reports
+/// (zero for pipes, FIFOs and `/proc` files) before giving up with `InvalidData`.
+pub const MAX_SECRET_FILE_GROWTH: usize = 1 << 20;
+
+fn read_file(mut file: File) -> io::Result<SecureBuf> {
+    let expected = usize::try_from(file.metadata()?.len())
+        .map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+    let limit = expected.saturating_add(MAX_SECRET_FILE_GROWTH);
+    let mut buf = SecureBuf::new(expected)?;
+    let chunk = crate::page_size();
+
+    let mut filled = 0;
+    loop {
+        if filled == buf.len() {
+            // Full: only grow if there is more to read, so a file whose metadata was
+            // right stays in a region of exactly its size.
+            let mut probe = [0u8; 1];
+            let extra = match file.read(&mut probe) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if extra == 0 {
+                break;
+            }
+            if filled == limit {
+                zeroize(&mut probe);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "secret file has more than {MAX_SECRET_FILE_GROWTH} bytes past its \
+                         reported size"
+                    ),
+                ));
+            }
+            // Double, so a long stream is copied a logarithmic number of times.
+            let grown = buf.grow(filled.saturating_mul(2).max(filled + chunk).min(limit));
+            if let Err(e) = grown {
+                zeroize(&mut probe);
+                return Err(e);
+            }
+            buf.as_mut_slice()[filled] = probe[0];
+            zeroize(&mut probe);
+            filled += 1;
+        }
+        let end = buf.len().min(filled + chunk);
+        match file.read(&mut buf.as_mut_slice()[filled..end]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    // The file may have shrunk; never expose the zeroed tail as key material.
+    buf.truncate(filled);
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{read_secret_file, read_secret_file_private};
+
+    #[test]
+    fn reads_file_contents_and_checks_permissions() {
+        let path = std::env::temp_dir().join(format!("os-memlock-key-{}", std::process::id()));
+        fs::write(&path, b"0123456789abcdef").unwrap();
+
+        assert_eq!(
+            read_secret_file(&path).unwrap().as_slice(),
+            b"0123456789abcdef"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            let err = read_secret_file_private(&path).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+            assert_eq!(read_secret_file_private(&path).unwrap().len(), 16);
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn files_of_exactly_a_region_are_not_grown() {
+        let page = crate::page_size();
+        let path = std::env::temp_dir().join(format!("os-memlock-exact-{}", std::process::id()));
+        for len in [page, 3 * page, 2 * page + 5] {
+            fs::write(&path, vec![0x5a; len]).unwrap();
+            let buf = read_secret_file(&path).unwrap();
+            assert_eq!(buf.len(), len);
+            assert!(buf.is_locked());
+            // The whole locked region, not just the visible length.
+            assert_eq!(buf.region().1, len.next_multiple_of(page));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn reads_files_that_report_no_size() {
+        assert_eq!(fs::metadata("/proc/self/status").unwrap().len(), 0);
+        let buf = read_secret_file("/proc/self/status").unwrap();
+        assert!(buf.as_slice().starts_with(b"Name:"));
+        assert!(buf.len() > crate::page_size() / 4);
+    }
+}
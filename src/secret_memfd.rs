@@ -0,0 +1,260 @@
+//! Secrets in `memfd_secret(2)` memory that can be shared with other processes.
+
+use std::fmt;
+use std::io;
+use std::mem;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd};
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::Ordering;
+
+use crate::sealed_memfd::{Mapping, map};
+use crate::secure_buf::LOCKED_BYTES;
+
+/// `f_type` of the secretmem filesystem, from <linux/magic.h>.
+const SECRETMEM_MAGIC: u64 = 0x5345_434d;
+
+/// A secret in a `memfd_secret(2)` object, which a broker process can hand to workers
+/// with [`SecretMemfd::send`] and [`SecretMemfd::receive`].
+///
+/// Behavior:
+/// - Secret memory is removed from the kernel's direct map, so not even the kernel can
+///   read it through its own page tables; it is implicitly locked (charged to
+///   `RLIMIT_MEMLOCK`) and never swapped or dumped.
+/// - The contents are written once by `init` at construction; afterwards only shared
+///   access (`&[u8]`) is offered. Receivers map the object read-only.
+/// - Unlike [`SealedMemfd`](crate::SealedMemfd), the object cannot be sealed: any
+///   process holding the fd could still write to it. Send it only to processes you
+///   trust as much as this one.
+/// - Nothing is zeroized on drop, since the pages are shared with every process the
+///   secret was sent to; the kernel clears secret memory when the last mapping and fd
+///   are gone.
+///
+/// Platform:
+/// - Creating one needs Linux 5.14+ on x86, x86_64, aarch64 or s390x, booted with
+///   `secretmem.enable`. Elsewhere [`SecretMemfd::new`] returns `Unsupported`.
+pub struct SecretMemfd {
+    ptr: NonNull<u8>,
+    len: usize,
+    cap: usize,
+    fd: OwnedFd,
+}
+
+// Safety: shared access only hands out `&[u8]`, and the mapping is uniquely owned.
+unsafe impl Send for SecretMemfd {}
+// Safety: as above.
+unsafe impl Sync for SecretMemfd {}
+
+impl SecretMemfd {
+    /// Create a secret of `len` bytes in secret memory, filled by `init`.
+    ///
+    /// `init` receives a zeroed view of the contents; it is the only chance to write
+    /// them.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` once the contents are written.
+    /// - `Err(Unsupported)` if `memfd_secret` is unavailable (ENOSYS) or unsupported on
+    ///   this platform.
+    /// - `Err(...)` with the OS error if creating or mapping the object failed (e.g.
+    ///   `RLIMIT_MEMLOCK` exhausted).
+    pub fn new(len: usize, init: impl FnOnce(&mut [u8])) -> io::Result<Self> {
+        let fd = create()?;
+        let cap = len.next_multiple_of(crate::page_size());
+        if cap != 0 {
+            let size = libc::off_t::try_from(cap)
+                .map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+            // Safety: plain syscall on an fd we own.
+            if unsafe { libc::ftruncate(fd.as_raw_fd(), size) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let secret = Self::view(fd, len, cap, libc::PROT_READ | libc::PROT_WRITE)?;
+        // Safety: the mapping is `cap` bytes, zero-filled, and not yet shared.
+        init(unsafe { slice::from_raw_parts_mut(secret.ptr.as_ptr(), len) });
+        Ok(secret)
+    }
+
+    /// Create a secret holding a copy of `bytes`.
+    ///
+    /// The caller is responsible for zeroizing `bytes` afterwards.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        Self::new(bytes.len(), |buf| buf.copy_from_slice(bytes))
+    }
+
+    /// Send the secret to another process over a Unix socket.
+    ///
+    /// Sends the fd with `SCM_RIGHTS`, together with the secret's length; the contents
+    /// never pass through the socket. The peer maps the same pages with
+    /// [`SecretMemfd::receive`].
+    ///
+    /// Returns `Err(...)` with the OS error from `sendmsg` if sending failed.
+    pub fn send(&self, socket: impl AsFd) -> io::Result<()> {
+        crate::scm::send_fd(socket.as_fd(), self.fd.as_fd(), self.len as u64)
+    }
+
+    /// Receive a secret sent with [`SecretMemfd::send`] and map it read-only.
+    ///
+    /// The descriptor must refer to secret memory at least as large as the length sent;
+    /// anything else is refused before it is mapped.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` with the same contents as the sender's.
+    /// - `Err(UnexpectedEof)` if the peer closed the socket.
+    /// - `Err(InvalidData)` if the message or descriptor is not what `send` produces.
+    /// - `Err(...)` with the OS error if receiving or mapping failed.
+    pub fn receive(socket: impl AsFd) -> io::Result<Self> {
+        let (fd, len) = crate::scm::recv_fd(socket.as_fd())?;
+        // Safety: statfs is plain data, filled in by fstatfs.
+        let mut fs: libc::statfs = unsafe { mem::zeroed() };
+        // Safety: plain syscall on an fd we own.
+        if unsafe { libc::fstatfs(fd.as_raw_fd(), &mut fs) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if fs.f_type as u64 != SECRETMEM_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "received descriptor is not secret memory",
+            ));
+        }
+        let (len, cap) = crate::scm::checked_len(&fd, len)?;
+        Self::view(fd, len, cap, libc::PROT_READ)
+    }
+
+    /// Map `cap` bytes of the secret memory behind `fd` as the view of a `len`-byte
+    /// secret. Secret memory mappings are locked by the kernel itself.
+    fn view(fd: OwnedFd, len: usize, cap: usize, prot: libc::c_int) -> io::Result<Self> {
+        let mut secret = Self {
+            ptr: NonNull::dangling(),
+            len,
+            cap: 0,
+            fd,
+        };
+        if cap != 0 {
+            let mapping = Mapping(map(&secret.fd, cap, prot)?, cap);
+            // Secret memory is charged and reported (VmLck) like locked memory.
+            LOCKED_BYTES.fetch_add(cap, Ordering::Relaxed);
+            secret.ptr = mapping.0;
+            secret.cap = cap;
+            mem::forget(mapping);
+        }
+        Ok(secret)
+    }
+
+    /// Borrow the contents.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid for `len` readable bytes (or dangling with len 0).
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the secret has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+impl AsFd for SecretMemfd {
+    /// The secret memory fd.
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for SecretMemfd {
+    fn drop(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+        LOCKED_BYTES.fetch_sub(self.cap, Ordering::Relaxed);
+        // Safety: the mapping created in `view`, no longer referenced.
+        if unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.cap) } != 0 {
+            diag!("munmap failed: {}", io::Error::last_os_error());
+        }
+    }
+}
+
+impl fmt::Debug for SecretMemfd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("SecretMemfd")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "s390x"
+    )
+))]
+fn create() -> io::Result<OwnedFd> {
+    use std::os::fd::FromRawFd;
+
+    // Safety: plain syscall with an integer flag argument.
+    let raw = unsafe { libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC) };
+    if raw < 0 {
+        let err = io::Error::last_os_error();
+        // ENOSYS: old kernel, or booted without secretmem.enable.
+        return Err(match err.raw_os_error() {
+            Some(libc::ENOSYS) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                "memfd_secret unavailable (kernel too old or secretmem.enable off)",
+            ),
+            _ => err,
+        });
+    }
+    // Safety: the syscall returned a fresh descriptor we now own.
+    Ok(unsafe { OwnedFd::from_raw_fd(raw as libc::c_int) })
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "s390x"
+    )
+)))]
+fn create() -> io::Result<OwnedFd> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "memfd_secret unsupported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+    use std::os::unix::net::UnixStream;
+
+    use super::SecretMemfd;
+
+    #[test]
+    fn secret_memory_is_passed_over_a_socket() {
+        let secret = match SecretMemfd::from_slice(b"signing-key") {
+            Ok(secret) => secret,
+            Err(e) if e.kind() == ErrorKind::Unsupported => return,
+            Err(e) => panic!("{e}"),
+        };
+        assert!(!format!("{secret:?}").contains("signing-key"));
+        let (broker, worker) = UnixStream::pair().unwrap();
+        secret.send(&broker).unwrap();
+        let received = SecretMemfd::receive(&worker).unwrap();
+        assert_eq!(received.as_slice(), b"signing-key");
+
+        // Ordinary memory is refused.
+        let sealed = crate::SealedMemfd::from_slice(b"x").unwrap();
+        sealed.send(&broker).unwrap();
+        let err = SecretMemfd::receive(&worker).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
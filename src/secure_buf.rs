@@ -0,0 +1,1370 @@
+//! Owned secret buffer backed by locked, page-aligned memory.
+
+use std::alloc::{self, Layout};
+use std::fmt;
+use std::io;
+use std::os::raw::c_void;
+use std::ptr::NonNull;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::zeroize::zeroize;
+
+/// Bytes currently locked by live buffers; see [`crate::locked_bytes`].
+pub(crate) static LOCKED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn locked_bytes() -> usize {
+    LOCKED_BYTES.load(Ordering::Relaxed)
+}
+
+/// An owned byte buffer for secret material that is locked in RAM while it lives.
+///
+/// Behavior:
+/// - Allocates a dedicated, page-aligned, page-rounded region so locking and
+///   dump-exclusion hints never touch unrelated heap data.
+/// - Attempts `mlock` on construction. `Unsupported` is tolerated (the buffer stays
+///   usable but unlocked, see [`SecureBuf::is_locked`]); other OS errors are returned.
+/// - Applies [`exclude_from_dumps`](crate::exclude_from_dumps) as a best-effort hint
+///   where the platform supports it (`MADV_DONTDUMP`, or WER exclusion on Windows).
+/// - When Linux transparent hugepages are set to `always`, marks regions smaller than a
+///   hugepage `MADV_NOHUGEPAGE` before locking, so a small secret cannot pin and charge
+///   `RLIMIT_MEMLOCK` for a whole 2 MiB page.
+/// - On drop, zeroizes the whole region while still locked, then unlocks and frees it.
+///
+/// The contents are never moved or reallocated through unlocked memory, so no
+/// unlocked copies are left behind by resizing or [`SecureBuf::replace_with`].
+pub struct SecureBuf {
+    ptr: NonNull<u8>,
+    len: usize,
+    cap: usize,
+    /// Bytes locked from the start of the region: `cap`, or less after a degraded
+    /// [`SecureBuf::new_best_effort`].
+    locked_len: usize,
+    dump_excluded: bool,
+    backing: Backing,
+    /// Set by [`SecureBuf::seal_mapping`]; the region can then never be unmapped.
+    sealed: bool,
+}
+
+/// Where a buffer's region came from, which decides how it is released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Backing {
+    /// `std::alloc` with a page-aligned layout, locked with `mlock`.
+    Heap,
+    /// A `memfd_secret(2)` mapping; unmapping releases it.
+    SecretMem,
+    /// An anonymous private mapping from [`secure_mmap`] or, with `huge`,
+    /// [`secure_mmap_hugepages`]; unmapping releases it.
+    Mapped { huge: bool },
+}
+
+// Safety: SecureBuf uniquely owns its allocation, like Box<[u8]>.
+unsafe impl Send for SecureBuf {}
+// Safety: shared access only hands out `&[u8]`.
+unsafe impl Sync for SecureBuf {}
+
+impl SecureBuf {
+    /// Allocate a zero-filled, locked buffer of `len` bytes.
+    ///
+    /// Returns:
+    /// - `Ok(buf)` when the buffer is locked, or when locking is Unsupported on this platform.
+    /// - `Err(...)` for other OS errors (e.g., `RLIMIT_MEMLOCK` exhausted) or allocation failure.
+    pub fn new(len: usize) -> io::Result<Self> {
+        Self::allocate(len, false)
+    }
+
+    /// Like [`SecureBuf::new`], but degrades instead of failing when the lock limit is
+    /// too small for the whole buffer.
+    ///
+    /// Meant for platforms where the limit is small and cannot be raised, such as
+    /// Android (typically 64 KiB per app).
+    ///
+    /// Behavior:
+    /// - Tries `mlock` on the whole region first.
+    /// - If that fails for lack of quota or memory (ENOMEM, EPERM, EAGAIN), locks on
+    ///   fault with [`crate::mlock2`] and [`crate::MLOCK_ONFAULT`], halving the locked
+    ///   prefix of the region until it fits. Keep the most sensitive bytes at the start.
+    /// - Check [`SecureBuf::locked_len`] or [`SecureBuf::is_locked`] for the outcome; the
+    ///   unlocked tail can be swapped out.
+    /// - Growing the buffer later allocates as [`SecureBuf::new`] does.
+    ///
+    /// Returns:
+    /// - `Ok(buf)`, fully, partially, or not locked.
+    /// - `Err(...)` on allocation failure or other OS errors from `mlock`.
+    pub fn new_best_effort(len: usize) -> io::Result<Self> {
+        Self::allocate(len, true)
+    }
+
+    fn allocate(len: usize, degrade: bool) -> io::Result<Self> {
+        if len == 0 {
+            return Ok(Self {
+                ptr: NonNull::dangling(),
+                len: 0,
+                cap: 0,
+                locked_len: 0,
+                dump_excluded: false,
+                backing: Backing::Heap,
+                sealed: false,
+            });
+        }
+
+        let layout = region_layout(len)?;
+        // Safety: layout has a non-zero size.
+        let ptr = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        let cap = layout.size();
+
+        if crate::thp::should_avoid_hugepages(cap) {
+            // Must precede mlock, which faults the pages in. Best effort: a failure only
+            // risks the larger RLIMIT_MEMLOCK charge this guards against.
+            // Safety: (ptr, cap) is the allocation we just made and exclusively own.
+            let _ = unsafe { crate::madvise_nohugepage(ptr.as_ptr() as *mut c_void, cap) };
+        }
+
+        // Safety: (ptr, cap) is the allocation we just made and exclusively own.
+        let locked_len = match unsafe { crate::mlock(ptr.as_ptr() as *const c_void, cap) } {
+            Ok(()) => cap,
+            Err(e) if e.kind() == io::ErrorKind::Unsupported => 0,
+            // Safety: as above.
+            Err(e) if degrade && is_quota_error(&e) => unsafe { lock_prefix(ptr, cap) },
+            Err(e) => {
+                // Safety: allocated above with this layout and never handed out.
+                unsafe { alloc::dealloc(ptr.as_ptr(), layout) };
+                return Err(crate::limits::explain_lock_failure(e, cap));
+            }
+        };
+        LOCKED_BYTES.fetch_add(locked_len, Ordering::Relaxed);
+
+        // Safety: as above; the region is page-aligned so the advice covers exactly it.
+        let dump_excluded =
+            unsafe { crate::exclude_from_dumps(ptr.as_ptr() as *mut c_void, cap) }.is_ok();
+        crate::fork::track(ptr.as_ptr(), cap);
+
+        Ok(Self {
+            ptr,
+            len,
+            cap,
+            locked_len,
+            dump_excluded,
+            backing: Backing::Heap,
+            sealed: false,
+        })
+    }
+
+    /// Allocate a zero-filled buffer of `len` bytes in `memfd_secret(2)` memory, falling
+    /// back to [`SecureBuf::new`] where that is unavailable.
+    ///
+    /// Secret memory is removed from the kernel's direct map, so it is unreadable even
+    /// through kernel memory access, and it is never swapped or included in core dumps.
+    /// Like locked memory it counts against `RLIMIT_MEMLOCK`. The mapping is shared, so
+    /// `fork` children see (and can change) the same pages.
+    ///
+    /// Platform:
+    /// - Linux 5.14+ on x86, x86_64, aarch64 and s390x, when `secretmem.enable` is on.
+    /// - Elsewhere, or when the syscall is unavailable (ENOSYS), same as [`SecureBuf::new`].
+    ///
+    /// Returns:
+    /// - `Ok(buf)`; check [`SecureBuf::is_secret_mem`] for which backend was used.
+    /// - `Err(...)` if the mapping (or the fallback allocation) failed, e.g. because
+    ///   `RLIMIT_MEMLOCK` is exhausted.
+    pub fn new_secret_mem(len: usize) -> io::Result<Self> {
+        if len == 0 {
+            return Self::new(0);
+        }
+        let cap = region_layout(len)?.size();
+        match secret_mem::map(cap)? {
+            Some(ptr) => {
+                // Secret memory is charged and reported (VmLck) like locked memory.
+                LOCKED_BYTES.fetch_add(cap, Ordering::Relaxed);
+                Ok(Self {
+                    ptr,
+                    len,
+                    cap,
+                    locked_len: cap,
+                    dump_excluded: true,
+                    backing: Backing::SecretMem,
+                    sealed: false,
+                })
+            }
+            None => Self::new(len),
+        }
+    }
+
+    /// Allocate a locked buffer and copy `bytes` into it.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        let mut buf = Self::new(bytes.len())?;
+        buf.as_mut_slice().copy_from_slice(bytes);
+        Ok(buf)
+    }
+
+    /// Length in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the buffer has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Borrow the contents.
+    pub fn as_slice(&self) -> &[u8] {
+        // Safety: ptr is valid for `len` initialized bytes (or dangling with len 0).
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Mutably borrow the contents.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        // Safety: as above, and &mut self guarantees exclusive access.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+
+    /// Shorten the buffer to `len` bytes, zeroizing the removed tail.
+    ///
+    /// Has no effect if `len` is greater than or equal to the current length. The
+    /// locked region keeps its size until the buffer is dropped.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            zeroize(&mut self.as_mut_slice()[len..]);
+            self.len = len;
+        }
+    }
+
+    /// Extend the buffer to `new_len` bytes, zero-filling the new tail.
+    ///
+    /// Behavior:
+    /// - Within the existing locked region only the length changes.
+    /// - On Linux, mappings from [`secure_mmap`] are extended with `mremap(2)`, moving
+    ///   them if the address range after the region is taken. The kernel moves the page
+    ///   tables rather than the bytes, so the contents are never copied and the locked
+    ///   footprint never doubles. The region is `mlock`ed again afterwards; the dontdump
+    ///   and wipeonfork hints travel with the mapping.
+    /// - Mappings from [`secure_mmap_hugepages`] are only extended in place, since a move
+    ///   could lose hugepage alignment.
+    /// - Otherwise a larger region of the same kind is allocated and locked, the contents
+    ///   are copied across, and the old region is zeroized and freed after the swap.
+    ///
+    /// Returns:
+    /// - `Ok(())` on success; has no effect if `new_len <= len()`.
+    /// - `Err(...)` if the region could not be extended or locked (e.g. `RLIMIT_MEMLOCK`
+    ///   exhausted); the buffer is then unchanged.
+    pub fn grow(&mut self, new_len: usize) -> io::Result<()> {
+        if new_len <= self.cap {
+            self.len = self.len.max(new_len);
+            return Ok(());
+        }
+        if self.sealed {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "a sealed mapping cannot grow",
+            ));
+        }
+        #[cfg(target_os = "linux")]
+        if let Backing::Mapped { huge } = self.backing {
+            let remapped = self.remap(new_len, !huge);
+            if remapped.is_ok() || !huge {
+                return remapped;
+            }
+        }
+        let mut grown = match self.backing {
+            Backing::Heap => Self::new(new_len)?,
+            Backing::SecretMem => Self::new_secret_mem(new_len)?,
+            Backing::Mapped { huge: false } => secure_mmap(new_len)?,
+            Backing::Mapped { huge: true } => secure_mmap_hugepages(new_len)?,
+        };
+        grown.as_mut_slice()[..self.len].copy_from_slice(self.as_slice());
+        *self = grown;
+        Ok(())
+    }
+
+    /// Like [`SecureBuf::grow`], but never moves or copies the region.
+    ///
+    /// Platform:
+    /// - Linux, for mappings from [`secure_mmap`] and [`secure_mmap_hugepages`].
+    /// - Other buffers can only grow within their current region.
+    ///
+    /// Returns:
+    /// - `Ok(())` on success; has no effect if `new_len <= len()`.
+    /// - `Err(...)` with the OS error (typically ENOMEM) if the address range after the
+    ///   mapping is taken, or if the extension could not be locked.
+    /// - `Err(Unsupported)` if the buffer would need a new region to hold `new_len` bytes.
+    pub fn grow_in_place(&mut self, new_len: usize) -> io::Result<()> {
+        if new_len <= self.cap {
+            self.len = self.len.max(new_len);
+            return Ok(());
+        }
+        if self.sealed {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "a sealed mapping cannot grow",
+            ));
+        }
+        #[cfg(target_os = "linux")]
+        if let Backing::Mapped { .. } = self.backing {
+            return self.remap(new_len, false);
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "growing this buffer in place is unsupported",
+        ))
+    }
+
+    /// Extend a mapping with mremap(2) and lock the result.
+    #[cfg(target_os = "linux")]
+    fn remap(&mut self, new_len: usize, may_move: bool) -> io::Result<()> {
+        let Backing::Mapped { huge } = self.backing else {
+            unreachable!("only mappings are remapped");
+        };
+        let new_cap = if huge {
+            new_len
+                .checked_next_multiple_of(crate::thp::hugepage_size())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?
+        } else {
+            region_layout(new_len)?.size()
+        };
+        // Hold the fork registry throughout, so a forked child never wipes a stale range.
+        crate::fork::with_regions(|regions| {
+            let region = regions
+                .iter_mut()
+                .find(|r| r.addr == self.ptr.as_ptr() as usize);
+            // Safety: (ptr, cap) is this buffer's own mapping and `&mut self` is exclusive.
+            let ptr = unsafe { mapping::remap(self.ptr, self.cap, new_cap, may_move) }?;
+            // The kernel extends the existing lock, but re-lock so a failure is reported.
+            // Safety: (ptr, new_cap) is the mapping just extended.
+            if let Err(e) = unsafe { crate::mlock(ptr.as_ptr() as *const c_void, new_cap) } {
+                // Safety: as above. Shrinking in place keeps the contents where they are.
+                self.ptr = unsafe { mapping::remap(ptr, new_cap, self.cap, false) }.unwrap_or(ptr);
+                if let Some(region) = region {
+                    region.addr = self.ptr.as_ptr() as usize;
+                }
+                return Err(e);
+            }
+            if let Some(region) = region {
+                region.addr = ptr.as_ptr() as usize;
+                region.len = new_cap;
+            }
+            LOCKED_BYTES.fetch_add(new_cap - self.cap, Ordering::Relaxed);
+            self.ptr = ptr;
+            self.cap = new_cap;
+            self.locked_len = new_cap;
+            self.len = new_len;
+            Ok(())
+        })
+    }
+
+    /// Replace the contents with `new`, zeroizing the previous secret.
+    ///
+    /// If `new` fits in the existing locked region it is written in place and any
+    /// leftover bytes of the old value are zeroized. Otherwise the buffer first grows as
+    /// in [`SecureBuf::grow`]. Either way the buffer is never without locked backing and
+    /// neither value is copied outside locked memory.
+    ///
+    /// Returns `Err(...)` if a larger region could not be allocated or locked; the buffer
+    /// then still holds the old value.
+    pub fn replace_with(&mut self, new: &[u8]) -> io::Result<()> {
+        if new.len() > self.cap {
+            self.grow(new.len())?;
+        }
+        // Safety: ptr is valid for `cap` bytes (or dangling with cap 0) and exclusively
+        // borrowed; `new` cannot alias it.
+        let region = unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.cap) };
+        region[..new.len()].copy_from_slice(new);
+        zeroize(&mut region[new.len()..self.len.max(new.len())]);
+        self.len = new.len();
+        Ok(())
+    }
+
+    /// The whole underlying region, including the zeroed tail past `len`.
+    pub(crate) fn region(&self) -> (*mut c_void, usize) {
+        (self.ptr.as_ptr().cast(), self.cap)
+    }
+
+    /// Whether `mlock` succeeded for this buffer.
+    ///
+    /// `false` means the platform reported Unsupported and the buffer is pageable, or
+    /// that [`SecureBuf::new_best_effort`] could lock only part of it.
+    pub fn is_locked(&self) -> bool {
+        self.locked_len != 0 && self.locked_len == self.cap
+    }
+
+    /// How many bytes from the start of the buffer are locked.
+    ///
+    /// Equal to [`SecureBuf::len`] when [`SecureBuf::is_locked`]; less only after a
+    /// degraded [`SecureBuf::new_best_effort`] or where locking is unsupported.
+    pub fn locked_len(&self) -> usize {
+        self.locked_len.min(self.len)
+    }
+
+    /// Whether the dump-exclusion hint was applied to this buffer.
+    pub fn is_dump_excluded(&self) -> bool {
+        self.dump_excluded
+    }
+
+    /// Seal the buffer's mapping with [`mseal`](crate::mseal), so no code in the process
+    /// can later unmap, move, resize, or re-protect it.
+    ///
+    /// Behavior:
+    /// - The contents stay readable and writable; only the mapping itself is frozen.
+    /// - [`SecureBuf::grow`] and [`SecureBuf::grow_in_place`] fail with
+    ///   `PermissionDenied` beyond the current capacity.
+    /// - On drop the contents are zeroized and unlocked and the pages released with
+    ///   `MADV_DONTNEED` where allowed, but the address range stays reserved until the
+    ///   process exits or execs.
+    ///
+    /// Platform:
+    /// - Linux 6.10+ on 64-bit targets, for buffers from [`secure_mmap`],
+    ///   [`secure_mmap_hugepages`], [`secure_mmap_on_node`], and
+    ///   [`SecureBuf::new_secret_mem`] when secret memory was used.
+    ///
+    /// Returns:
+    /// - `Ok(())` once sealed (or if already sealed).
+    /// - `Err(Unsupported)` for heap-backed or empty buffers, whose region belongs to the
+    ///   allocator, or where the kernel lacks `mseal`.
+    /// - `Err(...)` with the OS error otherwise.
+    pub fn seal_mapping(&mut self) -> io::Result<()> {
+        if self.sealed {
+            return Ok(());
+        }
+        if self.backing == Backing::Heap || self.cap == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "only dedicated mappings can be sealed",
+            ));
+        }
+        // Safety: (ptr, cap) is this buffer's own mapping, which it never unmaps, moves,
+        // or re-protects once `sealed` is set.
+        unsafe { crate::mseal(self.ptr.as_ptr() as *mut c_void, self.cap)? };
+        self.sealed = true;
+        Ok(())
+    }
+
+    /// Whether [`SecureBuf::seal_mapping`] sealed this buffer's mapping.
+    pub fn is_mapping_sealed(&self) -> bool {
+        self.sealed
+    }
+
+    /// Whether the buffer lives in `memfd_secret` memory (see [`SecureBuf::new_secret_mem`]).
+    pub fn is_secret_mem(&self) -> bool {
+        self.backing == Backing::SecretMem
+    }
+}
+
+impl Drop for SecureBuf {
+    fn drop(&mut self) {
+        if self.cap == 0 {
+            return;
+        }
+        if self.backing != Backing::SecretMem {
+            crate::fork::untrack(self.ptr.as_ptr());
+        }
+        // Zeroize the whole region (not just `len`) while it is still locked.
+        // Safety: ptr is valid for `cap` bytes and exclusively owned.
+        zeroize(unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.cap) });
+        LOCKED_BYTES.fetch_sub(self.locked_len, Ordering::Relaxed);
+        #[cfg(windows)]
+        if self.dump_excluded {
+            // WER registrations are keyed by address and would outlive the memory.
+            // Safety: the buffer's own region, still allocated.
+            let _ = unsafe { crate::include_in_dumps(self.ptr.as_ptr().cast(), self.cap) };
+        }
+
+        if self.sealed {
+            let addr = self.ptr.as_ptr() as *mut c_void;
+            // The range can never be unmapped; unlock it and hand the pages back.
+            // Safety: the buffer's own mapping, no longer referenced.
+            unsafe {
+                if self.locked_len != 0 {
+                    let _ = crate::munlock(addr, self.locked_len);
+                }
+                let _ = crate::madvise(addr, self.cap, crate::Advice::DontNeed);
+            }
+            return;
+        }
+
+        if self.backing != Backing::Heap {
+            // Unmapping also drops any lock on the pages.
+            // Safety: a mapping created by this module, no longer referenced.
+            if let Err(e) = unsafe { mapping::unmap(self.ptr, self.cap) } {
+                diag!("munmap failed: {e}");
+            }
+            return;
+        }
+
+        if self.locked_len != 0 {
+            // Safety: same region that was locked in `new`.
+            let addr = self.ptr.as_ptr() as *const c_void;
+            if let Err(e) = unsafe { crate::munlock(addr, self.locked_len) } {
+                // Avoid panicking in Drop; emit a diagnostic.
+                diag!("munlock failed: {e}");
+            }
+        }
+
+        // Safety: the layout is recomputed from the same page size and capacity.
+        unsafe {
+            alloc::dealloc(
+                self.ptr.as_ptr(),
+                Layout::from_size_align_unchecked(self.cap, crate::page_size()),
+            )
+        };
+    }
+}
+
+impl fmt::Debug for SecureBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("SecureBuf")
+            .field("len", &self.len)
+            .field("locked", &self.is_locked())
+            .field("dump_excluded", &self.dump_excluded)
+            .field("secret_mem", &self.is_secret_mem())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Run `f` with a temporary locked, dump-excluded buffer of `len` zero bytes.
+///
+/// The buffer is zeroized, unlocked, and freed when `f` returns, including when it
+/// panics, which suits short-lived derived keys that do not warrant a named container.
+///
+/// Returns:
+/// - `Ok(R)` with the closure's result.
+/// - `Err(...)` if the buffer could not be allocated or locked; `f` is not called.
+pub fn with_locked_secret<R>(len: usize, f: impl FnOnce(&mut [u8]) -> R) -> io::Result<R> {
+    let mut buf = SecureBuf::new(len)?;
+    Ok(f(buf.as_mut_slice()))
+}
+
+/// Allocate a zero-filled, locked buffer of `len` bytes in its own anonymous mapping.
+///
+/// Unlike [`SecureBuf::new`], which carves its region out of the heap, this creates a
+/// dedicated mapping with page-exact boundaries, shared with no other allocation, and
+/// releases it on drop. On Unix it is a private `mmap` with flags a heap allocation
+/// cannot carry:
+/// - `MAP_LOCKED | MAP_NORESERVE` on Linux and Android, so the pages are locked as they
+///   are mapped and no swap space is reserved for them. The region is also `mlock`ed
+///   explicitly, so a lock that could not be established is reported.
+/// - `MAP_CONCEAL` on OpenBSD, which has no dontdump advice, and `MAP_NOCORE` on
+///   FreeBSD and DragonFly, so the kernel leaves the region out of core dumps from the
+///   moment it is mapped, before any advice.
+/// - Best-effort `madvise_dontdump` and `madvise_wipeonfork`, so the region is left out
+///   of core dumps and reads as zeros in `fork` children where supported.
+///
+/// On Windows it is a `VirtualAlloc` reservation, locked with `VirtualLock`, registered
+/// with WER as excluded from crash dumps, and released with `VirtualFree`.
+///
+/// On Haiku it is a `B_FULL_LOCK` area, wired by the kernel from creation (so no `mlock`
+/// is needed, which older releases lack) and deleted on drop.
+///
+/// Platform:
+/// - Unix targets and Windows. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(buf)` with a locked mapping (see [`SecureBuf::is_dump_excluded`] for the hint).
+/// - `Err(...)` if mapping or locking failed (e.g. `RLIMIT_MEMLOCK` exhausted).
+pub fn secure_mmap(len: usize) -> io::Result<SecureBuf> {
+    if len == 0 {
+        return SecureBuf::new(0);
+    }
+    let cap = region_layout(len)?.size();
+    let ptr = mapping::anonymous(cap)?;
+    lock_mapping(SecureBuf {
+        ptr,
+        len,
+        cap,
+        locked_len: 0,
+        dump_excluded: false,
+        backing: Backing::Mapped { huge: false },
+        sealed: false,
+    })
+}
+
+/// Like [`secure_mmap`], but backed by hugepages for large locked arenas.
+///
+/// Hugepages cut TLB misses and the number of page-table entries the kernel has to pin,
+/// which matters for locked regions of hundreds of MiB. The region is rounded up to a
+/// whole number of hugepages (2 MiB on x86_64), so this is wasteful for small secrets.
+///
+/// Platform:
+/// - Linux and Android: a `MAP_HUGETLB` mapping from the reserved hugetlb pool
+///   (`vm.nr_hugepages`). If the pool cannot satisfy the request, a hugepage-aligned
+///   mapping is advised `MADV_HUGEPAGE` before locking so transparent hugepages back it
+///   where THP is `always` or `madvise`; otherwise it ends up with normal pages.
+/// - Windows: enables `SeLockMemoryPrivilege` in the process token and allocates
+///   large pages (`GetLargePageMinimum`, usually 2 MiB) with
+///   `VirtualAlloc(MEM_LARGE_PAGES)`. Large pages are never paged out, so the region
+///   counts as locked without `VirtualLock` and without touching the working-set quota.
+///   The account needs the "Lock pages in memory" user right; without it this returns
+///   `PermissionDenied`.
+/// - Other Unix targets: same as [`secure_mmap`].
+/// - Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(buf)` with a locked mapping.
+/// - `Err(...)` if mapping or locking failed (e.g. `RLIMIT_MEMLOCK` exhausted, or on
+///   Windows too little contiguous physical memory for large pages).
+pub fn secure_mmap_hugepages(len: usize) -> io::Result<SecureBuf> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    if len != 0 {
+        let huge = crate::thp::hugepage_size();
+        let cap = len
+            .checked_next_multiple_of(huge)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        let (ptr, hugetlb) = match mapping::hugetlb(cap)? {
+            Some(ptr) => (ptr, true),
+            None => (mapping::aligned(cap, huge)?, false),
+        };
+        let buf = SecureBuf {
+            ptr,
+            len,
+            cap,
+            locked_len: 0,
+            dump_excluded: false,
+            backing: Backing::Mapped { huge: true },
+            sealed: false,
+        };
+        if !hugetlb {
+            // Must precede mlock, which faults the pages in. Best effort: without THP
+            // the region is still usable with normal pages.
+            // Safety: (ptr, cap) is the mapping created above, owned by `buf`.
+            let _ = unsafe { crate::madvise_hugepage(ptr.as_ptr() as *mut c_void, cap) };
+        }
+        return lock_mapping(buf);
+    }
+    #[cfg(windows)]
+    if len != 0 {
+        let (ptr, cap) = mapping::large_pages(len)?;
+        let mut buf = SecureBuf {
+            ptr,
+            len,
+            cap,
+            locked_len: cap,
+            dump_excluded: false,
+            backing: Backing::Mapped { huge: true },
+            sealed: false,
+        };
+        LOCKED_BYTES.fetch_add(cap, Ordering::Relaxed);
+        // Safety: (ptr, cap) is the allocation created above, owned by `buf`.
+        buf.dump_excluded = unsafe { crate::exclude_from_dumps(ptr.as_ptr().cast(), cap) }.is_ok();
+        return Ok(buf);
+    }
+    secure_mmap(len)
+}
+
+/// Like [`secure_mmap`], but with the pages bound to NUMA node `node`.
+///
+/// The region is bound with `mbind(2)` (`MPOL_BIND`) before it is locked, so every page
+/// is faulted in on `node` and, being locked, is never migrated away. Use it to keep key
+/// material next to the cores that use it in latency-sensitive services; pair it with
+/// CPU affinity for those threads.
+///
+/// Platform:
+/// - Linux. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - `Ok(buf)` with a locked mapping whose pages are on `node`. [`SecureBuf::grow`]
+///   keeps the binding; the mapping's policy travels with `mremap`.
+/// - `Err(InvalidInput)` if `node` is past the last node the system could have
+///   (`/sys/devices/system/node/possible`).
+/// - `Err(...)` with EINVAL if `node` is offline, has no memory, or is outside this
+///   process's cpuset; otherwise the OS error from mapping or locking (e.g. ENOMEM when
+///   `node` cannot hold the region).
+/// - `Err(Unsupported)` if the kernel was built without NUMA support.
+pub fn secure_mmap_on_node(len: usize, node: u32) -> io::Result<SecureBuf> {
+    #[cfg(target_os = "linux")]
+    {
+        if len == 0 {
+            return SecureBuf::new(0);
+        }
+        let cap = region_layout(len)?.size();
+        let buf = SecureBuf {
+            ptr: mapping::unpopulated(cap)?,
+            len,
+            cap,
+            locked_len: 0,
+            dump_excluded: false,
+            backing: Backing::Mapped { huge: false },
+            sealed: false,
+        };
+        // Safety: (ptr, cap) is the mapping created above, owned by `buf`, which unmaps
+        // it if binding fails.
+        unsafe { mapping::bind(buf.ptr, cap, node)? };
+        lock_mapping(buf)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (len, node);
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "NUMA binding unsupported on this platform",
+        ))
+    }
+}
+
+/// Whether an `mlock` failure means the lock quota or memory ran out, rather than a bad
+/// request.
+fn is_quota_error(e: &io::Error) -> bool {
+    // ERROR_WORKING_SET_QUOTA from VirtualLock arrives as OutOfMemory.
+    matches!(
+        e.kind(),
+        io::ErrorKind::OutOfMemory | io::ErrorKind::PermissionDenied | io::ErrorKind::WouldBlock
+    )
+}
+
+/// Lock as long a prefix of `(ptr, cap)` as the limit allows, on fault, halving the
+/// length after each failure. Returns the number of bytes locked.
+///
+/// # Safety
+/// `(ptr, cap)` must be a page-aligned region exclusively owned by the caller.
+unsafe fn lock_prefix(ptr: NonNull<u8>, cap: usize) -> usize {
+    let page = crate::page_size();
+    let mut len = cap;
+    loop {
+        // Safety: a prefix of the caller's region.
+        if unsafe { crate::mlock2(ptr.as_ptr() as *const c_void, len, crate::MLOCK_ONFAULT) }
+            .is_ok()
+        {
+            return len;
+        }
+        if len <= page {
+            return 0;
+        }
+        len = (len / 2 / page * page).max(page);
+    }
+}
+
+/// Lock a freshly mapped buffer and apply the dontdump and wipeonfork hints.
+fn lock_mapping(mut buf: SecureBuf) -> io::Result<SecureBuf> {
+    let addr = buf.ptr.as_ptr() as *mut c_void;
+    // Safety: (addr, cap) is the buffer's own mapping; on error `buf` unmaps it.
+    unsafe {
+        // Haiku wires the pages of the `B_FULL_LOCK` area when it is created.
+        #[cfg(not(target_os = "haiku"))]
+        crate::mlock(addr, buf.cap).map_err(|e| crate::limits::explain_lock_failure(e, buf.cap))?;
+        buf.locked_len = buf.cap;
+        LOCKED_BYTES.fetch_add(buf.cap, Ordering::Relaxed);
+        // On OpenBSD the mapping is `MAP_CONCEAL`, which madvise cannot express.
+        buf.dump_excluded =
+            cfg!(target_os = "openbsd") || crate::exclude_from_dumps(addr, buf.cap).is_ok();
+        let _ = crate::madvise_wipeonfork(addr, buf.cap);
+    }
+    crate::fork::track(buf.ptr.as_ptr(), buf.cap);
+    Ok(buf)
+}
+
+/// Transcode UTF-16 code units to UTF-8 directly into a new locked buffer.
+///
+/// Returns `Err(InvalidData)` for unpaired surrogates.
+#[cfg(windows)]
+pub(crate) fn utf8_from_utf16<I>(units: I) -> io::Result<SecureBuf>
+where
+    I: Iterator<Item = u16> + Clone,
+{
+    let mut len = 0;
+    for c in char::decode_utf16(units.clone()) {
+        len += c
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-16"))?
+            .len_utf8();
+    }
+    let mut buf = SecureBuf::new(len)?;
+    let mut at = 0;
+    for c in char::decode_utf16(units).flatten() {
+        at += c.encode_utf8(&mut buf.as_mut_slice()[at..]).len();
+    }
+    Ok(buf)
+}
+
+#[cfg(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "s390x"
+    )
+))]
+mod secret_mem {
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::ptr::{self, NonNull};
+
+    /// Map `cap` bytes of secret memory, or `Ok(None)` if memfd_secret is unavailable.
+    pub(super) fn map(cap: usize) -> io::Result<Option<NonNull<u8>>> {
+        // Safety: plain syscall with an integer flag argument.
+        let raw = unsafe { libc::syscall(libc::SYS_memfd_secret, libc::O_CLOEXEC) };
+        if raw < 0 {
+            let err = io::Error::last_os_error();
+            // ENOSYS: old kernel, or booted without secretmem.enable.
+            return match err.raw_os_error() {
+                Some(libc::ENOSYS) => Ok(None),
+                _ => Err(err),
+            };
+        }
+        // Safety: the syscall returned a fresh descriptor we now own.
+        let fd = unsafe { OwnedFd::from_raw_fd(raw as libc::c_int) };
+        let size =
+            libc::off_t::try_from(cap).map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        // Safety: fd is a valid secretmem descriptor.
+        if unsafe { libc::ftruncate(fd.as_raw_fd(), size) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Safety: a new shared mapping of the whole descriptor; the mapping keeps the
+        // memory alive after fd is closed.
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                cap,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(NonNull::new(addr.cast()))
+    }
+}
+
+#[cfg(not(all(
+    target_os = "linux",
+    any(
+        target_arch = "x86",
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "s390x"
+    )
+)))]
+mod secret_mem {
+    use std::io;
+    use std::ptr::NonNull;
+
+    pub(super) fn map(_cap: usize) -> io::Result<Option<NonNull<u8>>> {
+        Ok(None)
+    }
+}
+
+#[cfg(unix)]
+mod mapping {
+    use std::io;
+    use std::os::raw::c_void;
+    use std::ptr::{self, NonNull};
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    const EXTRA_FLAGS: libc::c_int = libc::MAP_LOCKED | libc::MAP_NORESERVE;
+    // Excluded from core dumps at mapping time, the only way OpenBSD offers.
+    #[cfg(target_os = "openbsd")]
+    const EXTRA_FLAGS: libc::c_int = libc::MAP_CONCEAL;
+    // Left out of cores from the first fault, before `MADV_NOCORE` is applied.
+    #[cfg(any(target_os = "freebsd", target_os = "dragonfly"))]
+    const EXTRA_FLAGS: libc::c_int = libc::MAP_NOCORE;
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "openbsd",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "haiku"
+    )))]
+    const EXTRA_FLAGS: libc::c_int = 0;
+
+    /// Create a private anonymous read-write mapping of `cap` bytes.
+    #[cfg(not(target_os = "haiku"))]
+    pub(super) fn anonymous(cap: usize) -> io::Result<NonNull<u8>> {
+        map(cap, libc::MAP_PRIVATE | libc::MAP_ANON | EXTRA_FLAGS)
+    }
+
+    /// Create a `B_FULL_LOCK` area of `cap` bytes, whose pages are wired for as long as
+    /// the area exists, whether or not this Haiku release has `mlock`.
+    #[cfg(target_os = "haiku")]
+    pub(super) fn anonymous(cap: usize) -> io::Result<NonNull<u8>> {
+        let mut addr = ptr::null_mut::<c_void>();
+        // Safety: a new area at an address of the kernel's choosing; `addr` receives it.
+        let area = unsafe {
+            libc::create_area(
+                c"os-memlock secret".as_ptr(),
+                &mut addr,
+                libc::B_ANY_ADDRESS,
+                cap,
+                libc::B_FULL_LOCK,
+                libc::B_READ_AREA | libc::B_WRITE_AREA,
+            )
+        };
+        // Haiku's error codes are its errno values.
+        if area < 0 {
+            return Err(io::Error::from_raw_os_error(area));
+        }
+        NonNull::new(addr.cast()).ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))
+    }
+
+    /// Like [`anonymous`], but without `MAP_LOCKED`, so no page is faulted in until the
+    /// caller has set a memory policy and locks the region.
+    #[cfg(target_os = "linux")]
+    pub(super) fn unpopulated(cap: usize) -> io::Result<NonNull<u8>> {
+        map(
+            cap,
+            libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_NORESERVE,
+        )
+    }
+
+    #[cfg(not(target_os = "haiku"))]
+    fn map(cap: usize, flags: libc::c_int) -> io::Result<NonNull<u8>> {
+        // Safety: a new mapping that does not overlap any existing memory.
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                cap,
+                libc::PROT_READ | libc::PROT_WRITE,
+                flags,
+                -1,
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        NonNull::new(addr.cast()).ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))
+    }
+
+    /// Bind the pages of a mapping to NUMA node `node` with mbind(2) (`MPOL_BIND`).
+    ///
+    /// `MPOL_MF_STRICT` makes the call fail if any page is already resident elsewhere.
+    ///
+    /// # Safety
+    /// (ptr, cap) must be a mapping created by this module and owned by the caller.
+    #[cfg(target_os = "linux")]
+    pub(super) unsafe fn bind(ptr: NonNull<u8>, cap: usize, node: u32) -> io::Result<()> {
+        // <linux/mempolicy.h>; not exported by libc.
+        const MPOL_MF_STRICT: libc::c_uint = 1;
+        const BITS: usize = libc::c_ulong::BITS as usize;
+
+        let node = node as usize;
+        // The mask is sized from `node`, so an absurd one must not get that far.
+        if node >= possible_nodes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("NUMA node {node} does not exist on this system"),
+            ));
+        }
+        let mut mask = vec![0 as libc::c_ulong; node / BITS + 1];
+        mask[node / BITS] |= 1 << (node % BITS);
+        // The kernel reads one bit fewer than `maxnode`, as libnuma's numa_bind does.
+        let maxnode = (mask.len() * BITS + 1) as libc::c_ulong;
+        // Safety: guaranteed by the caller; `mask` holds `maxnode - 1` bits.
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_mbind,
+                ptr.as_ptr().cast::<c_void>(),
+                cap,
+                libc::c_long::from(libc::MPOL_BIND),
+                mask.as_ptr(),
+                maxnode,
+                MPOL_MF_STRICT,
+            )
+        };
+        if rc == 0 {
+            return Ok(());
+        }
+        let err = io::Error::last_os_error();
+        match err.raw_os_error() {
+            Some(libc::ENOSYS) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "NUMA memory policies unsupported by this kernel",
+            )),
+            _ => Err(err),
+        }
+    }
+
+    /// One past the highest node id the kernel could ever bring online.
+    #[cfg(target_os = "linux")]
+    fn possible_nodes() -> usize {
+        // 1 << CONFIG_NODES_SHIFT at its largest, for kernels without the sysfs file.
+        const MAX_NUMNODES: usize = 1 << 10;
+        std::fs::read_to_string("/sys/devices/system/node/possible")
+            .ok()
+            .and_then(|list| list.trim().rsplit([',', '-']).next()?.parse::<usize>().ok())
+            .map_or(MAX_NUMNODES, |last| last + 1)
+    }
+
+    /// Map `cap` bytes (a multiple of the hugepage size) from the hugetlb pool, or
+    /// `Ok(None)` if the pool cannot supply them.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(super) fn hugetlb(cap: usize) -> io::Result<Option<NonNull<u8>>> {
+        // No MAP_NORESERVE: without a reservation, running out of pool pages later
+        // would be a SIGBUS on first touch instead of an error here.
+        // Safety: a new mapping that does not overlap any existing memory.
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                cap,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_HUGETLB | libc::MAP_LOCKED,
+                -1,
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            let err = io::Error::last_os_error();
+            // ENOMEM: pool too small; EINVAL: no hugetlb support for this size.
+            return match err.raw_os_error() {
+                Some(libc::ENOMEM | libc::EINVAL) => Ok(None),
+                _ => Err(err),
+            };
+        }
+        Ok(NonNull::new(addr.cast()))
+    }
+
+    /// Map `cap` bytes starting at a multiple of `align`, a power of two.
+    ///
+    /// Over-maps by `align` and trims both ends, since mmap only guarantees page
+    /// alignment. Locking is left to the caller, after any madvise.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(super) fn aligned(cap: usize, align: usize) -> io::Result<NonNull<u8>> {
+        let total = cap
+            .checked_add(align)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        // Safety: a new mapping that does not overlap any existing memory.
+        let base = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                total,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_NORESERVE,
+                -1,
+                0,
+            )
+        };
+        if base == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let head = (base as usize).next_multiple_of(align) - base as usize;
+        let tail = total - head - cap;
+        // Safety: both ranges are page-aligned pieces of the mapping just created, and
+        // unmapping them cannot fail for lack of resources.
+        unsafe {
+            if head != 0 {
+                libc::munmap(base, head);
+            }
+            if tail != 0 {
+                libc::munmap(base.cast::<u8>().add(head + cap).cast(), tail);
+            }
+        }
+        NonNull::new(base.cast::<u8>().wrapping_add(head))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))
+    }
+
+    /// Resize a mapping created by [`anonymous`], [`hugetlb`] or [`aligned`] from `cap` to
+    /// `new_cap` bytes, letting the kernel move it only if `may_move`.
+    ///
+    /// # Safety
+    /// (ptr, cap) must be a whole mapping created by this module and not referenced
+    /// through other pointers, since it may move.
+    #[cfg(target_os = "linux")]
+    pub(super) unsafe fn remap(
+        ptr: NonNull<u8>,
+        cap: usize,
+        new_cap: usize,
+        may_move: bool,
+    ) -> io::Result<NonNull<u8>> {
+        let flags = if may_move { libc::MREMAP_MAYMOVE } else { 0 };
+        // Safety: guaranteed by the caller.
+        let addr = unsafe { libc::mremap(ptr.as_ptr().cast::<c_void>(), cap, new_cap, flags) };
+        if addr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        NonNull::new(addr.cast()).ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))
+    }
+
+    /// # Safety
+    /// (ptr, cap) must be a whole mapping created by this module (or `secret_mem`) that
+    /// is no longer referenced.
+    #[cfg(not(target_os = "haiku"))]
+    pub(super) unsafe fn unmap(ptr: NonNull<u8>, cap: usize) -> io::Result<()> {
+        // Safety: guaranteed by the caller.
+        if unsafe { libc::munmap(ptr.as_ptr().cast::<c_void>(), cap) } == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+
+    /// # Safety
+    /// `ptr` must be the start of an area created by [`anonymous`] that is no longer
+    /// referenced.
+    #[cfg(target_os = "haiku")]
+    pub(super) unsafe fn unmap(ptr: NonNull<u8>, _cap: usize) -> io::Result<()> {
+        // Safety: querying an address has no side effects.
+        let area = unsafe { libc::area_for(ptr.as_ptr().cast()) };
+        if area < 0 {
+            return Err(io::Error::from_raw_os_error(area));
+        }
+        // Safety: guaranteed by the caller.
+        match unsafe { libc::delete_area(area) } {
+            0 => Ok(()),
+            status => Err(io::Error::from_raw_os_error(status)),
+        }
+    }
+}
+
+#[cfg(windows)]
+mod mapping {
+    use std::io;
+    use std::ptr::{self, NonNull};
+
+    use windows_sys::Win32::System::Memory::{
+        MEM_COMMIT, MEM_LARGE_PAGES, MEM_RELEASE, MEM_RESERVE, PAGE_READWRITE, VirtualAlloc,
+        VirtualFree,
+    };
+
+    /// Reserve and commit a dedicated region of `cap` bytes.
+    pub(super) fn anonymous(cap: usize) -> io::Result<NonNull<u8>> {
+        // Safety: a new allocation; no existing memory is touched.
+        let addr =
+            unsafe { VirtualAlloc(ptr::null(), cap, MEM_RESERVE | MEM_COMMIT, PAGE_READWRITE) };
+        NonNull::new(addr.cast()).ok_or_else(io::Error::last_os_error)
+    }
+
+    /// Allocate at least `len` bytes of large pages. Returns the allocation and its size,
+    /// a whole number of large pages.
+    pub(super) fn large_pages(len: usize) -> io::Result<(NonNull<u8>, usize)> {
+        let Some(large) = crate::large_page_size() else {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "large pages unsupported by this processor",
+            ));
+        };
+        let cap = len
+            .checked_next_multiple_of(large)
+            .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+        crate::windows::enable_lock_memory_privilege()?;
+        // Safety: a new allocation; no existing memory is touched.
+        let addr = unsafe {
+            VirtualAlloc(
+                ptr::null(),
+                cap,
+                MEM_RESERVE | MEM_COMMIT | MEM_LARGE_PAGES,
+                PAGE_READWRITE,
+            )
+        };
+        match NonNull::new(addr.cast()) {
+            Some(ptr) => Ok((ptr, cap)),
+            None => Err(io::Error::last_os_error()),
+        }
+    }
+
+    /// # Safety
+    /// `ptr` must be an allocation from this module, no longer referenced.
+    pub(super) unsafe fn unmap(ptr: NonNull<u8>, _cap: usize) -> io::Result<()> {
+        // Safety: guaranteed by the caller; MEM_RELEASE frees the whole allocation.
+        if unsafe { VirtualFree(ptr.as_ptr().cast(), 0, MEM_RELEASE) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod mapping {
+    use std::io;
+    use std::ptr::NonNull;
+
+    pub(super) fn anonymous(_cap: usize) -> io::Result<NonNull<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "secure_mmap unsupported on this platform",
+        ))
+    }
+
+    /// # Safety
+    /// Never called: no mappings are created on this platform.
+    pub(super) unsafe fn unmap(_ptr: NonNull<u8>, _cap: usize) -> io::Result<()> {
+        unreachable!("no memory mappings on this platform")
+    }
+}
+
+/// Page-aligned layout covering `len` bytes, rounded up to whole pages.
+fn region_layout(len: usize) -> io::Result<Layout> {
+    let page = crate::page_size();
+    let cap = len
+        .checked_next_multiple_of(page)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::OutOfMemory))?;
+    Layout::from_size_align(cap, page).map_err(|_| io::Error::from(io::ErrorKind::OutOfMemory))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SecureBuf, secure_mmap, secure_mmap_hugepages, with_locked_secret};
+
+    #[test]
+    fn from_slice_round_trips_and_hides_contents_in_debug() {
+        let buf = SecureBuf::from_slice(b"hunter2").unwrap();
+        assert_eq!(buf.as_slice(), b"hunter2");
+        assert!(!format!("{buf:?}").contains("hunter2"));
+    }
+
+    #[test]
+    fn replace_with_overwrites_in_place_or_grows() {
+        let mut buf = SecureBuf::from_slice(b"old-session-key").unwrap();
+        buf.replace_with(b"new").unwrap();
+        assert_eq!(buf.as_slice(), b"new");
+        buf.replace_with(b"rotated-key").unwrap();
+        assert_eq!(buf.as_slice(), b"rotated-key");
+
+        let big = vec![7u8; crate::page_size() + 1];
+        buf.replace_with(&big).unwrap();
+        assert_eq!(buf.as_slice(), &big[..]);
+    }
+
+    #[test]
+    fn secret_mem_round_trips_or_falls_back() {
+        let mut buf = SecureBuf::new_secret_mem(16).unwrap();
+        assert!(buf.is_locked());
+        buf.as_mut_slice().copy_from_slice(b"0123456789abcdef");
+        let big = vec![9u8; crate::page_size() * 2];
+        buf.replace_with(&big).unwrap();
+        assert_eq!(buf.as_slice(), &big[..]);
+        if buf.is_secret_mem() {
+            assert!(buf.is_dump_excluded());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secure_mmap_is_locked_and_grows_as_a_mapping() {
+        let mut buf = secure_mmap(100).unwrap();
+        assert!(buf.is_locked());
+        assert!(buf.as_slice().iter().all(|&b| b == 0));
+        buf.as_mut_slice().fill(3);
+        let big = vec![4u8; crate::page_size() + 1];
+        buf.replace_with(&big).unwrap();
+        assert_eq!(buf.as_slice(), &big[..]);
+        assert_eq!(buf.backing, super::Backing::Mapped { huge: false });
+        #[cfg(any(target_os = "openbsd", target_os = "freebsd", target_os = "dragonfly"))]
+        assert!(buf.is_dump_excluded());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn secure_mmap_hugepages_rounds_to_whole_hugepages() {
+        let mut buf = secure_mmap_hugepages(10).unwrap();
+        assert!(buf.is_locked());
+        assert_eq!(buf.len(), 10);
+        buf.as_mut_slice().copy_from_slice(b"0123456789");
+        #[cfg(target_os = "linux")]
+        {
+            let huge = crate::thp::hugepage_size();
+            assert_eq!(buf.cap % huge, 0);
+            assert_eq!(buf.ptr.as_ptr() as usize % huge, 0);
+        }
+    }
+
+    #[test]
+    fn grow_keeps_contents_and_zero_fills() {
+        let page = crate::page_size();
+        let mut buf = SecureBuf::from_slice(b"seed").unwrap();
+        buf.grow(page * 2).unwrap();
+        assert_eq!(&buf.as_slice()[..4], b"seed");
+        assert!(buf.as_slice()[4..].iter().all(|&b| b == 0));
+        assert!(buf.grow_in_place(page * 3).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn secure_mmap_grows_with_mremap() {
+        let page = crate::page_size();
+        let mut buf = secure_mmap(page).unwrap();
+        buf.as_mut_slice().fill(6);
+        buf.grow(page * 64).unwrap();
+        assert!(buf.as_slice()[..page].iter().all(|&b| b == 6));
+        assert!(buf.as_slice()[page..].iter().all(|&b| b == 0));
+        // The whole mapping is still locked, wherever it ended up.
+        assert!(crate::verify_region_locked(buf.as_slice().as_ptr().cast(), buf.len()).unwrap());
+        match buf.grow_in_place(page * 65) {
+            Ok(()) => assert_eq!(buf.len(), page * 65),
+            Err(e) => assert_eq!(e.raw_os_error(), Some(libc::ENOMEM)),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn secure_mmap_on_node_binds_to_node_zero_or_reports_no_numa() {
+        match super::secure_mmap_on_node(100, 0) {
+            Ok(buf) => assert!(buf.is_locked()),
+            Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported),
+        }
+        for node in [4095, u32::MAX] {
+            let err = super::secure_mmap_on_node(100, node).unwrap_err();
+            assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn sealed_mapping_stays_writable_but_cannot_grow() {
+        let mut heap = SecureBuf::new(16).unwrap();
+        let err = heap.seal_mapping().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+
+        let mut buf = secure_mmap(16).unwrap();
+        match buf.seal_mapping() {
+            Ok(()) => {
+                assert!(buf.is_mapping_sealed());
+                buf.as_mut_slice().copy_from_slice(b"0123456789abcdef");
+                let err = buf.grow(crate::page_size() * 2).unwrap_err();
+                assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+                assert_eq!(buf.as_slice(), b"0123456789abcdef");
+            }
+            Err(e) => assert_eq!(e.kind(), std::io::ErrorKind::Unsupported),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn best_effort_locks_a_prefix_under_a_small_limit() {
+        const LIMIT: usize = 64 * 1024;
+        // The lowered limit is process-wide, so keep it away from the other tests.
+        if !crate::test_util::in_own_process(
+            "secure_buf::tests::best_effort_locks_a_prefix_under_a_small_limit",
+        ) {
+            return;
+        }
+        let mut lim = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // Safety: plain syscalls on a valid rlimit; munlockall only drops earlier locks.
+        unsafe {
+            assert_eq!(libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut lim), 0);
+            lim.rlim_cur = lim.rlim_max.min(LIMIT as libc::rlim_t);
+            assert_eq!(libc::setrlimit(libc::RLIMIT_MEMLOCK, &lim), 0);
+            libc::munlockall();
+        }
+        // CAP_IPC_LOCK bypasses the limit; there is nothing to test without dropping it.
+        if SecureBuf::new(16 * LIMIT).is_ok() {
+            return;
+        }
+        let buf = SecureBuf::new_best_effort(16 * LIMIT).unwrap();
+        assert!(!buf.is_locked());
+        assert!(buf.locked_len() > 0 && buf.locked_len() <= LIMIT);
+    }
+
+    #[test]
+    fn zero_length_is_empty_and_unlocked() {
+        let buf = SecureBuf::new(0).unwrap();
+        assert!(buf.is_empty());
+        assert!(!buf.is_locked());
+    }
+
+    #[test]
+    fn with_locked_secret_passes_zeroed_buffer_and_returns_result() {
+        let sum = with_locked_secret(32, |buf| {
+            assert!(buf.iter().all(|&b| b == 0));
+            buf.fill(5);
+            buf.iter().map(|&b| b as u32).sum::<u32>()
+        })
+        .unwrap();
+        assert_eq!(sum, 160);
+    }
+}
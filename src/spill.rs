@@ -0,0 +1,218 @@
+//! Spilling secrets that cannot be locked to DPAPI ciphertext instead of pageable
+//! plaintext (Windows).
+
+use std::fmt;
+use std::io;
+
+use crate::SecureBuf;
+
+/// A secret kept in locked memory, or, when it cannot be locked, encrypted with DPAPI
+/// (`CryptProtectData`) so only ciphertext can reach the page file.
+///
+/// Behavior:
+/// - Allocates like [`SecureBuf::new_best_effort`]. If the whole buffer is locked, the
+///   secret lives there.
+/// - Otherwise (working-set quota or lock limit exhausted, or locking unsupported), on
+///   Windows the secret is encrypted straight from the caller's slice with
+///   `CryptProtectData` and only the ciphertext is kept; see [`is_spilled`](Self::is_spilled).
+/// - [`with_secret`](Self::with_secret) decrypts a spilled secret into a fresh
+///   best-effort [`SecureBuf`] for the duration of the call. `CryptUnprotectData` hands
+///   the plaintext back in a pageable `LocalAlloc` buffer, which is zeroized and freed
+///   right after the copy.
+///
+/// DPAPI keys are tied to the user's logon credentials: other processes of the same
+/// user can decrypt the ciphertext, as they could read this process's memory.
+///
+/// Platform:
+/// - Windows spills. Elsewhere there is no DPAPI, and a secret that cannot be fully
+///   locked stays in the degraded [`SecureBuf`], as with `new_best_effort`.
+pub struct SpillableSecret {
+    inner: Inner,
+}
+
+enum Inner {
+    Locked(SecureBuf),
+    Spilled(Vec<u8>),
+}
+
+impl SpillableSecret {
+    /// Keep a copy of `bytes`, locked if possible and spilled to DPAPI ciphertext if not.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    ///
+    /// Returns:
+    /// - `Ok(secret)` once the copy is locked, spilled, or (off Windows) degraded
+    /// - `Err(...)` if allocation failed, or with the OS error if `CryptProtectData`
+    ///   failed
+    pub fn from_slice(bytes: &[u8]) -> io::Result<Self> {
+        let mut buf = SecureBuf::new_best_effort(bytes.len())?;
+        if !buf.is_locked() && !bytes.is_empty() {
+            drop(buf);
+            match imp::protect(bytes) {
+                Ok(ciphertext) => {
+                    return Ok(Self {
+                        inner: Inner::Spilled(ciphertext),
+                    });
+                }
+                Err(e) if e.kind() != io::ErrorKind::Unsupported => return Err(e),
+                Err(_) => buf = SecureBuf::new_best_effort(bytes.len())?,
+            }
+        }
+        buf.as_mut_slice().copy_from_slice(bytes);
+        Ok(Self {
+            inner: Inner::Locked(buf),
+        })
+    }
+
+    /// Whether the secret was spilled to DPAPI ciphertext rather than kept locked.
+    pub fn is_spilled(&self) -> bool {
+        matches!(self.inner, Inner::Spilled(_))
+    }
+
+    /// Run `f` with the plaintext, decrypting a spilled secret first.
+    ///
+    /// Returns:
+    /// - `Ok(f(..))`
+    /// - `Err(...)` with the OS error if `CryptUnprotectData` failed, e.g. after the
+    ///   user's credentials changed
+    pub fn with_secret<R>(&self, f: impl FnOnce(&[u8]) -> R) -> io::Result<R> {
+        match &self.inner {
+            Inner::Locked(buf) => Ok(f(buf.as_slice())),
+            Inner::Spilled(ciphertext) => {
+                let plaintext = imp::unprotect(ciphertext)?;
+                Ok(f(plaintext.as_slice()))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SpillableSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents, nor the ciphertext.
+        f.debug_struct("SpillableSecret")
+            .field("spilled", &self.is_spilled())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::ptr;
+    use std::slice;
+
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Cryptography::{
+        CRYPT_INTEGER_BLOB, CRYPTPROTECT_UI_FORBIDDEN, CryptProtectData, CryptUnprotectData,
+    };
+
+    use crate::SecureBuf;
+    use crate::zeroize::zeroize;
+
+    fn blob(bytes: &[u8]) -> io::Result<CRYPT_INTEGER_BLOB> {
+        Ok(CRYPT_INTEGER_BLOB {
+            cbData: u32::try_from(bytes.len())
+                .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?,
+            pbData: bytes.as_ptr().cast_mut(),
+        })
+    }
+
+    /// Run `call` and take ownership of the `LocalAlloc` buffer it produced.
+    fn take_output(call: impl FnOnce(*mut CRYPT_INTEGER_BLOB) -> i32) -> io::Result<Output> {
+        let mut out = CRYPT_INTEGER_BLOB::default();
+        if call(&mut out) == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Output(out))
+    }
+
+    /// A DPAPI output buffer, zeroized and freed on drop.
+    struct Output(CRYPT_INTEGER_BLOB);
+
+    impl Output {
+        fn as_mut_slice(&mut self) -> &mut [u8] {
+            if self.0.pbData.is_null() {
+                return &mut [];
+            }
+            // Safety: DPAPI returned `cbData` bytes at `pbData`, owned by us.
+            unsafe { slice::from_raw_parts_mut(self.0.pbData, self.0.cbData as usize) }
+        }
+    }
+
+    impl Drop for Output {
+        fn drop(&mut self) {
+            zeroize(self.as_mut_slice());
+            // Safety: allocated by DPAPI with LocalAlloc, freed once.
+            unsafe { LocalFree(self.0.pbData.cast()) };
+        }
+    }
+
+    pub(super) fn protect(bytes: &[u8]) -> io::Result<Vec<u8>> {
+        let input = blob(bytes)?;
+        // Safety: `input` describes `bytes`; no description, entropy or prompt.
+        let mut out = take_output(|out| unsafe {
+            CryptProtectData(
+                &input,
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                out,
+            )
+        })?;
+        Ok(out.as_mut_slice().to_vec())
+    }
+
+    pub(super) fn unprotect(ciphertext: &[u8]) -> io::Result<SecureBuf> {
+        let input = blob(ciphertext)?;
+        // Safety: as in `protect`; the description is not requested.
+        let mut out = take_output(|out| unsafe {
+            CryptUnprotectData(
+                &input,
+                ptr::null_mut(),
+                ptr::null(),
+                ptr::null(),
+                ptr::null(),
+                CRYPTPROTECT_UI_FORBIDDEN,
+                out,
+            )
+        })?;
+        let plaintext = out.as_mut_slice();
+        let mut buf = SecureBuf::new_best_effort(plaintext.len())?;
+        buf.as_mut_slice().copy_from_slice(plaintext);
+        Ok(buf)
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+
+    use crate::SecureBuf;
+
+    pub(super) fn protect(_bytes: &[u8]) -> io::Result<Vec<u8>> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "DPAPI spilling is Windows-only",
+        ))
+    }
+
+    pub(super) fn unprotect(_ciphertext: &[u8]) -> io::Result<SecureBuf> {
+        unreachable!("nothing is spilled on this platform")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpillableSecret;
+
+    #[test]
+    fn spillable_secret_round_trips() {
+        let secret = SpillableSecret::from_slice(b"api-token").unwrap();
+        assert_eq!(secret.with_secret(|s| s.to_vec()).unwrap(), b"api-token");
+        #[cfg(not(windows))]
+        assert!(!secret.is_spilled());
+        assert!(!format!("{secret:?}").contains("api-token"));
+    }
+}
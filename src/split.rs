@@ -0,0 +1,130 @@
+//! Secrets stored as XOR shares in separate locked allocations.
+
+use std::fmt;
+use std::io;
+
+use crate::{SecureBuf, with_locked_secret};
+
+/// A secret split into `n` XOR shares, each in its own locked, page-aligned region.
+///
+/// Any `n - 1` shares are uniformly random and reveal nothing about the secret, so a
+/// partial memory disclosure or a dump that captures only some pages does not leak it.
+/// The plaintext is recombined only inside [`SplitSecret::with_secret`], into a
+/// temporary locked buffer that is zeroized as soon as the closure returns.
+#[cfg_attr(docsrs, doc(cfg(feature = "getrandom")))]
+pub struct SplitSecret {
+    shares: Vec<SecureBuf>,
+}
+
+impl SplitSecret {
+    /// Split `secret` into two shares.
+    ///
+    /// The source slice is not modified; zeroize it yourself if it held a secret.
+    pub fn new(secret: &[u8]) -> io::Result<Self> {
+        Self::with_shares(secret, 2)
+    }
+
+    /// Split `secret` into `n` shares.
+    ///
+    /// Returns `Err(InvalidInput)` if `n < 2`, or an error from the OS RNG or from
+    /// allocating and locking a share.
+    pub fn with_shares(secret: &[u8], n: usize) -> io::Result<Self> {
+        if n < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "SplitSecret needs at least two shares",
+            ));
+        }
+        let mut shares = Vec::with_capacity(n);
+        shares.push(SecureBuf::from_slice(secret)?);
+        for _ in 1..n {
+            let share = SecureBuf::random(secret.len())?;
+            xor_into(shares[0].as_mut_slice(), share.as_slice());
+            shares.push(share);
+        }
+        Ok(Self { shares })
+    }
+
+    /// Length of the secret in bytes.
+    pub fn len(&self) -> usize {
+        self.shares[0].len()
+    }
+
+    /// Whether the secret has zero length.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of shares the secret is split into.
+    pub fn share_count(&self) -> usize {
+        self.shares.len()
+    }
+
+    /// Recombine the shares into a temporary locked buffer and run `f` over it.
+    ///
+    /// The buffer is zeroized, unlocked, and freed when `f` returns, including on panic.
+    pub fn with_secret<R>(&self, f: impl FnOnce(&[u8]) -> R) -> io::Result<R> {
+        with_locked_secret(self.len(), |buf| {
+            for share in &self.shares {
+                xor_into(buf, share.as_slice());
+            }
+            f(buf)
+        })
+    }
+
+    /// Re-randomize every share without changing the secret.
+    ///
+    /// Shares captured by an earlier disclosure cannot be combined with the refreshed
+    /// ones. The secret is never recombined while refreshing.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let (first, rest) = self.shares.split_first_mut().expect("at least two shares");
+        for share in rest {
+            let mask = SecureBuf::random(share.len())?;
+            xor_into(first.as_mut_slice(), mask.as_slice());
+            xor_into(share.as_mut_slice(), mask.as_slice());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for SplitSecret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never print the contents.
+        f.debug_struct("SplitSecret")
+            .field("len", &self.len())
+            .field("shares", &self.shares.len())
+            .finish_non_exhaustive()
+    }
+}
+
+fn xor_into(dst: &mut [u8], src: &[u8]) {
+    for (d, s) in dst.iter_mut().zip(src) {
+        *d ^= s;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SplitSecret;
+
+    #[test]
+    fn recombines_only_inside_closure_and_survives_refresh() {
+        let secret = [0x5a; 32];
+        let mut split = SplitSecret::with_shares(&secret, 3).unwrap();
+        assert!(split.shares.iter().all(|s| s.as_slice() != secret));
+        assert!(split.with_secret(|s| s == secret).unwrap());
+
+        let before: Vec<Vec<u8>> = split.shares.iter().map(|s| s.as_slice().to_vec()).collect();
+        split.refresh().unwrap();
+        assert!(
+            split
+                .shares
+                .iter()
+                .zip(&before)
+                .all(|(s, b)| s.as_slice() != &b[..])
+        );
+        assert!(split.with_secret(|s| s == secret).unwrap());
+
+        assert!(SplitSecret::with_shares(&secret, 1).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! What the crate implements on the target it was built for.
+
+use std::fmt;
+
+/// A capability of the crate, as listed by [`platform_support`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Capability {
+    /// Locking ranges in RAM: [`mlock`](crate::mlock) and everything built on it.
+    Lock,
+    /// Locking the whole address space: [`mlockall`](crate::mlockall).
+    LockAll,
+    /// Querying and raising the lock limit: [`memlock_limit`](crate::memlock_limit), or
+    /// [`working_set_limits`](crate::working_set_limits) on Windows.
+    LockLimit,
+    /// Leaving single regions out of crash dumps:
+    /// [`exclude_from_dumps`](crate::exclude_from_dumps), or on OpenBSD the `MAP_CONCEAL`
+    /// mappings of [`secure_mmap`](crate::secure_mmap).
+    DumpExclusion,
+    /// Turning off core dumps for the whole process:
+    /// [`disable_core_dumps_for_process`](crate::disable_core_dumps_for_process), or
+    /// `disable_wer_dumps_for_process` on Windows.
+    CoreDumpLimit,
+    /// Zeroing regions in children after `fork`:
+    /// [`madvise_wipeonfork`](crate::madvise_wipeonfork).
+    WipeOnFork,
+    /// Refusing debugger attachment: [`set_dumpable`](crate::set_dumpable), or
+    /// [`disable_tracing`](crate::disable_tracing) on FreeBSD.
+    TracingProtection,
+    /// Checking which pages are in RAM: [`is_resident`](crate::is_resident).
+    ResidencyCheck,
+    /// Process-wide locked and swapped totals:
+    /// [`process_lock_stats`](crate::process_lock_stats); on Fuchsia only the committed
+    /// total, since nothing can be locked there.
+    LockStats,
+}
+
+impl Capability {
+    const ALL: [Self; 9] = [
+        Self::Lock,
+        Self::LockAll,
+        Self::LockLimit,
+        Self::DumpExclusion,
+        Self::CoreDumpLimit,
+        Self::WipeOnFork,
+        Self::TracingProtection,
+        Self::ResidencyCheck,
+        Self::LockStats,
+    ];
+
+    /// Whether the crate has an implementation for the build target.
+    fn implemented(self) -> bool {
+        match self {
+            Self::Lock => cfg!(any(all(unix, not(target_os = "fuchsia")), windows)),
+            Self::LockAll => cfg!(all(
+                unix,
+                not(any(target_os = "haiku", target_os = "fuchsia"))
+            )),
+            Self::LockLimit => cfg!(any(
+                windows,
+                target_os = "linux",
+                target_os = "android",
+                target_os = "emscripten",
+                target_vendor = "apple",
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_os = "hurd",
+                target_os = "nto",
+                target_os = "redox"
+            )),
+            Self::DumpExclusion => cfg!(any(
+                windows,
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "openbsd"
+            )),
+            Self::CoreDumpLimit => cfg!(any(
+                windows,
+                target_os = "macos",
+                target_os = "netbsd",
+                target_os = "dragonfly",
+                target_os = "illumos",
+                target_os = "solaris",
+                target_os = "aix"
+            )),
+            Self::WipeOnFork => cfg!(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "openbsd",
+                target_os = "netbsd"
+            )),
+            Self::TracingProtection => cfg!(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "freebsd"
+            )),
+            Self::ResidencyCheck => cfg!(any(
+                windows,
+                target_os = "linux",
+                target_os = "android",
+                target_vendor = "apple",
+                target_os = "freebsd",
+                target_os = "dragonfly",
+                target_os = "netbsd",
+                target_os = "openbsd",
+                target_os = "solaris",
+                target_os = "illumos",
+                target_os = "aix"
+            )),
+            Self::LockStats => cfg!(any(
+                target_os = "linux",
+                target_os = "android",
+                target_os = "macos",
+                target_os = "fuchsia"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Lock => "lock",
+            Self::LockAll => "lock all",
+            Self::LockLimit => "lock limit",
+            Self::DumpExclusion => "dump exclusion",
+            Self::CoreDumpLimit => "core dump limit",
+            Self::WipeOnFork => "wipe on fork",
+            Self::TracingProtection => "tracing protection",
+            Self::ResidencyCheck => "residency check",
+            Self::LockStats => "lock stats",
+        })
+    }
+}
+
+/// How well a [`Capability`] is supported on the build target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Support {
+    /// Implemented, and covered by the test suite on this target.
+    Implemented,
+    /// Implemented, but the test suite is not run on this target; expect rough edges.
+    Untested,
+    /// Not available: the calls return `Unsupported`.
+    Unsupported,
+}
+
+/// Support for every [`Capability`] on the build target, as returned by
+/// [`platform_support`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlatformSupport {
+    entries: [(Capability, Support); Capability::ALL.len()],
+}
+
+impl PlatformSupport {
+    /// Support for `capability`.
+    pub fn get(&self, capability: Capability) -> Support {
+        self.iter()
+            .find(|&(c, _)| c == capability)
+            .map_or(Support::Unsupported, |(_, support)| support)
+    }
+
+    /// Every capability with its support, in a fixed order.
+    pub fn iter(&self) -> impl Iterator<Item = (Capability, Support)> + '_ {
+        self.entries.iter().copied()
+    }
+}
+
+impl fmt::Display for PlatformSupport {
+    /// One line grouping the capabilities by support, for logs and bug reports.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let groups = [
+            ("implemented", Support::Implemented),
+            ("untested", Support::Untested),
+            ("unsupported", Support::Unsupported),
+        ];
+        for (i, (label, support)) in groups.into_iter().enumerate() {
+            let names: Vec<_> = self
+                .iter()
+                .filter(|&(_, s)| s == support)
+                .map(|(c, _)| c.to_string())
+                .collect();
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            if names.is_empty() {
+                write!(f, "{label}: none")?;
+            } else {
+                write!(f, "{label}: {}", names.join(", "))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// List, per capability, whether the crate implements it on the target it was built
+/// for.
+///
+/// This is decided at compile time from the target alone; whether the running kernel
+/// provides an interface is [`KernelCapabilities::detect`](crate::KernelCapabilities::detect).
+/// The test suite runs on Linux, macOS and Windows; implementations for other targets
+/// are reported as [`Support::Untested`].
+pub fn platform_support() -> PlatformSupport {
+    let tested = cfg!(any(target_os = "linux", target_os = "macos", windows));
+    PlatformSupport {
+        entries: Capability::ALL.map(|capability| {
+            let support = match capability.implemented() {
+                false => Support::Unsupported,
+                true if tested => Support::Implemented,
+                true => Support::Untested,
+            };
+            (capability, support)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capability, Support, platform_support};
+
+    #[test]
+    fn listing_matches_the_calls() {
+        let support = platform_support();
+        assert_eq!(support.iter().count(), Capability::ALL.len());
+        assert_eq!(
+            support.get(Capability::Lock) == Support::Unsupported,
+            cfg!(target_os = "fuchsia")
+        );
+        #[cfg(not(windows))]
+        assert_eq!(
+            support.get(Capability::CoreDumpLimit) == Support::Unsupported,
+            crate::disable_core_dumps_with_guard()
+                .is_err_and(|e| e.kind() == std::io::ErrorKind::Unsupported)
+        );
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            support.to_string(),
+            "implemented: lock, lock all, lock limit, dump exclusion, wipe on fork, \
+             tracing protection, residency check, lock stats; untested: none; \
+             unsupported: core dump limit"
+        );
+    }
+}
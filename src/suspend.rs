@@ -0,0 +1,1349 @@
+//! Sealing or wiping secrets before the system suspends or hibernates.
+//!
+//! `mlock` keeps pages out of swap, but a hibernation image contains all of RAM,
+//! locked pages included. Hooks registered with [`on_suspend`] run from
+//! [`prepare_for_suspend`], which is called automatically on platforms where
+//! [`watch_system_suspend`] is supported and can be called by hand elsewhere.
+
+use std::fmt;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+type Hook = Arc<dyn Fn() + Send + Sync>;
+
+static HOOKS: Mutex<Vec<(u64, Hook)>> = Mutex::new(Vec::new());
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Registration returned by [`on_suspend`]; the hook is removed when this is dropped.
+#[must_use = "the hook is unregistered when the SuspendHook is dropped"]
+pub struct SuspendHook {
+    id: u64,
+}
+
+impl Drop for SuspendHook {
+    fn drop(&mut self) {
+        let mut hooks = HOOKS.lock().unwrap_or_else(PoisonError::into_inner);
+        hooks.retain(|(id, _)| *id != self.id);
+    }
+}
+
+impl fmt::Debug for SuspendHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuspendHook").field("id", &self.id).finish()
+    }
+}
+
+/// Register `f` to run before the system suspends or hibernates.
+///
+/// Typical hooks seal (e.g. `IdleSealed::seal_on_suspend`) or wipe secrets the
+/// process can re-derive after resume. Hooks run on whichever thread delivers the
+/// notification, so keep them short and non-blocking.
+pub fn on_suspend(f: impl Fn() + Send + Sync + 'static) -> SuspendHook {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let mut hooks = HOOKS.lock().unwrap_or_else(PoisonError::into_inner);
+    hooks.push((id, Arc::new(f)));
+    SuspendHook { id }
+}
+
+/// Run every registered suspend hook now.
+///
+/// [`watch_system_suspend`] calls this for you; call it by hand where that is
+/// unsupported, or from a suspend handler the application already has (e.g. one for
+/// logind's `PrepareForSleep(true)` that holds its own delay inhibitor). A panicking hook
+/// is reported and does not stop the rest.
+pub fn prepare_for_suspend() {
+    // Snapshot so hooks can register or unregister without deadlocking.
+    let hooks: Vec<Hook> = HOOKS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .map(|(_, hook)| Arc::clone(hook))
+        .collect();
+    for hook in hooks {
+        if panic::catch_unwind(AssertUnwindSafe(|| hook())).is_err() {
+            diag!("suspend hook panicked");
+        }
+    }
+}
+
+/// Subscription to OS suspend notifications; unsubscribes on drop.
+#[must_use = "notifications stop when the SuspendWatch is dropped"]
+pub struct SuspendWatch {
+    _inner: imp::Watch,
+}
+
+impl fmt::Debug for SuspendWatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SuspendWatch").finish_non_exhaustive()
+    }
+}
+
+/// Subscribe to OS suspend notifications and run [`prepare_for_suspend`] on each one.
+///
+/// Platform:
+/// - Linux: logind's `PrepareForSleep` signal, read from the system bus
+///   (`DBUS_SYSTEM_BUS_ADDRESS`, else `/run/dbus/system_bus_socket`) by a small built-in
+///   D-Bus client on a background thread. A delay inhibitor makes logind wait for the
+///   hooks, up to its `InhibitDelayMaxSec`; without one (e.g. refused by polkit) they race
+///   the suspend. Returns `NotFound` when logind is not running.
+/// - macOS: `IORegisterForSystemPower` on a background thread running its own run loop;
+///   hooks run on `kIOMessageSystemWillSleep`, before the sleep is acknowledged.
+/// - Windows: `PowerRegisterSuspendResumeNotification`; hooks run on `PBT_APMSUSPEND`.
+/// - Elsewhere: returns `Unsupported`.
+pub fn watch_system_suspend() -> io::Result<SuspendWatch> {
+    Ok(SuspendWatch {
+        _inner: imp::Watch::new()?,
+    })
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    use windows_sys::Win32::Foundation::ERROR_SUCCESS;
+    use windows_sys::Win32::System::Power::{
+        DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, HPOWERNOTIFY, PowerRegisterSuspendResumeNotification,
+        PowerUnregisterSuspendResumeNotification,
+    };
+    use windows_sys::Win32::UI::WindowsAndMessaging::{DEVICE_NOTIFY_CALLBACK, PBT_APMSUSPEND};
+
+    unsafe extern "system" fn callback(
+        _context: *const c_void,
+        kind: u32,
+        _setting: *const c_void,
+    ) -> u32 {
+        if kind == PBT_APMSUSPEND {
+            super::prepare_for_suspend();
+        }
+        ERROR_SUCCESS
+    }
+
+    pub(super) struct Watch {
+        handle: HPOWERNOTIFY,
+        // Kept alive for the lifetime of the registration.
+        _params: Box<DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS>,
+    }
+
+    impl Watch {
+        pub(super) fn new() -> io::Result<Self> {
+            let mut params = Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+                Callback: Some(callback),
+                Context: ptr::null_mut(),
+            });
+            let mut handle: *mut c_void = ptr::null_mut();
+            let rc = unsafe {
+                PowerRegisterSuspendResumeNotification(
+                    DEVICE_NOTIFY_CALLBACK,
+                    (&mut *params as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS).cast(),
+                    &mut handle,
+                )
+            };
+            if rc != ERROR_SUCCESS {
+                return Err(io::Error::from_raw_os_error(rc as i32));
+            }
+            Ok(Self {
+                handle: handle as HPOWERNOTIFY,
+                _params: params,
+            })
+        }
+    }
+
+    impl Drop for Watch {
+        fn drop(&mut self) {
+            let rc = unsafe { PowerUnregisterSuspendResumeNotification(self.handle) };
+            if rc != ERROR_SUCCESS {
+                // Avoid panicking in Drop; emit a diagnostic.
+                diag!(
+                    "failed to unregister suspend notification: {}",
+                    io::Error::from_raw_os_error(rc as i32)
+                );
+            }
+        }
+    }
+}
+
+/// Just enough of the D-Bus wire protocol to follow logind on the system bus: EXTERNAL
+/// authentication, method calls and signals whose arguments are strings or 32-bit
+/// integers, and descriptors passed with `SCM_RIGHTS`. Messages go out in native byte
+/// order; either order is accepted.
+#[cfg(target_os = "linux")]
+mod dbus {
+    use std::collections::VecDeque;
+    use std::ffi::OsStr;
+    use std::io::{self, Read, Write};
+    use std::mem;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::net::{SocketAddr, UnixStream};
+
+    const METHOD_CALL: u8 = 1;
+    #[cfg(test)]
+    const METHOD_RETURN: u8 = 2;
+    pub(super) const ERROR: u8 = 3;
+    pub(super) const SIGNAL: u8 = 4;
+
+    const PATH: u8 = 1;
+    const INTERFACE: u8 = 2;
+    const MEMBER: u8 = 3;
+    const ERROR_NAME: u8 = 4;
+    const REPLY_SERIAL: u8 = 5;
+    const DESTINATION: u8 = 6;
+    const SENDER: u8 = 7;
+    const SIGNATURE: u8 = 8;
+    const UNIX_FDS: u8 = 9;
+
+    /// The largest message the specification allows.
+    const MAX_MESSAGE: usize = 1 << 27;
+
+    pub(super) const BUS: Object<'static> = Object {
+        destination: "org.freedesktop.DBus",
+        path: "/org/freedesktop/DBus",
+        interface: "org.freedesktop.DBus",
+    };
+
+    fn invalid(what: &str) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, format!("D-Bus: {what}"))
+    }
+
+    /// Where a call is sent, or a signal is emitted from.
+    pub(super) struct Object<'a> {
+        pub(super) destination: &'a str,
+        pub(super) path: &'a str,
+        pub(super) interface: &'a str,
+    }
+
+    /// A message argument: `s`/`o`/`g` are strings, `b`/`u`/`h` 32-bit integers (`h` is
+    /// an index into [`Message::fds`]).
+    #[derive(Debug, PartialEq)]
+    pub(super) enum Arg {
+        Str(String),
+        U32(u32),
+    }
+
+    /// A received message; header fields that were absent are empty.
+    #[derive(Default)]
+    pub(super) struct Message {
+        pub(super) kind: u8,
+        #[cfg_attr(not(test), allow(dead_code))] // Only the tests send replies.
+        pub(super) serial: u32,
+        pub(super) reply_serial: Option<u32>,
+        pub(super) path: String,
+        pub(super) interface: String,
+        pub(super) member: String,
+        pub(super) error_name: String,
+        pub(super) sender: String,
+        pub(super) signature: String,
+        pub(super) fds: Vec<OwnedFd>,
+        body: Vec<u8>,
+        big_endian: bool,
+    }
+
+    impl Message {
+        pub(super) fn args(&self) -> io::Result<Vec<Arg>> {
+            let mut body = Reader {
+                data: &self.body,
+                pos: 0,
+                big_endian: self.big_endian,
+            };
+            self.signature
+                .bytes()
+                .map(|kind| body.value(kind))
+                .collect()
+        }
+
+        /// The error an `ERROR` reply stands for.
+        pub(super) fn error(&self) -> io::Error {
+            let kind = match self.error_name.rsplit('.').next() {
+                Some("NameHasNoOwner" | "ServiceUnknown") => io::ErrorKind::NotFound,
+                Some("AccessDenied") => io::ErrorKind::PermissionDenied,
+                _ => io::ErrorKind::Other,
+            };
+            match self.args().as_deref() {
+                Ok([Arg::Str(text), ..]) => {
+                    io::Error::new(kind, format!("{}: {text}", self.error_name))
+                }
+                _ => io::Error::new(kind, self.error_name.clone()),
+            }
+        }
+    }
+
+    struct Reader<'a> {
+        data: &'a [u8],
+        pos: usize,
+        big_endian: bool,
+    }
+
+    impl<'a> Reader<'a> {
+        fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+            let end = self
+                .pos
+                .checked_add(len)
+                .filter(|&end| end <= self.data.len())
+                .ok_or_else(|| invalid("truncated message"))?;
+            let bytes = &self.data[self.pos..end];
+            self.pos = end;
+            Ok(bytes)
+        }
+
+        fn align(&mut self, to: usize) -> io::Result<()> {
+            let pad = self.pos.next_multiple_of(to) - self.pos;
+            self.bytes(pad).map(drop)
+        }
+
+        fn u32(&mut self) -> io::Result<u32> {
+            self.align(4)?;
+            let bytes = self.bytes(4)?.try_into().unwrap();
+            Ok(match self.big_endian {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            })
+        }
+
+        /// A string of `len` bytes followed by its NUL terminator.
+        fn text(&mut self, len: usize) -> io::Result<String> {
+            let text = self.bytes(len)?;
+            self.bytes(1)?;
+            String::from_utf8(text.to_vec()).map_err(|_| invalid("string is not UTF-8"))
+        }
+
+        fn value(&mut self, kind: u8) -> io::Result<Arg> {
+            Ok(match kind {
+                b's' | b'o' => {
+                    let len = self.u32()? as usize;
+                    Arg::Str(self.text(len)?)
+                }
+                b'g' => {
+                    let len = self.bytes(1)?[0] as usize;
+                    Arg::Str(self.text(len)?)
+                }
+                b'b' | b'u' | b'h' => Arg::U32(self.u32()?),
+                _ => return Err(invalid("unsupported argument type")),
+            })
+        }
+
+        /// Step over one value of the first complete type in `sig`, whatever the type;
+        /// returns the rest of `sig`. `depth` bounds variants nested inside variants.
+        fn skip<'s>(&mut self, sig: &'s [u8], depth: u32) -> io::Result<&'s [u8]> {
+            let (&kind, rest) = sig
+                .split_first()
+                .ok_or_else(|| invalid("truncated signature"))?;
+            match kind {
+                b'y' => self.bytes(1).map(drop)?,
+                b'n' | b'q' => self.align(2).and_then(|()| self.bytes(2)).map(drop)?,
+                b'b' | b'i' | b'u' | b'h' => self.u32().map(drop)?,
+                b'x' | b't' | b'd' => self.align(8).and_then(|()| self.bytes(8)).map(drop)?,
+                b's' | b'o' | b'g' => self.value(kind).map(drop)?,
+                b'v' => {
+                    if depth == 64 {
+                        return Err(invalid("variants nested too deeply"));
+                    }
+                    let Arg::Str(inner) = self.value(b'g')? else {
+                        unreachable!()
+                    };
+                    if !self.skip(inner.as_bytes(), depth + 1)?.is_empty() {
+                        return Err(invalid("variant is not a single complete type"));
+                    }
+                }
+                b'a' => {
+                    // The length covers the elements, which can be stepped over whole.
+                    let len = self.u32()? as usize;
+                    let element = rest.first().ok_or_else(|| invalid("truncated signature"))?;
+                    self.align(alignment(*element))?;
+                    self.bytes(len)?;
+                    return after_type(rest);
+                }
+                b'(' | b'{' => {
+                    self.align(8)?;
+                    let close = if kind == b'(' { b')' } else { b'}' };
+                    let mut members = rest;
+                    while members.first() != Some(&close) {
+                        members = self.skip(members, depth)?;
+                    }
+                    return Ok(&members[1..]);
+                }
+                _ => return Err(invalid("unknown type in signature")),
+            }
+            Ok(rest)
+        }
+    }
+
+    /// The alignment of values whose signature starts with `kind`.
+    fn alignment(kind: u8) -> usize {
+        match kind {
+            b'n' | b'q' => 2,
+            b'b' | b'i' | b'u' | b'h' | b's' | b'o' | b'a' => 4,
+            b'x' | b't' | b'd' | b'(' | b'{' => 8,
+            _ => 1,
+        }
+    }
+
+    /// The rest of `sig` after its first complete type.
+    fn after_type(sig: &[u8]) -> io::Result<&[u8]> {
+        let (&kind, mut rest) = sig
+            .split_first()
+            .ok_or_else(|| invalid("truncated signature"))?;
+        match kind {
+            b'a' => after_type(rest),
+            b'(' | b'{' => {
+                let close = if kind == b'(' { b')' } else { b'}' };
+                while rest.first() != Some(&close) {
+                    rest = after_type(rest)?;
+                }
+                Ok(&rest[1..])
+            }
+            _ => Ok(rest),
+        }
+    }
+
+    #[derive(Default)]
+    struct Writer(Vec<u8>);
+
+    impl Writer {
+        fn align(&mut self, to: usize) {
+            self.0.resize(self.0.len().next_multiple_of(to), 0);
+        }
+
+        fn u32(&mut self, value: u32) {
+            self.align(4);
+            self.0.extend_from_slice(&value.to_ne_bytes());
+        }
+
+        fn string(&mut self, value: &str) {
+            self.u32(value.len() as u32);
+            self.0.extend_from_slice(value.as_bytes());
+            self.0.push(0);
+        }
+
+        fn signature(&mut self, value: &str) {
+            self.0.push(value.len() as u8);
+            self.0.extend_from_slice(value.as_bytes());
+            self.0.push(0);
+        }
+
+        /// A header field: a `(yv)` struct whose variant holds `value` of type `kind`.
+        fn field(&mut self, code: u8, kind: &str, value: &str) {
+            self.align(8);
+            self.0.push(code);
+            self.signature(kind);
+            match kind {
+                "g" => self.signature(value),
+                _ => self.string(value),
+            }
+        }
+
+        #[cfg(test)]
+        fn u32_field(&mut self, code: u8, value: u32) {
+            self.align(8);
+            self.0.push(code);
+            self.signature("u");
+            self.u32(value);
+        }
+    }
+
+    /// An authenticated connection to a message bus.
+    pub(super) struct Bus {
+        stream: UnixStream,
+        serial: u32,
+        buf: Vec<u8>,
+        fds: VecDeque<OwnedFd>,
+    }
+
+    impl Bus {
+        /// Connect to the first `unix:` transport in `address` and say `Hello`.
+        pub(super) fn connect(address: &str) -> io::Result<Self> {
+            let mut stream = open(address)?;
+            authenticate(&mut stream)?;
+            let mut bus = Self::over(stream);
+            bus.call(&BUS, "Hello", "", &[])?;
+            Ok(bus)
+        }
+
+        /// Exchange messages over `stream`, which must be past authentication.
+        pub(super) fn over(stream: UnixStream) -> Self {
+            Self {
+                stream,
+                serial: 0,
+                buf: Vec::new(),
+                fds: VecDeque::new(),
+            }
+        }
+
+        pub(super) fn try_clone_stream(&self) -> io::Result<UnixStream> {
+            self.stream.try_clone()
+        }
+
+        /// Encode a message whose header fields, past the signature, are written by
+        /// `fields`; returns its serial and bytes.
+        fn encode(
+            &mut self,
+            kind: u8,
+            fields: impl FnOnce(&mut Writer),
+            signature: &str,
+            args: &[Arg],
+        ) -> (u32, Vec<u8>) {
+            self.serial = self.serial.checked_add(1).unwrap_or(1);
+            let mut body = Writer::default();
+            for arg in args {
+                match arg {
+                    Arg::Str(value) => body.string(value),
+                    Arg::U32(value) => body.u32(*value),
+                }
+            }
+            let mut msg = Writer::default();
+            let order = if cfg!(target_endian = "big") {
+                b'B'
+            } else {
+                b'l'
+            };
+            msg.0.extend_from_slice(&[order, kind, 0, 1]);
+            msg.u32(body.0.len() as u32);
+            msg.u32(self.serial);
+            // Length of the header field array, filled in below.
+            msg.u32(0);
+            if !signature.is_empty() {
+                msg.field(SIGNATURE, "g", signature);
+            }
+            fields(&mut msg);
+            let len = (msg.0.len() - 16) as u32;
+            msg.0[12..16].copy_from_slice(&len.to_ne_bytes());
+            msg.align(8);
+            msg.0.extend_from_slice(&body.0);
+            (self.serial, msg.0)
+        }
+
+        fn send(
+            &mut self,
+            kind: u8,
+            to: &Object<'_>,
+            member: &str,
+            signature: &str,
+            args: &[Arg],
+        ) -> io::Result<u32> {
+            let fields = |msg: &mut Writer| {
+                msg.field(PATH, "o", to.path);
+                msg.field(INTERFACE, "s", to.interface);
+                msg.field(MEMBER, "s", member);
+                if kind != SIGNAL {
+                    msg.field(DESTINATION, "s", to.destination);
+                }
+            };
+            let (serial, msg) = self.encode(kind, fields, signature, args);
+            self.stream.write_all(&msg)?;
+            Ok(serial)
+        }
+
+        /// Send a method call without waiting; returns the serial its reply will carry.
+        pub(super) fn request(
+            &mut self,
+            to: &Object<'_>,
+            member: &str,
+            signature: &str,
+            args: &[Arg],
+        ) -> io::Result<u32> {
+            self.send(METHOD_CALL, to, member, signature, args)
+        }
+
+        /// Call a method and wait for its reply, dropping whatever arrives first.
+        pub(super) fn call(
+            &mut self,
+            to: &Object<'_>,
+            member: &str,
+            signature: &str,
+            args: &[Arg],
+        ) -> io::Result<Message> {
+            let serial = self.request(to, member, signature, args)?;
+            loop {
+                let msg = self.read()?.ok_or(io::ErrorKind::UnexpectedEof)?;
+                if msg.reply_serial != Some(serial) {
+                    continue;
+                }
+                if msg.kind == ERROR {
+                    return Err(msg.error());
+                }
+                return Ok(msg);
+            }
+        }
+
+        #[cfg(test)]
+        pub(super) fn signal(
+            &mut self,
+            from: &Object<'_>,
+            member: &str,
+            signature: &str,
+            args: &[Arg],
+        ) -> io::Result<()> {
+            self.send(SIGNAL, from, member, signature, args).map(drop)
+        }
+
+        /// Reply to `call` with a single descriptor, as logind answers `Inhibit`.
+        #[cfg(test)]
+        pub(super) fn reply_with_fd(
+            &mut self,
+            call: &Message,
+            fd: std::os::fd::BorrowedFd<'_>,
+        ) -> io::Result<()> {
+            let fields = |msg: &mut Writer| {
+                msg.field(DESTINATION, "s", &call.sender);
+                msg.u32_field(REPLY_SERIAL, call.serial);
+                msg.u32_field(UNIX_FDS, 1);
+            };
+            let (_, bytes) = self.encode(METHOD_RETURN, fields, "h", &[Arg::U32(0)]);
+            let mut iov = libc::iovec {
+                iov_base: bytes.as_ptr().cast_mut().cast(),
+                iov_len: bytes.len(),
+            };
+            let mut control = [0u64; 4];
+            // Safety: as in `fill`; the control buffer has room for one descriptor.
+            let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = mem::size_of_val(&control) as _;
+            // Safety: fills in the first control message of the buffer set up above, then
+            // sends; the message is small enough to go out whole.
+            let rc = unsafe {
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(size_of::<libc::c_int>() as u32) as _;
+                libc::CMSG_DATA(cmsg)
+                    .cast::<libc::c_int>()
+                    .write_unaligned(fd.as_raw_fd());
+                msg.msg_controllen = libc::CMSG_SPACE(size_of::<libc::c_int>() as u32) as _;
+                libc::sendmsg(self.stream.as_raw_fd(), &msg, 0)
+            };
+            if rc < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        }
+
+        /// The next message, or `None` once the connection is closed.
+        pub(super) fn read(&mut self) -> io::Result<Option<Message>> {
+            loop {
+                if let Some(msg) = self.parse()? {
+                    return Ok(Some(msg));
+                }
+                if self.fill()? == 0 {
+                    return Ok(None);
+                }
+            }
+        }
+
+        /// Receive more bytes, keeping any descriptors that come with them.
+        fn fill(&mut self) -> io::Result<usize> {
+            let mut chunk = [0u8; 4096];
+            // Room for several descriptors; logind passes one per reply.
+            let mut control = [0u64; 16];
+            let mut iov = libc::iovec {
+                iov_base: chunk.as_mut_ptr().cast(),
+                iov_len: chunk.len(),
+            };
+            // Safety: all-zero is a valid msghdr; the pointers set below outlive the call.
+            let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+            msg.msg_iov = &mut iov;
+            msg.msg_iovlen = 1;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = mem::size_of_val(&control) as _;
+            let len = loop {
+                // Safety: `msg` describes the two buffers above.
+                let rc = unsafe {
+                    libc::recvmsg(self.stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC)
+                };
+                if rc >= 0 {
+                    break rc as usize;
+                }
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(err);
+                }
+            };
+            // Safety: the kernel filled in `msg_controllen` bytes of control messages, and
+            // every descriptor in an SCM_RIGHTS message is newly installed and ours to own.
+            unsafe {
+                let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+                while !cmsg.is_null() {
+                    if (*cmsg).cmsg_level == libc::SOL_SOCKET
+                        && (*cmsg).cmsg_type == libc::SCM_RIGHTS
+                    {
+                        let data = libc::CMSG_DATA(cmsg).cast::<libc::c_int>();
+                        let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                            / size_of::<libc::c_int>();
+                        for i in 0..count {
+                            self.fds
+                                .push_back(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                        }
+                    }
+                    cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+                }
+            }
+            self.buf.extend_from_slice(&chunk[..len]);
+            Ok(len)
+        }
+
+        /// Split the first complete message off the buffer.
+        fn parse(&mut self) -> io::Result<Option<Message>> {
+            if self.buf.len() < 16 {
+                return Ok(None);
+            }
+            let big_endian = match self.buf[0] {
+                b'l' => false,
+                b'B' => true,
+                _ => return Err(invalid("unknown byte order")),
+            };
+            let mut fixed = Reader {
+                data: &self.buf[..16],
+                pos: 4,
+                big_endian,
+            };
+            let body_len = fixed.u32()? as usize;
+            let serial = fixed.u32()?;
+            let fields_len = fixed.u32()? as usize;
+            if body_len > MAX_MESSAGE || fields_len > MAX_MESSAGE {
+                return Err(invalid("message too large"));
+            }
+            let body_start = (16 + fields_len).next_multiple_of(8);
+            if self.buf.len() < body_start + body_len {
+                return Ok(None);
+            }
+            let data: Vec<u8> = self.buf.drain(..body_start + body_len).collect();
+            let mut msg = Message {
+                kind: data[1],
+                serial,
+                big_endian,
+                ..Message::default()
+            };
+            let mut unix_fds = 0;
+            let mut fields = Reader {
+                data: &data[..16 + fields_len],
+                pos: 16,
+                big_endian,
+            };
+            while fields.pos < fields.data.len() {
+                fields.align(8)?;
+                let code = fields.bytes(1)?[0];
+                let Arg::Str(kind) = fields.value(b'g')? else {
+                    unreachable!()
+                };
+                let value = match kind.as_bytes() {
+                    &[kind @ (b's' | b'o' | b'g' | b'b' | b'u' | b'h')] => fields.value(kind)?,
+                    // The specification has readers skip fields they do not know.
+                    other => {
+                        if !fields.skip(other, 0)?.is_empty() {
+                            return Err(invalid("header field is not a single complete type"));
+                        }
+                        continue;
+                    }
+                };
+                match (code, value) {
+                    (PATH, Arg::Str(value)) => msg.path = value,
+                    (INTERFACE, Arg::Str(value)) => msg.interface = value,
+                    (MEMBER, Arg::Str(value)) => msg.member = value,
+                    (ERROR_NAME, Arg::Str(value)) => msg.error_name = value,
+                    (SENDER, Arg::Str(value)) => msg.sender = value,
+                    (SIGNATURE, Arg::Str(value)) => msg.signature = value,
+                    (REPLY_SERIAL, Arg::U32(value)) => msg.reply_serial = Some(value),
+                    (UNIX_FDS, Arg::U32(value)) => unix_fds = value as usize,
+                    _ => {}
+                }
+            }
+            if unix_fds > self.fds.len() {
+                return Err(invalid("message refers to descriptors that did not arrive"));
+            }
+            msg.fds = self.fds.drain(..unix_fds).collect();
+            msg.body = data[body_start..].to_vec();
+            Ok(Some(msg))
+        }
+    }
+
+    fn open(address: &str) -> io::Result<UnixStream> {
+        for params in address
+            .split(';')
+            .filter_map(|entry| entry.strip_prefix("unix:"))
+        {
+            for param in params.split(',') {
+                if let Some(path) = param.strip_prefix("path=") {
+                    return UnixStream::connect(OsStr::from_bytes(&unescape(path)?));
+                }
+                if let Some(name) = param.strip_prefix("abstract=") {
+                    return UnixStream::connect_addr(&SocketAddr::from_abstract_name(unescape(
+                        name,
+                    )?)?);
+                }
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("D-Bus: no unix socket transport in {address:?}"),
+        ))
+    }
+
+    /// Undo the `%xx` escaping of address values.
+    fn unescape(value: &str) -> io::Result<Vec<u8>> {
+        let bytes = value.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] != b'%' {
+                out.push(bytes[i]);
+                i += 1;
+                continue;
+            }
+            let byte = bytes
+                .get(i + 1..i + 3)
+                .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+                .ok_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "D-Bus: bad escape in address")
+                })?;
+            out.push(byte);
+            i += 3;
+        }
+        Ok(out)
+    }
+
+    /// SASL EXTERNAL with our uid, asking for descriptor passing (needed for logind's
+    /// inhibitor) but carrying on without it.
+    fn authenticate(stream: &mut UnixStream) -> io::Result<()> {
+        // Safety: getuid cannot fail.
+        let uid = unsafe { libc::getuid() }.to_string();
+        let hex: String = uid.bytes().map(|b| format!("{b:02x}")).collect();
+        stream.write_all(format!("\0AUTH EXTERNAL {hex}\r\n").as_bytes())?;
+        if !read_line(stream)?.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "D-Bus: the bus refused EXTERNAL authentication",
+            ));
+        }
+        stream.write_all(b"NEGOTIATE_UNIX_FD\r\n")?;
+        read_line(stream)?;
+        stream.write_all(b"BEGIN\r\n")
+    }
+
+    /// One line of the authentication exchange. Read a byte at a time: the server sends
+    /// nothing past it until we reply, but this keeps the stream clean for messages.
+    fn read_line(stream: &mut UnixStream) -> io::Result<String> {
+        let mut line = Vec::new();
+        while !line.ends_with(b"\r\n") {
+            let mut byte = 0;
+            if stream.read(std::slice::from_mut(&mut byte))? == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            line.push(byte);
+            if line.len() > 512 {
+                return Err(invalid("authentication line too long"));
+            }
+        }
+        line.truncate(line.len() - 2);
+        Ok(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::io;
+    use std::net::Shutdown;
+    use std::os::fd::OwnedFd;
+    use std::os::unix::net::UnixStream;
+    use std::thread::{self, JoinHandle};
+
+    use super::dbus::{self, Arg, Bus, Object};
+
+    /// Used when `DBUS_SYSTEM_BUS_ADDRESS` is unset, as by libdbus and sd-bus.
+    const SYSTEM_BUS: &str = "unix:path=/run/dbus/system_bus_socket";
+
+    pub(super) const LOGIND: Object<'static> = Object {
+        destination: "org.freedesktop.login1",
+        path: "/org/freedesktop/login1",
+        interface: "org.freedesktop.login1.Manager",
+    };
+
+    pub(super) struct Watch {
+        stream: UnixStream,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Watch {
+        pub(super) fn new() -> io::Result<Self> {
+            let address = std::env::var("DBUS_SYSTEM_BUS_ADDRESS");
+            Self::connect(address.as_deref().unwrap_or(SYSTEM_BUS))
+        }
+
+        pub(super) fn connect(address: &str) -> io::Result<Self> {
+            let mut bus = Bus::connect(address)?;
+            let rule = format!(
+                "type='signal',sender='{}',path='{}',interface='{}',member='PrepareForSleep'",
+                LOGIND.destination, LOGIND.path, LOGIND.interface
+            );
+            bus.call(&dbus::BUS, "AddMatch", "s", &[Arg::Str(rule)])?;
+            // Signals are only believed from the connection that owns logind's name, which
+            // also tells us whether logind is running at all.
+            let name = [Arg::Str(LOGIND.destination.into())];
+            let owner = match bus
+                .call(&dbus::BUS, "GetNameOwner", "s", &name)?
+                .args()?
+                .pop()
+            {
+                Some(Arg::Str(owner)) => owner,
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "D-Bus: GetNameOwner did not return a name",
+                    ));
+                }
+            };
+            let stream = bus.try_clone_stream()?;
+            let thread = thread::Builder::new()
+                .name("os-memlock-suspend".into())
+                .spawn(move || watch(bus, &owner))?;
+            Ok(Self {
+                stream,
+                thread: Some(thread),
+            })
+        }
+    }
+
+    impl Drop for Watch {
+        fn drop(&mut self) {
+            // Ends the watcher thread's blocking read.
+            let _ = self.stream.shutdown(Shutdown::Both);
+            // A hook that drops the watch runs on the watcher thread itself.
+            if let Some(thread) = self.thread.take()
+                && thread.thread().id() != thread::current().id()
+            {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    /// Ask for a delay inhibitor; returns the serial of the pending reply.
+    fn inhibit(bus: &mut Bus) -> Option<u32> {
+        let args = [
+            "sleep",
+            "os-memlock",
+            "Sealing secrets before sleep",
+            "delay",
+        ]
+        .map(|arg| Arg::Str(arg.into()));
+        bus.request(&LOGIND, "Inhibit", "ssss", &args)
+            .inspect_err(|err| diag!("failed to request a sleep inhibitor from logind: {err}"))
+            .ok()
+    }
+
+    /// Run the hooks on `PrepareForSleep(true)`. A delay inhibitor makes logind wait for
+    /// them (up to its `InhibitDelayMaxSec`); it is released once they return and taken
+    /// again on `PrepareForSleep(false)`, after resume.
+    fn watch(mut bus: Bus, owner: &str) {
+        let mut inhibitor: Option<OwnedFd> = None;
+        let mut pending = inhibit(&mut bus);
+        loop {
+            let msg = match bus.read() {
+                Ok(Some(msg)) => msg,
+                // Shut down by `Watch::drop`, or the bus went away.
+                Ok(None) => return,
+                Err(err) => {
+                    diag!("stopped watching for system suspend: {err}");
+                    return;
+                }
+            };
+            if msg.kind == dbus::SIGNAL
+                && msg.sender == owner
+                && msg.path == LOGIND.path
+                && msg.interface == LOGIND.interface
+                && msg.member == "PrepareForSleep"
+            {
+                match msg.args().as_deref() {
+                    Ok([Arg::U32(1)]) => {
+                        super::prepare_for_suspend();
+                        inhibitor = None;
+                    }
+                    Ok([Arg::U32(0)]) if inhibitor.is_none() && pending.is_none() => {
+                        pending = inhibit(&mut bus);
+                    }
+                    _ => {}
+                }
+            } else if pending.is_some() && msg.reply_serial == pending {
+                pending = None;
+                if msg.kind == dbus::ERROR {
+                    diag!("logind refused a sleep inhibitor: {}", msg.error());
+                    continue;
+                }
+                if let Ok([Arg::U32(index)]) = msg.args().as_deref() {
+                    let index = *index as usize;
+                    inhibitor = msg.fds.into_iter().nth(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use std::ffi::c_void;
+    use std::io;
+    use std::ptr;
+    use std::sync::mpsc;
+    use std::thread::{self, JoinHandle};
+
+    /// `io_connect_t` / `io_object_t`.
+    type IoPort = libc::mach_port_t;
+    type NotificationPort = *mut c_void;
+    type RunLoop = *mut c_void;
+    type InterestCallback = unsafe extern "C" fn(*mut c_void, IoPort, u32, *mut c_void);
+
+    /// `kIOMessageCanSystemSleep`: idle sleep may be vetoed; we never do.
+    const CAN_SYSTEM_SLEEP: u32 = 0xe000_0270;
+    /// `kIOMessageSystemWillSleep`: sleep goes ahead once acknowledged, or after 30 s.
+    const SYSTEM_WILL_SLEEP: u32 = 0xe000_0280;
+
+    #[link(name = "IOKit", kind = "framework")]
+    unsafe extern "C" {
+        fn IORegisterForSystemPower(
+            refcon: *mut c_void,
+            port: *mut NotificationPort,
+            callback: InterestCallback,
+            notifier: *mut IoPort,
+        ) -> IoPort;
+        fn IODeregisterForSystemPower(notifier: *mut IoPort) -> libc::kern_return_t;
+        fn IOAllowPowerChange(root: IoPort, notification: isize) -> libc::kern_return_t;
+        fn IOServiceClose(connect: IoPort) -> libc::kern_return_t;
+        fn IONotificationPortGetRunLoopSource(port: NotificationPort) -> *mut c_void;
+        fn IONotificationPortDestroy(port: NotificationPort);
+    }
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    unsafe extern "C" {
+        static kCFRunLoopDefaultMode: *const c_void;
+        fn CFRunLoopGetCurrent() -> RunLoop;
+        fn CFRunLoopAddSource(run_loop: RunLoop, source: *mut c_void, mode: *const c_void);
+        fn CFRunLoopRun();
+        fn CFRunLoopStop(run_loop: RunLoop);
+        fn CFRetain(cf: *const c_void) -> *const c_void;
+        fn CFRelease(cf: *const c_void);
+    }
+
+    /// `refcon` points at the root power port the notification must be acknowledged on.
+    unsafe extern "C" fn callback(
+        refcon: *mut c_void,
+        _: IoPort,
+        kind: u32,
+        argument: *mut c_void,
+    ) {
+        match kind {
+            SYSTEM_WILL_SLEEP => super::prepare_for_suspend(),
+            CAN_SYSTEM_SLEEP => {}
+            _ => return,
+        }
+        // Safety: `run` stored the port before starting the run loop that delivers this
+        // call, and frees it only after the loop has stopped; `argument` identifies the
+        // notification being acknowledged.
+        unsafe { IOAllowPowerChange(*refcon.cast::<IoPort>(), argument as isize) };
+    }
+
+    pub(super) struct Watch {
+        /// The watcher thread's `CFRunLoopRef`, retained.
+        run_loop: usize,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl Watch {
+        pub(super) fn new() -> io::Result<Self> {
+            let (ready, registered) = mpsc::channel();
+            let thread = thread::Builder::new()
+                .name("os-memlock-suspend".into())
+                .spawn(move || run(&ready))?;
+            match registered.recv() {
+                Ok(Ok(run_loop)) => Ok(Self {
+                    run_loop,
+                    thread: Some(thread),
+                }),
+                Ok(Err(err)) => {
+                    let _ = thread.join();
+                    Err(err)
+                }
+                Err(_) => Err(io::Error::other("suspend watch thread exited")),
+            }
+        }
+    }
+
+    impl Drop for Watch {
+        fn drop(&mut self) {
+            // Safety: the run loop is retained until below. Stopping it from another thread
+            // is allowed, and a stop that lands before `CFRunLoopRun` is kept until it runs.
+            unsafe { CFRunLoopStop(self.run_loop as RunLoop) };
+            // A hook that drops the watch runs on the watcher thread itself.
+            if let Some(thread) = self.thread.take()
+                && thread.thread().id() != thread::current().id()
+            {
+                let _ = thread.join();
+            }
+            // Safety: balances the CFRetain in `run`.
+            unsafe { CFRelease(self.run_loop as *const c_void) };
+        }
+    }
+
+    /// Register for system power notifications and serve them from this thread's run loop
+    /// until `Watch::drop` stops it.
+    fn run(ready: &mpsc::Sender<io::Result<usize>>) {
+        let root = Box::into_raw(Box::new(0 as IoPort));
+        let mut port: NotificationPort = ptr::null_mut();
+        let mut notifier: IoPort = 0;
+        // Safety: `root` stays allocated until after deregistration; the out-pointers are
+        // locals.
+        unsafe {
+            *root = IORegisterForSystemPower(root.cast(), &mut port, callback, &mut notifier)
+        };
+        // Safety: `root` was allocated above.
+        if unsafe { *root } == 0 {
+            // Safety: never registered, so nothing else refers to it.
+            drop(unsafe { Box::from_raw(root) });
+            let _ = ready.send(Err(io::Error::other("IORegisterForSystemPower failed")));
+            return;
+        }
+        // Safety: adds the notification port's source to this thread's run loop, and
+        // retains that run loop for `Watch::drop`.
+        let run_loop = unsafe {
+            let run_loop = CFRunLoopGetCurrent();
+            CFRunLoopAddSource(
+                run_loop,
+                IONotificationPortGetRunLoopSource(port),
+                kCFRunLoopDefaultMode,
+            );
+            CFRetain(run_loop) as usize
+        };
+        let _ = ready.send(Ok(run_loop));
+        // Safety: returns once `Watch::drop` calls CFRunLoopStop.
+        unsafe { CFRunLoopRun() };
+        // Safety: the run loop has stopped, so no callback can still be using `root`;
+        // releases what IORegisterForSystemPower handed out, in the documented order.
+        unsafe {
+            IODeregisterForSystemPower(&mut notifier);
+            IOServiceClose(*root);
+            IONotificationPortDestroy(port);
+            drop(Box::from_raw(root));
+        }
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux", target_os = "macos")))]
+mod imp {
+    use std::io;
+
+    pub(super) struct Watch;
+
+    impl Watch {
+        pub(super) fn new() -> io::Result<Self> {
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "watch_system_suspend unsupported on this platform; call prepare_for_suspend",
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{on_suspend, prepare_for_suspend};
+
+    #[test]
+    fn hooks_run_until_unregistered() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&calls);
+        let hook = on_suspend(move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+        let _panicky = on_suspend(|| panic!("hook failure"));
+        prepare_for_suspend();
+        let seen = calls.load(Ordering::SeqCst);
+        assert!(seen >= 1);
+        drop(hook);
+        prepare_for_suspend();
+        assert_eq!(calls.load(Ordering::SeqCst), seen);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn logind_prepare_for_sleep_runs_hooks() {
+        use std::io::{BufRead, BufReader};
+        use std::os::fd::{AsFd, AsRawFd, FromRawFd, OwnedFd};
+        use std::process::{Child, Command, Stdio};
+        use std::sync::mpsc;
+        use std::time::Duration;
+
+        use super::dbus::{self, Arg, Bus};
+        use super::imp::{LOGIND, Watch};
+
+        struct Daemon(Child);
+
+        fn hung_up(fd: &OwnedFd, timeout: libc::c_int) -> bool {
+            let mut pfd = libc::pollfd {
+                fd: fd.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // Safety: one pollfd, for a descriptor that outlives the call.
+            unsafe { libc::poll(&mut pfd, 1, timeout) == 1 }
+        }
+
+        impl Drop for Daemon {
+            fn drop(&mut self) {
+                let _ = self.0.kill();
+                let _ = self.0.wait();
+            }
+        }
+
+        // Hooks are process-wide, and the other test runs them too.
+        if !crate::test_util::in_own_process("suspend::tests::logind_prepare_for_sleep_runs_hooks")
+        {
+            return;
+        }
+        // A private bus stands in for the system bus, with this test playing logind.
+        let dir = std::env::temp_dir().join(format!("os-memlock-bus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                "<busconfig><type>session</type><listen>unix:path={}</listen>\
+                 <policy context=\"default\"><allow send_destination=\"*\"/>\
+                 <allow receive_sender=\"*\"/><allow own=\"*\"/></policy></busconfig>",
+                dir.join("socket").display()
+            ),
+        )
+        .unwrap();
+        let spawned = Command::new("dbus-daemon")
+            .arg("--nofork")
+            .arg("--print-address")
+            .arg(format!("--config-file={}", config.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut daemon) = spawned.map(Daemon) else {
+            // dbus-daemon is not installed.
+            let _ = std::fs::remove_dir_all(&dir);
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.0.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let address = address.trim();
+
+        let err = Watch::connect(address).err().unwrap();
+        assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+        let mut logind = Bus::connect(address).unwrap();
+        let name = [Arg::Str(LOGIND.destination.into()), Arg::U32(0)];
+        logind.call(&dbus::BUS, "RequestName", "su", &name).unwrap();
+        // The inhibitor handed out is the write end of a pipe, held while the read end
+        // does not poll as hung up.
+        let mut fds = [0; 2];
+        // Safety: pipe2 fills in two new descriptors, owned from here on.
+        assert_eq!(unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) }, 0);
+        // Safety: as above.
+        let (read, write) = unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) };
+        let read = Arc::new(read);
+        let (tx, rx) = mpsc::channel();
+        let probe = Arc::clone(&read);
+        let _hook = on_suspend(move || {
+            let _ = tx.send(hung_up(&probe, 0));
+        });
+
+        let watch = Watch::connect(address).unwrap();
+        let inhibit = loop {
+            let msg = logind.read().unwrap().unwrap();
+            if msg.member == "Inhibit" {
+                break msg;
+            }
+        };
+        assert_eq!(inhibit.args().unwrap()[3], Arg::Str("delay".into()));
+        logind.reply_with_fd(&inhibit, write.as_fd()).unwrap();
+        drop(write);
+        logind
+            .signal(&LOGIND, "PrepareForSleep", "b", &[Arg::U32(1)])
+            .unwrap();
+        let released_early = rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(!released_early, "inhibitor released before the hooks ran");
+        assert!(
+            hung_up(&read, 10_000),
+            "inhibitor still held after the hooks ran"
+        );
+        drop(watch);
+        drop(daemon);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn unknown_header_fields_are_skipped() {
+        use std::io::Write;
+        use std::os::unix::net::UnixStream;
+
+        use super::dbus::{Arg, Bus};
+
+        fn pad(msg: &mut Vec<u8>, to: usize) {
+            msg.resize(msg.len().next_multiple_of(to), 0);
+        }
+        fn string(msg: &mut Vec<u8>, value: &str) {
+            pad(msg, 4);
+            msg.extend_from_slice(&(value.len() as u32).to_le_bytes());
+            msg.extend_from_slice(value.as_bytes());
+            msg.push(0);
+        }
+        fn signature(msg: &mut Vec<u8>, value: &str) {
+            msg.push(value.len() as u8);
+            msg.extend_from_slice(value.as_bytes());
+            msg.push(0);
+        }
+        fn field(msg: &mut Vec<u8>, code: u8, kind: &str) {
+            pad(msg, 8);
+            msg.push(code);
+            signature(msg, kind);
+        }
+
+        // A little-endian PrepareForSleep(true) signal with an unknown field, typed
+        // `a(tv)`, between the ones the client reads.
+        let mut msg = vec![b'l', 4, 0, 1, 4, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        field(&mut msg, 1, "o");
+        string(&mut msg, "/org/freedesktop/login1");
+        field(&mut msg, 0x7f, "a(tv)");
+        let len_at = msg.len();
+        msg.extend_from_slice(&[0; 4]);
+        pad(&mut msg, 8);
+        let elements = msg.len();
+        msg.extend_from_slice(&u64::MAX.to_le_bytes());
+        signature(&mut msg, "s");
+        string(&mut msg, "unknown");
+        let len = (msg.len() - elements) as u32;
+        msg[len_at..len_at + 4].copy_from_slice(&len.to_le_bytes());
+        field(&mut msg, 3, "s");
+        string(&mut msg, "PrepareForSleep");
+        field(&mut msg, 8, "g");
+        signature(&mut msg, "b");
+        let fields = (msg.len() - 16) as u32;
+        msg[12..16].copy_from_slice(&fields.to_le_bytes());
+        pad(&mut msg, 8);
+        msg.extend_from_slice(&1u32.to_le_bytes());
+
+        let (mut peer, stream) = UnixStream::pair().unwrap();
+        peer.write_all(&msg).unwrap();
+        let msg = Bus::over(stream).read().unwrap().unwrap();
+        assert_eq!(msg.path, "/org/freedesktop/login1");
+        assert_eq!(msg.member, "PrepareForSleep");
+        assert_eq!(msg.args().unwrap(), [Arg::U32(1)]);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn registers_for_system_power() {
+        drop(super::watch_system_suspend().unwrap());
+    }
+}
@@ -0,0 +1,335 @@
+//! System calls under the Unix wrappers.
+//!
+//! Backends, chosen at compile time:
+//! - `rustix` feature on Linux/Android: rustix, which issues the calls directly without
+//!   errno juggling (and through libc on targets where rustix itself uses libc).
+//! - `raw-syscalls` feature on Linux: `syscall(2)` by number, skipping libc's wrappers,
+//!   for static musl binaries, seccomp policies written against raw syscall numbers, and
+//!   libcs that lag behind the kernel.
+//! - Otherwise: the libc functions. On Haiku, `mlock` and `munlock` are looked up at run
+//!   time, since older releases lack them, and `mlockall` does not exist. On Fuchsia the
+//!   lock calls are never made: Zircon cannot lock user memory.
+//!
+//! Every backend maps failures to `io::Error` with the OS error code, so the public API
+//! behaves identically whichever is selected.
+
+#[cfg(all(feature = "rustix", any(target_os = "linux", target_os = "android")))]
+mod imp {
+    use std::io;
+    use std::os::raw::{c_int, c_void};
+
+    use rustix::mm::{self, MlockAllFlags};
+    use rustix::process::{self, DumpableBehavior};
+
+    use crate::Advice;
+
+    pub(crate) unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller; mlock does not write through the pointer.
+        Ok(unsafe { mm::mlock(addr.cast_mut(), len) }?)
+    }
+
+    pub(crate) unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: as for mlock.
+        Ok(unsafe { mm::munlock(addr.cast_mut(), len) }?)
+    }
+
+    pub(crate) unsafe fn madvise(addr: *mut c_void, len: usize, advice: Advice) -> io::Result<()> {
+        let advice = match advice {
+            Advice::Normal => mm::Advice::Normal,
+            Advice::Random => mm::Advice::Random,
+            Advice::Sequential => mm::Advice::Sequential,
+            Advice::WillNeed => mm::Advice::WillNeed,
+            // rustix's `DontNeed` is the POSIX no-op; the crate means MADV_DONTNEED.
+            Advice::DontNeed => mm::Advice::LinuxDontNeed,
+            Advice::Free => mm::Advice::LinuxFree,
+            Advice::DontDump => mm::Advice::LinuxDontDump,
+            Advice::DoDump => mm::Advice::LinuxDoDump,
+            Advice::WipeOnFork => mm::Advice::LinuxWipeOnFork,
+            Advice::KeepOnFork => mm::Advice::LinuxKeepOnFork,
+            Advice::DontFork => mm::Advice::LinuxDontFork,
+            Advice::DoFork => mm::Advice::LinuxDoFork,
+            Advice::Hugepage => mm::Advice::LinuxHugepage,
+            Advice::NoHugepage => mm::Advice::LinuxNoHugepage,
+        };
+        // Safety: forwarded from the caller.
+        Ok(unsafe { mm::madvise(addr, len, advice) }?)
+    }
+
+    pub(crate) fn mlockall(flags: c_int) -> io::Result<()> {
+        Ok(mm::mlockall(MlockAllFlags::from_bits_retain(flags as u32))?)
+    }
+
+    pub(crate) fn munlockall() -> io::Result<()> {
+        Ok(mm::munlockall()?)
+    }
+
+    pub(crate) fn set_dumpable(dumpable: bool) -> io::Result<()> {
+        let behavior = if dumpable {
+            DumpableBehavior::Dumpable
+        } else {
+            DumpableBehavior::NotDumpable
+        };
+        Ok(process::set_dumpable_behavior(behavior)?)
+    }
+
+    pub(crate) fn is_dumpable() -> io::Result<bool> {
+        Ok(process::dumpable_behavior()? != DumpableBehavior::NotDumpable)
+    }
+}
+
+#[cfg(all(feature = "raw-syscalls", target_os = "linux", not(feature = "rustix")))]
+mod imp {
+    use std::io;
+    use std::os::raw::{c_int, c_long, c_ulong, c_void};
+
+    use super::{advice_or_unsupported, cvt};
+    use crate::Advice;
+
+    pub(crate) unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::syscall(libc::SYS_mlock, addr, len) })
+    }
+
+    pub(crate) unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::syscall(libc::SYS_munlock, addr, len) })
+    }
+
+    pub(crate) unsafe fn madvise(addr: *mut c_void, len: usize, advice: Advice) -> io::Result<()> {
+        // Integer arguments are widened so the variadic call fills whole registers.
+        let raw = c_long::from(advice_or_unsupported(advice)?);
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::syscall(libc::SYS_madvise, addr, len, raw) })
+    }
+
+    pub(crate) fn mlockall(flags: c_int) -> io::Result<()> {
+        // Safety: no pointer arguments.
+        cvt(unsafe { libc::syscall(libc::SYS_mlockall, c_long::from(flags)) })
+    }
+
+    pub(crate) fn munlockall() -> io::Result<()> {
+        // Safety: no arguments.
+        cvt(unsafe { libc::syscall(libc::SYS_munlockall) })
+    }
+
+    pub(crate) fn set_dumpable(dumpable: bool) -> io::Result<()> {
+        let option = c_long::from(libc::PR_SET_DUMPABLE);
+        let zero: c_ulong = 0;
+        // Safety: PR_SET_DUMPABLE takes a single integer argument.
+        cvt(unsafe {
+            libc::syscall(
+                libc::SYS_prctl,
+                option,
+                c_ulong::from(dumpable),
+                zero,
+                zero,
+                zero,
+            )
+        })
+    }
+
+    pub(crate) fn is_dumpable() -> io::Result<bool> {
+        let option = c_long::from(libc::PR_GET_DUMPABLE);
+        let zero: c_ulong = 0;
+        // Safety: PR_GET_DUMPABLE takes no arguments.
+        let rc = unsafe { libc::syscall(libc::SYS_prctl, option, zero, zero, zero, zero) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(rc != 0)
+    }
+}
+
+#[cfg(not(any(
+    all(feature = "rustix", any(target_os = "linux", target_os = "android")),
+    all(feature = "raw-syscalls", target_os = "linux")
+)))]
+mod imp {
+    use std::io;
+    use std::os::raw::{c_int, c_void};
+
+    #[cfg(target_os = "haiku")]
+    use std::sync::OnceLock;
+
+    use super::cvt;
+
+    #[cfg(not(any(target_os = "haiku", target_os = "fuchsia")))]
+    pub(crate) unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::mlock(addr, len) })
+    }
+
+    #[cfg(not(any(target_os = "haiku", target_os = "fuchsia")))]
+    pub(crate) unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::munlock(addr, len) })
+    }
+
+    #[cfg(target_os = "haiku")]
+    pub(crate) unsafe fn mlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        static MLOCK: OnceLock<Option<LockFn>> = OnceLock::new();
+        let mlock = MLOCK.get_or_init(|| lookup(c"mlock"));
+        // Safety: forwarded from the caller; the pointer has mlock's signature.
+        cvt(unsafe { mlock.ok_or_else(missing)?(addr, len) })
+    }
+
+    #[cfg(target_os = "haiku")]
+    pub(crate) unsafe fn munlock(addr: *const c_void, len: usize) -> io::Result<()> {
+        static MUNLOCK: OnceLock<Option<LockFn>> = OnceLock::new();
+        let munlock = MUNLOCK.get_or_init(|| lookup(c"munlock"));
+        // Safety: as for mlock.
+        cvt(unsafe { munlock.ok_or_else(missing)?(addr, len) })
+    }
+
+    /// The signature `mlock` and `munlock` share.
+    #[cfg(target_os = "haiku")]
+    type LockFn = unsafe extern "C" fn(*const c_void, usize) -> c_int;
+
+    /// Look up `name` in the loaded libroot, which has had it only since R1/beta2.
+    #[cfg(target_os = "haiku")]
+    fn lookup(name: &std::ffi::CStr) -> Option<LockFn> {
+        // Safety: a NUL-terminated name, looked up in the global scope.
+        let sym = unsafe { libc::dlsym(libc::RTLD_DEFAULT, name.as_ptr()) };
+        // Safety: a non-null result is the libroot function of that name.
+        (!sym.is_null()).then(|| unsafe { std::mem::transmute::<*mut c_void, LockFn>(sym) })
+    }
+
+    #[cfg(target_os = "haiku")]
+    fn missing() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "mlock unavailable in this Haiku release",
+        )
+    }
+
+    // QNX has no madvise and AIX's takes a `caddr_t`; both use posix_madvise instead.
+    #[cfg(not(any(target_os = "aix", target_os = "nto")))]
+    pub(crate) unsafe fn madvise(
+        addr: *mut c_void,
+        len: usize,
+        advice: crate::Advice,
+    ) -> io::Result<()> {
+        let raw = super::advice_or_unsupported(advice)?;
+        // Safety: forwarded from the caller.
+        cvt(unsafe { libc::madvise(addr, len, raw) })
+    }
+
+    #[cfg(not(any(target_os = "haiku", target_os = "fuchsia")))]
+    pub(crate) fn mlockall(flags: c_int) -> io::Result<()> {
+        // Safety: no pointer arguments.
+        cvt(unsafe { libc::mlockall(flags) })
+    }
+
+    #[cfg(not(any(target_os = "haiku", target_os = "fuchsia")))]
+    pub(crate) fn munlockall() -> io::Result<()> {
+        // Safety: no arguments.
+        cvt(unsafe { libc::munlockall() })
+    }
+
+    #[cfg(target_os = "haiku")]
+    pub(crate) fn munlockall() -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "munlockall unsupported on Haiku",
+        ))
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    pub(crate) unsafe fn mlock(_addr: *const c_void, _len: usize) -> io::Result<()> {
+        Err(no_locking())
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    pub(crate) unsafe fn munlock(_addr: *const c_void, _len: usize) -> io::Result<()> {
+        Err(no_locking())
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    pub(crate) fn mlockall(_flags: c_int) -> io::Result<()> {
+        Err(no_locking())
+    }
+
+    #[cfg(target_os = "fuchsia")]
+    pub(crate) fn munlockall() -> io::Result<()> {
+        Err(no_locking())
+    }
+
+    /// Zircon only pins memory for device DMA, through a bus transaction initiator
+    /// handle ordinary processes do not hold; there is nothing `mlock` could map to.
+    #[cfg(target_os = "fuchsia")]
+    fn no_locking() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "memory locking unsupported on Fuchsia: Zircon has no mlock",
+        )
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn set_dumpable(dumpable: bool) -> io::Result<()> {
+        let value = libc::c_ulong::from(dumpable);
+        // Safety: PR_SET_DUMPABLE takes a single integer argument.
+        cvt(unsafe { libc::prctl(libc::PR_SET_DUMPABLE, value, 0, 0, 0) })
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub(crate) fn is_dumpable() -> io::Result<bool> {
+        // Safety: PR_GET_DUMPABLE takes no arguments.
+        let rc = unsafe { libc::prctl(libc::PR_GET_DUMPABLE, 0, 0, 0, 0) };
+        if rc < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(rc != 0)
+    }
+}
+
+pub(crate) use imp::*;
+
+/// Map a libc-style return value (0 on success, -1 and errno on failure).
+#[cfg(not(all(feature = "rustix", any(target_os = "linux", target_os = "android"))))]
+fn cvt<T: Default + PartialEq>(rc: T) -> std::io::Result<()> {
+    if rc == T::default() {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+#[cfg(not(any(
+    all(feature = "rustix", any(target_os = "linux", target_os = "android")),
+    target_os = "aix",
+    target_os = "nto"
+)))]
+fn advice_or_unsupported(advice: crate::Advice) -> std::io::Result<std::os::raw::c_int> {
+    crate::unix::raw_advice(advice).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "madvise advice unsupported on this platform",
+        )
+    })
+}
+
+/// mseal(2), which libc does not export for every architecture. New calls share one
+/// number on every architecture with the unified syscall table; MIPS offsets it per
+/// ABI, alpha has its own numbering, and x32 sets a flag bit, so those have none.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) const SYS_MSEAL: Option<libc::c_long> = if cfg!(any(
+    target_arch = "x86",
+    all(target_arch = "x86_64", target_pointer_width = "64"),
+    target_arch = "arm",
+    target_arch = "aarch64",
+    target_arch = "riscv32",
+    target_arch = "riscv64",
+    target_arch = "loongarch64",
+    target_arch = "powerpc",
+    target_arch = "powerpc64",
+    target_arch = "s390x",
+    target_arch = "sparc",
+    target_arch = "sparc64",
+    target_arch = "m68k",
+    target_arch = "csky",
+    target_arch = "hexagon"
+)) {
+    Some(462)
+} else {
+    None
+};
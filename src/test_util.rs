@@ -0,0 +1,31 @@
+//! Running a unit test in a process of its own.
+
+use std::process::Command;
+
+/// Set in the re-executed test binary to the test it was started for.
+const VAR: &str = "OS_MEMLOCK_OWN_PROCESS";
+
+/// Whether the calling test should run its body here.
+///
+/// For tests that change process-wide state (resource limits, dumpability) or read
+/// counters other tests move, which the harness's threads would otherwise share. In the
+/// test harness this re-runs the test binary with only `test` (its path in the crate,
+/// e.g. `verify::tests::name`), asserts that it passed and returns false; in that copy
+/// it returns true.
+pub(crate) fn in_own_process(test: &str) -> bool {
+    if std::env::var_os(VAR).is_some_and(|name| name == test) {
+        return true;
+    }
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([test, "--exact", "--test-threads=1"])
+        .env(VAR, test)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success() && stdout.contains("1 passed"),
+        "{test} failed in its own process:\n{stdout}{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    false
+}
@@ -0,0 +1,73 @@
+//! Transparent hugepage policy for secret allocations.
+//!
+//! With THP set to `always`, the kernel may back a page-aligned heap region with a
+//! 2 MiB hugepage. Locking any part of it then pins (and charges RLIMIT_MEMLOCK for) the
+//! whole hugepage, which shows up as a mysterious ENOMEM after a handful of tiny secrets.
+
+use std::sync::OnceLock;
+
+/// Whether a region of `len` bytes should be marked `MADV_NOHUGEPAGE` before locking.
+///
+/// True only when THP is `always` and the region is smaller than one hugepage; under
+/// `madvise` or `never` the kernel does not collapse regions nobody opted in.
+pub(crate) fn should_avoid_hugepages(len: usize) -> bool {
+    match policy() {
+        Some(hugepage_size) => len < hugepage_size,
+        None => false,
+    }
+}
+
+/// The hugepage size when THP is `always`, cached after the first lookup.
+fn policy() -> Option<usize> {
+    static POLICY: OnceLock<Option<usize>> = OnceLock::new();
+    *POLICY.get_or_init(read_policy)
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const SYSFS: &str = "/sys/kernel/mm/transparent_hugepage";
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_policy() -> Option<usize> {
+    let enabled = std::fs::read_to_string(format!("{SYSFS}/enabled")).ok()?;
+    if selected_mode(&enabled)? != "always" {
+        return None;
+    }
+    Some(hugepage_size())
+}
+
+/// Size of a PMD-level hugepage (2 MiB on x86_64), cached after the first lookup.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn hugepage_size() -> usize {
+    static SIZE: OnceLock<usize> = OnceLock::new();
+    *SIZE.get_or_init(|| {
+        std::fs::read_to_string(format!("{SYSFS}/hpage_pmd_size"))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(2 * 1024 * 1024)
+    })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn read_policy() -> Option<usize> {
+    None
+}
+
+/// The bracketed entry of a sysfs choice list, e.g. `madvise` in `always [madvise] never`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn selected_mode(choices: &str) -> Option<&str> {
+    choices
+        .split_whitespace()
+        .find_map(|word| word.strip_prefix('[')?.strip_suffix(']'))
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "android")))]
+mod tests {
+    use super::selected_mode;
+
+    #[test]
+    fn parses_bracketed_mode() {
+        assert_eq!(selected_mode("[always] madvise never\n"), Some("always"));
+        assert_eq!(selected_mode("always [madvise] never\n"), Some("madvise"));
+        assert_eq!(selected_mode("always madvise never\n"), None);
+    }
+}
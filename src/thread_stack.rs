@@ -0,0 +1,146 @@
+//! Locking the calling thread's stack, where secrets pass through in local variables.
+
+use std::io;
+use std::marker::PhantomData;
+
+/// The calling thread's stack, locked by [`lock_thread_stack`]; unlocked on drop.
+///
+/// Not `Send`: drop it on the thread whose stack it locks, before the thread exits.
+#[must_use = "dropping the guard unlocks the stack again"]
+#[derive(Debug)]
+pub struct ThreadStackLock {
+    addr: usize,
+    len: usize,
+    _thread: PhantomData<*const ()>,
+}
+
+impl ThreadStackLock {
+    /// Number of bytes locked, from the deepest prepared frame to the stack's base.
+    pub fn locked_len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Drop for ThreadStackLock {
+    fn drop(&mut self) {
+        // Safety: the range locked in `lock_thread_stack`, on this thread's live stack.
+        if let Err(e) = unsafe { crate::munlock(self.addr as *const _, self.len) } {
+            diag!("unlocking the thread stack failed: {e}");
+        }
+    }
+}
+
+/// Lock the calling thread's stack in RAM, so secrets that transiently live in stack
+/// frames (small key arrays, copies made by callees) are never paged out.
+///
+/// Locks everything between the stack's base and `depth` bytes below the caller's
+/// frame. Those pages are touched first, so frames of functions called later, down to
+/// that depth, are already committed and locked.
+///
+/// Behavior:
+/// - Windows: `GetCurrentThreadStackLimits` bounds the stack and `VirtualLock` locks the
+///   range; pages below it are still committed on demand and not locked.
+/// - The lock counts against the working-set quota like any [`mlock`](crate::mlock);
+///   see [`mlock_grow_working_set`](crate::mlock_grow_working_set).
+///
+/// Platform:
+/// - Windows only. On other platforms, returns `Unsupported`; use
+///   [`lock_all_memory`](crate::lock_all_memory) with `MclFlags::CURRENT |
+///   MclFlags::FUTURE`, which also covers thread stacks.
+///
+/// Returns:
+/// - `Ok(guard)` once the range is locked
+/// - `Err(InvalidInput)` if `depth` reaches past the stack's reserved size
+/// - `Err(...)` with the OS error if locking failed
+pub fn lock_thread_stack(depth: usize) -> io::Result<ThreadStackLock> {
+    let (addr, len) = imp::lock(depth)?;
+    Ok(ThreadStackLock {
+        addr,
+        len,
+        _thread: PhantomData,
+    })
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::hint::black_box;
+    use std::io;
+
+    use windows_sys::Win32::System::Threading::GetCurrentThreadStackLimits;
+
+    /// Stack kept free below the probed range: the guard page and what the OS needs to
+    /// raise a stack overflow cleanly.
+    const HEADROOM: usize = 64 * 1024;
+
+    const FRAME: usize = 4096;
+
+    /// Touch `frames` stack frames of one page each, committing the pages below the
+    /// caller the way `__chkstk` would.
+    #[inline(never)]
+    fn probe(frames: usize) {
+        let mut frame = [0u8; FRAME];
+        black_box(&mut frame);
+        if frames > 1 {
+            probe(frames - 1);
+        }
+        black_box(&frame);
+    }
+
+    pub(super) fn lock(depth: usize) -> io::Result<(usize, usize)> {
+        let (mut low, mut high) = (0, 0);
+        // Safety: two valid out-pointers.
+        unsafe { GetCurrentThreadStackLimits(&mut low, &mut high) };
+        let here = black_box(&low) as *const usize as usize;
+        let page = crate::page_size();
+        let bottom = here
+            .checked_sub(depth)
+            .filter(|&bottom| bottom >= low.saturating_add(HEADROOM))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "depth {depth} exceeds the {} bytes left on the thread stack",
+                        here.saturating_sub(low + HEADROOM)
+                    ),
+                )
+            })?;
+        probe(depth.div_ceil(FRAME) + 1);
+        let start = bottom / page * page;
+        let len = high - start;
+        // Safety: [start, high) is this thread's stack, committed down to `start` by the
+        // probe above, and outlives the lock's guard.
+        unsafe { crate::mlock(start as *const _, len)? };
+        Ok((start, len))
+    }
+}
+
+#[cfg(not(windows))]
+mod imp {
+    use std::io;
+
+    pub(super) fn lock(_depth: usize) -> io::Result<(usize, usize)> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "lock_thread_stack is Windows-only; lock the stacks with mlockall instead",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::lock_thread_stack;
+
+    #[test]
+    fn thread_stack_locks_or_is_unsupported() {
+        let lock = match lock_thread_stack(16 * 1024) {
+            Ok(lock) => lock,
+            Err(e) if e.kind() == ErrorKind::Unsupported => return,
+            Err(e) => panic!("{e}"),
+        };
+        assert!(lock.locked_len() >= 16 * 1024);
+        let err = lock_thread_stack(usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,966 @@
+//! Checking that memory the crate reports as locked really is.
+//!
+//! A successful `mlock` is a statement of intent; some container runtimes and sandboxes
+//! accept it without pinning anything. These helpers ask the kernel what actually
+//! happened.
+
+use std::fmt;
+use std::io;
+use std::os::raw::c_void;
+
+/// Page-by-page residency of a memory range, as reported by [`is_resident`].
+#[derive(Clone, PartialEq, Eq)]
+pub struct ResidencyReport {
+    page_size: usize,
+    pages: Vec<bool>,
+}
+
+impl ResidencyReport {
+    /// Page size the report is expressed in.
+    pub fn page_size(&self) -> usize {
+        self.page_size
+    }
+
+    /// Residency of each page covering the range, in address order.
+    ///
+    /// The first entry is the page containing the range's start address.
+    pub fn pages(&self) -> &[bool] {
+        &self.pages
+    }
+
+    /// Number of pages covering the range.
+    pub fn total_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Number of pages currently in RAM.
+    pub fn resident_pages(&self) -> usize {
+        self.pages.iter().filter(|&&resident| resident).count()
+    }
+
+    /// Whether every page covering the range is in RAM.
+    pub fn is_fully_resident(&self) -> bool {
+        self.pages.iter().all(|&resident| resident)
+    }
+
+    /// Indices (into [`ResidencyReport::pages`]) of pages that are not in RAM.
+    pub fn non_resident(&self) -> impl Iterator<Item = usize> + '_ {
+        self.pages
+            .iter()
+            .enumerate()
+            .filter(|(_, resident)| !**resident)
+            .map(|(index, _)| index)
+    }
+}
+
+impl fmt::Debug for ResidencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResidencyReport")
+            .field("page_size", &self.page_size)
+            .field("total_pages", &self.total_pages())
+            .field("resident_pages", &self.resident_pages())
+            .finish()
+    }
+}
+
+/// Report which pages of (addr, len) are resident in RAM, using mincore(2) (on
+/// Windows, the `Valid` attribute from `QueryWorkingSetEx`).
+///
+/// The range is widened to whole pages, so `addr` need not be page-aligned. A locked
+/// region should always be fully resident; a page that is not means the lock was a
+/// no-op or was lost.
+///
+/// Platform:
+/// - Linux, Android, macOS/iOS, FreeBSD, DragonFly, NetBSD, OpenBSD, Solaris, illumos,
+///   AIX and Windows.
+/// - Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(report); an empty report for a zero-length range
+/// - Err(...) with last_os_error() if the call failed (ENOMEM if part of the range is
+///   not mapped; Windows reports unmapped pages as not resident instead)
+/// - Err(Unsupported) if not supported on this platform
+///
+/// # Safety
+/// Nothing is read or written through `addr`, but the range must not be unmapped
+/// concurrently, or the report may describe an unrelated mapping.
+pub unsafe fn is_resident(addr: *const c_void, len: usize) -> io::Result<ResidencyReport> {
+    let page_size = crate::page_size();
+    if len == 0 {
+        return Ok(ResidencyReport {
+            page_size,
+            pages: Vec::new(),
+        });
+    }
+    let (start, end) = page_span(addr, len)?;
+    let mut vec = vec![0u8; (end - start) / page_size];
+    // Safety: forwarded from the caller; `vec` has one byte per page in the range.
+    unsafe { mincore(start as *mut c_void, end - start, &mut vec)? };
+    Ok(ResidencyReport {
+        page_size,
+        // Only the low bit is specified; the rest are platform-specific flags.
+        pages: vec.iter().map(|&flags| flags & 1 != 0).collect(),
+    })
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "solaris",
+    target_os = "illumos",
+    target_os = "aix"
+))]
+unsafe fn mincore(start: *mut c_void, len: usize, vec: &mut [u8]) -> io::Result<()> {
+    // The pointer types differ between platforms (`void *`, `caddr_t`, `char *`).
+    // Safety: guaranteed by the caller.
+    let rc = unsafe { libc::mincore(start as _, len, vec.as_mut_ptr() as _) };
+    if rc == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(windows)]
+unsafe fn mincore(start: *mut c_void, _len: usize, vec: &mut [u8]) -> io::Result<()> {
+    for (resident, flags) in vec
+        .iter_mut()
+        .zip(working_set_ex(start as usize, vec.len())?)
+    {
+        *resident = (flags & WS_VALID != 0) as u8;
+    }
+    Ok(())
+}
+
+#[cfg(not(any(
+    windows,
+    target_os = "linux",
+    target_os = "android",
+    target_vendor = "apple",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "solaris",
+    target_os = "illumos",
+    target_os = "aix"
+)))]
+unsafe fn mincore(_start: *mut c_void, _len: usize, _vec: &mut [u8]) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "is_resident unsupported on this platform",
+    ))
+}
+
+/// Check in /proc/self/smaps (on Windows, with `QueryWorkingSetEx`; on macOS, in the
+/// task's VM map) that every page of (addr, len) is locked.
+///
+/// This asks the kernel about the memory itself rather than trusting the `mlock` return
+/// value, which suits compliance checks and integration tests. The range is widened to
+/// whole pages. On Windows, it is locked when every page has the `Valid` and `Locked`
+/// attributes. On Linux, it is locked when:
+/// - every page of it is mapped,
+/// - every mapping overlapping it carries the `lo` (VM_LOCKED) flag in `VmFlags`, and
+/// - every such mapping with resident pages reports a non-zero `Locked:` size.
+///
+/// On macOS, it is locked when every VM map entry covering it (found with
+/// `mach_vm_region_recurse`) has a non-zero user wired count. Wired pages are resident;
+/// there is no per-region dump exclusion to check on macOS.
+///
+/// smaps and the VM map describe whole mappings, not pages; `mlock` splits mappings at
+/// the locked range's boundaries, so in practice this is exact for page-aligned regions.
+///
+/// Platform:
+/// - Linux, Android, macOS and Windows. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(true) if the range is locked (trivially so for a zero-length range)
+/// - Ok(false) if any part of it is unlocked or unmapped
+/// - Err(...) if /proc/self/smaps could not be read, `QueryWorkingSetEx` failed or
+///   `mach_vm_region_recurse` failed
+pub fn verify_region_locked(addr: *const c_void, len: usize) -> io::Result<bool> {
+    if len == 0 {
+        return Ok(true);
+    }
+    let (start, end) = page_span(addr, len)?;
+    smaps_range_locked(start, end)
+}
+
+/// (addr, len) widened to whole pages, as a [start, end) address pair.
+fn page_span(addr: *const c_void, len: usize) -> io::Result<(usize, usize)> {
+    let page_size = crate::page_size();
+    let start = addr as usize - addr as usize % page_size;
+    let end = (addr as usize)
+        .checked_add(len)
+        .and_then(|end| end.checked_next_multiple_of(page_size))
+        .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+    Ok((start, end))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn smaps_range_locked(start: usize, end: usize) -> io::Result<bool> {
+    let smaps = std::fs::read_to_string("/proc/self/smaps")?;
+    Ok(range_locked(&parse_smaps(&smaps), start, end))
+}
+
+#[cfg(windows)]
+fn smaps_range_locked(start: usize, end: usize) -> io::Result<bool> {
+    let pages = (end - start) / crate::page_size();
+    Ok(working_set_ex(start, pages)?
+        .into_iter()
+        .all(|flags| flags & (WS_VALID | WS_LOCKED) == WS_VALID | WS_LOCKED))
+}
+
+/// `PSAPI_WORKING_SET_EX_BLOCK` bits: the page is in the working set, and it is locked
+/// there with `VirtualLock`.
+#[cfg(windows)]
+const WS_VALID: usize = 1 << 0;
+#[cfg(windows)]
+const WS_LOCKED: usize = 1 << 22;
+
+/// The `PSAPI_WORKING_SET_EX_BLOCK` flags of `pages` pages starting at `start`, from
+/// `QueryWorkingSetEx`. Pages outside any allocation come back with no bits set.
+#[cfg(windows)]
+fn working_set_ex(start: usize, pages: usize) -> io::Result<Vec<usize>> {
+    use windows_sys::Win32::System::ProcessStatus::{
+        K32QueryWorkingSetEx, PSAPI_WORKING_SET_EX_INFORMATION,
+    };
+    use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+    let page_size = crate::page_size();
+    let mut info: Vec<PSAPI_WORKING_SET_EX_INFORMATION> = (0..pages)
+        .map(|i| PSAPI_WORKING_SET_EX_INFORMATION {
+            VirtualAddress: (start + i * page_size) as *mut c_void,
+            ..Default::default()
+        })
+        .collect();
+    let size = u32::try_from(std::mem::size_of_val(info.as_slice()))
+        .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    // Safety: the current-process pseudo handle and a buffer of `size` bytes whose
+    // entries name the addresses to query; only the attributes are written.
+    if unsafe { K32QueryWorkingSetEx(GetCurrentProcess(), info.as_mut_ptr().cast(), size) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Safety: every bit pattern is a valid `Flags` value.
+    Ok(info
+        .iter()
+        .map(|entry| unsafe { entry.VirtualAttributes.Flags })
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+fn smaps_range_locked(start: usize, end: usize) -> io::Result<bool> {
+    // Like smaps, the VM map describes whole entries; `mlock` splits entries at the
+    // locked range's boundaries, so this is exact for page-aligned regions.
+    let mut next = start;
+    let mut locked = true;
+    mach::for_each_region(start as u64, |region| {
+        if region.start as usize > next || region.user_wired_count == 0 {
+            locked = false;
+        }
+        next = region.end as usize;
+        locked && next < end
+    })?;
+    Ok(locked && next >= end)
+}
+
+/// The Mach VM map of the current task, walked with `mach_vm_region_recurse`, and its
+/// `task_info` statistics (macOS).
+#[cfg(target_os = "macos")]
+mod mach {
+    use std::io;
+
+    /// `vm_region_submap_info_64`, declared under `#pragma pack(4)`; libc does not
+    /// define it.
+    #[repr(C, packed(4))]
+    #[derive(Default)]
+    #[allow(dead_code)] // Only some fields are read.
+    struct SubmapInfo64 {
+        protection: libc::c_int,
+        max_protection: libc::c_int,
+        inheritance: libc::c_uint,
+        offset: u64,
+        user_tag: libc::c_uint,
+        pages_resident: libc::c_uint,
+        pages_shared_now_private: libc::c_uint,
+        pages_swapped_out: libc::c_uint,
+        pages_dirtied: libc::c_uint,
+        ref_count: libc::c_uint,
+        shadow_depth: libc::c_ushort,
+        external_pager: libc::c_uchar,
+        share_mode: libc::c_uchar,
+        is_submap: libc::boolean_t,
+        behavior: libc::c_int,
+        object_id: u32,
+        user_wired_count: libc::c_ushort,
+        pages_reusable: libc::c_uint,
+        object_id_full: u64,
+    }
+
+    /// The first revision of `task_vm_info`, also under `#pragma pack(4)`; the kernel
+    /// fills in as many fields as the caller's count covers.
+    #[repr(C, packed(4))]
+    #[derive(Default)]
+    #[allow(dead_code)] // Only some fields are read.
+    pub(super) struct TaskVmInfo {
+        virtual_size: u64,
+        region_count: libc::integer_t,
+        page_size: libc::integer_t,
+        pub(super) resident_size: u64,
+        resident_size_peak: u64,
+        device: u64,
+        device_peak: u64,
+        internal: u64,
+        internal_peak: u64,
+        external: u64,
+        external_peak: u64,
+        reusable: u64,
+        reusable_peak: u64,
+        purgeable_volatile_pmap: u64,
+        purgeable_volatile_resident: u64,
+        purgeable_volatile_virtual: u64,
+        pub(super) compressed: u64,
+        compressed_peak: u64,
+        compressed_lifetime: u64,
+    }
+
+    const TASK_VM_INFO: libc::task_flavor_t = 22;
+
+    fn count_of<T>() -> libc::mach_msg_type_number_t {
+        (size_of::<T>() / size_of::<libc::natural_t>()) as libc::mach_msg_type_number_t
+    }
+
+    unsafe extern "C" {
+        // libc only exposes `mach_task_self` as deprecated in favour of the mach2 crate.
+        static mach_task_self_: libc::mach_port_t;
+
+        fn mach_vm_region_recurse(
+            target_task: libc::mach_port_t,
+            address: *mut libc::mach_vm_address_t,
+            size: *mut libc::mach_vm_size_t,
+            nesting_depth: *mut libc::natural_t,
+            info: *mut libc::integer_t,
+            info_count: *mut libc::mach_msg_type_number_t,
+        ) -> libc::kern_return_t;
+    }
+
+    fn task_self() -> libc::mach_port_t {
+        // Safety: set up by libSystem before `main` and never changed.
+        unsafe { mach_task_self_ }
+    }
+
+    fn kern_error(call: &str, kr: libc::kern_return_t) -> io::Error {
+        io::Error::other(format!("{call} failed with kern_return_t {kr}"))
+    }
+
+    /// One VM map entry: [start, end) and how many times user space wired it.
+    pub(super) struct Region {
+        pub(super) start: u64,
+        pub(super) end: u64,
+        pub(super) user_wired_count: u16,
+    }
+
+    /// Call `f` with every leaf entry at or above `from`, in address order, until it
+    /// returns false or the address space ends. Submaps (the shared cache) are entered.
+    pub(super) fn for_each_region(from: u64, mut f: impl FnMut(Region) -> bool) -> io::Result<()> {
+        let mut address = from;
+        let mut depth: libc::natural_t = 0;
+        loop {
+            let mut size = 0;
+            let mut info = SubmapInfo64::default();
+            let mut count = count_of::<SubmapInfo64>();
+            // Safety: valid out-pointers, and `info` is `count` naturals long.
+            let kr = unsafe {
+                mach_vm_region_recurse(
+                    task_self(),
+                    &mut address,
+                    &mut size,
+                    &mut depth,
+                    (&mut info as *mut SubmapInfo64).cast(),
+                    &mut count,
+                )
+            };
+            match kr {
+                libc::KERN_SUCCESS => {}
+                // No entry at or above `address`.
+                libc::KERN_INVALID_ADDRESS => return Ok(()),
+                _ => return Err(kern_error("mach_vm_region_recurse", kr)),
+            }
+            if info.is_submap != 0 {
+                depth += 1;
+                continue;
+            }
+            let region = Region {
+                start: address,
+                end: address.saturating_add(size),
+                user_wired_count: info.user_wired_count,
+            };
+            address = region.end;
+            if !f(region) || address == u64::MAX {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Total size of the entries user space wired, with `mlock` or `mlockall`.
+    pub(super) fn wired_bytes() -> io::Result<u64> {
+        let mut wired = 0u64;
+        for_each_region(0, |region| {
+            if region.user_wired_count > 0 {
+                wired += region.end - region.start;
+            }
+            true
+        })?;
+        Ok(wired)
+    }
+
+    pub(super) fn task_vm_info() -> io::Result<TaskVmInfo> {
+        let mut info = TaskVmInfo::default();
+        let mut count = count_of::<TaskVmInfo>();
+        // Safety: `info` is `count` naturals long.
+        let kr = unsafe {
+            libc::task_info(
+                task_self(),
+                TASK_VM_INFO,
+                (&mut info as *mut TaskVmInfo).cast(),
+                &mut count,
+            )
+        };
+        if kr != libc::KERN_SUCCESS {
+            return Err(kern_error("task_info", kr));
+        }
+        Ok(info)
+    }
+}
+
+/// The process's memory statistics from `zx_object_get_info` (Fuchsia).
+#[cfg(target_os = "fuchsia")]
+mod zircon {
+    use std::io;
+    use std::os::raw::c_void;
+
+    /// `zx_info_task_stats_t` as first defined; later revisions only append fields, and
+    /// are asked for under a different topic.
+    #[repr(C)]
+    #[derive(Default)]
+    #[allow(dead_code)] // Only some fields are read.
+    pub(super) struct TaskStats {
+        mem_mapped_bytes: usize,
+        pub(super) mem_private_bytes: usize,
+        pub(super) mem_shared_bytes: usize,
+        mem_scaled_shared_bytes: usize,
+    }
+
+    /// `ZX_INFO_TASK_STATS_V1`.
+    const ZX_INFO_TASK_STATS: u32 = 12;
+    const ZX_OK: i32 = 0;
+
+    unsafe extern "C" {
+        /// The process handle Fuchsia's libc holds for the process; never closed.
+        fn zx_process_self() -> u32;
+    }
+
+    #[link(name = "zircon")]
+    unsafe extern "C" {
+        fn zx_object_get_info(
+            handle: u32,
+            topic: u32,
+            buffer: *mut c_void,
+            buffer_size: usize,
+            actual: *mut usize,
+            avail: *mut usize,
+        ) -> i32;
+    }
+
+    pub(super) fn task_stats() -> io::Result<TaskStats> {
+        let mut stats = TaskStats::default();
+        let (mut actual, mut avail) = (0, 0);
+        // Safety: `stats` is a valid out-buffer of the size passed.
+        let status = unsafe {
+            zx_object_get_info(
+                zx_process_self(),
+                ZX_INFO_TASK_STATS,
+                (&mut stats as *mut TaskStats).cast(),
+                size_of::<TaskStats>(),
+                &mut actual,
+                &mut avail,
+            )
+        };
+        if status != ZX_OK {
+            return Err(io::Error::other(format!(
+                "zx_object_get_info failed with zx_status_t {status}"
+            )));
+        }
+        Ok(stats)
+    }
+}
+
+#[cfg(not(any(
+    windows,
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos"
+)))]
+fn smaps_range_locked(_start: usize, _end: usize) -> io::Result<bool> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "verify_region_locked unsupported on this platform",
+    ))
+}
+
+/// The smaps fields `verify_region_locked` looks at, for one mapping.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+#[derive(Default)]
+struct Mapping {
+    start: usize,
+    end: usize,
+    rss_kb: u64,
+    locked_kb: u64,
+    vm_locked: bool,
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_smaps(smaps: &str) -> Vec<Mapping> {
+    let mut mappings: Vec<Mapping> = Vec::new();
+    for line in smaps.lines() {
+        let Some((key, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        if let Some((start, end)) = key.split_once('-')
+            && let (Ok(start), Ok(end)) = (
+                usize::from_str_radix(start, 16),
+                usize::from_str_radix(end, 16),
+            )
+        {
+            mappings.push(Mapping {
+                start,
+                end,
+                ..Mapping::default()
+            });
+            continue;
+        }
+        let Some(mapping) = mappings.last_mut() else {
+            continue;
+        };
+        let kb = || {
+            rest.split_whitespace()
+                .next()
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0)
+        };
+        match key {
+            "Rss:" => mapping.rss_kb = kb(),
+            "Locked:" => mapping.locked_kb = kb(),
+            "VmFlags:" => mapping.vm_locked = rest.split_whitespace().any(|flag| flag == "lo"),
+            _ => {}
+        }
+    }
+    mappings
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn range_locked(mappings: &[Mapping], start: usize, end: usize) -> bool {
+    // smaps lists mappings in address order; walk them and reject any gap.
+    let mut next = start;
+    for mapping in mappings.iter().filter(|m| m.end > start && m.start < end) {
+        if mapping.start > next
+            || !mapping.vm_locked
+            || (mapping.rss_kb > 0 && mapping.locked_kb == 0)
+        {
+            return false;
+        }
+        next = mapping.end;
+    }
+    next >= end
+}
+
+/// Memory accounting for the current process, as reported by [`process_lock_stats`].
+///
+/// All sizes are in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ProcessLockStats {
+    locked: u64,
+    pinned: u64,
+    resident: u64,
+    swapped: u64,
+}
+
+impl ProcessLockStats {
+    /// Memory locked with `mlock`/`mlockall` or mapped `MAP_LOCKED` (`VmLck`).
+    pub fn locked_bytes(&self) -> u64 {
+        self.locked
+    }
+
+    /// Memory pinned by the kernel on the process's behalf, e.g. for RDMA (`VmPin`).
+    pub fn pinned_bytes(&self) -> u64 {
+        self.pinned
+    }
+
+    /// Resident set size (`VmRSS`).
+    pub fn resident_bytes(&self) -> u64 {
+        self.resident
+    }
+
+    /// Anonymous memory swapped out (`VmSwap`). Locked pages are never counted here.
+    pub fn swapped_bytes(&self) -> u64 {
+        self.swapped
+    }
+}
+
+/// Read the process's locked-memory accounting from /proc/self/status (on macOS, from
+/// the task's VM map and `task_info(TASK_VM_INFO)`).
+///
+/// Compare [`ProcessLockStats::locked_bytes`] with [`locked_bytes`] to see how much the
+/// crate's own buffers account for; a `VmLck` that keeps growing past it points at
+/// locked regions leaked elsewhere (or at `mlockall`).
+///
+/// On macOS, locked bytes are the total size of the VM map entries user space wired,
+/// resident bytes are `resident_size`, swapped bytes are `compressed` (memory held by
+/// the compressor, in RAM or in swap files) and pinned bytes are zero.
+///
+/// On Fuchsia, resident bytes are the committed private and shared bytes from
+/// `zx_object_get_info(ZX_INFO_TASK_STATS)`. Zircon can neither lock user memory nor
+/// swap it out, so the other fields are zero.
+///
+/// Platform:
+/// - Linux, Android, macOS and Fuchsia. Elsewhere, returns `Unsupported`.
+///
+/// Returns:
+/// - Ok(stats); fields missing from the file (e.g. `VmPin` on old kernels) are zero
+/// - Err(...) if /proc/self/status could not be read, with the `kern_return_t` if a
+///   Mach call failed, or with the `zx_status_t` if `zx_object_get_info` failed
+pub fn process_lock_stats() -> io::Result<ProcessLockStats> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let status = std::fs::read_to_string("/proc/self/status")?;
+        Ok(parse_status(&status))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let info = mach::task_vm_info()?;
+        Ok(ProcessLockStats {
+            locked: mach::wired_bytes()?,
+            pinned: 0,
+            resident: info.resident_size,
+            swapped: info.compressed,
+        })
+    }
+    #[cfg(target_os = "fuchsia")]
+    {
+        let stats = zircon::task_stats()?;
+        Ok(ProcessLockStats {
+            locked: 0,
+            pinned: 0,
+            resident: (stats.mem_private_bytes + stats.mem_shared_bytes) as u64,
+            swapped: 0,
+        })
+    }
+    #[cfg(not(any(
+        target_os = "linux",
+        target_os = "android",
+        target_os = "macos",
+        target_os = "fuchsia"
+    )))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "process_lock_stats unsupported on this platform",
+    ))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn parse_status(status: &str) -> ProcessLockStats {
+    let mut stats = ProcessLockStats::default();
+    for line in status.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let field = match key {
+            "VmLck" => &mut stats.locked,
+            "VmPin" => &mut stats.pinned,
+            "VmRSS" => &mut stats.resident,
+            "VmSwap" => &mut stats.swapped,
+            _ => continue,
+        };
+        // Values are in kB ("VmLck:      16 kB").
+        let kb: u64 = value
+            .split_whitespace()
+            .next()
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0);
+        *field = kb.saturating_mul(1024);
+    }
+    stats
+}
+
+/// What [`verify_effective_lock`] observed after locking a canary page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LockEffectiveness {
+    resident: bool,
+    accounted: Option<bool>,
+    flagged: Option<bool>,
+}
+
+impl LockEffectiveness {
+    /// Whether the checks on the canary itself confirmed the lock: it is resident, and
+    /// flagged locked where that can be read. [`accounted`](Self::accounted) is left
+    /// out, since other threads move the process-wide count.
+    pub fn is_effective(&self) -> bool {
+        self.resident && self.flagged != Some(false)
+    }
+
+    /// Whether `mlock` faulted the untouched canary page into RAM, as a real lock must.
+    pub fn resident(&self) -> bool {
+        self.resident
+    }
+
+    /// Whether `VmLck` grew by at least the canary page; `None` where it cannot be read.
+    ///
+    /// Informational only: `VmLck` counts the whole process, so another thread
+    /// unlocking or dropping a buffer meanwhile can hide the canary's page.
+    pub fn accounted(&self) -> Option<bool> {
+        self.accounted
+    }
+
+    /// Whether smaps shows the canary mapping as locked; `None` where it cannot be read.
+    pub fn flagged(&self) -> Option<bool> {
+        self.flagged
+    }
+}
+
+impl fmt::Display for LockEffectiveness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_effective() {
+            return f.write_str("mlock pins memory on this host");
+        }
+        f.write_str("mlock was accepted but did not pin the canary page (")?;
+        let failed: Vec<_> = [
+            (Some(self.resident), "not resident"),
+            (self.accounted, "not counted in VmLck"),
+            (self.flagged, "not flagged locked in smaps"),
+        ]
+        .into_iter()
+        .filter(|&(ok, _)| ok == Some(false))
+        .map(|(_, what)| what)
+        .collect();
+        write!(f, "{})", failed.join(", "))
+    }
+}
+
+/// Check whether `mlock` really pins memory on this host, by locking a canary page.
+///
+/// Some sandboxed kernels (gVisor, certain hypervisor-isolated runtimes) accept `mlock`
+/// without pinning anything, so a successful call proves little. This maps one fresh
+/// anonymous page without touching it, locks it, and checks that:
+/// - the page became resident ([`is_resident`]), since locking faults pages in; and
+/// - smaps (on macOS, the VM map) reports the canary's own range locked
+///   ([`verify_region_locked`], Linux/Android/macOS only).
+///
+/// It also reports whether `VmLck` (on macOS, the wired total) grew by at least a page
+/// ([`process_lock_stats`]), but only for information: other threads locking or
+/// unlocking at the same time move that process-wide count either way, so it is not
+/// part of the verdict.
+///
+/// The page is unlocked and unmapped before returning.
+///
+/// Platform:
+/// - Unix targets with `mincore` (see [`is_resident`]). Elsewhere, returns
+///   `Unsupported`.
+///
+/// Returns:
+/// - Ok(report); [`LockEffectiveness::is_effective`] gives the verdict
+/// - Err(...) if mapping or locking the canary failed (e.g. an exhausted
+///   `RLIMIT_MEMLOCK`), which already answers the question differently
+/// - Err(Unsupported) if not supported on this platform
+pub fn verify_effective_lock() -> io::Result<LockEffectiveness> {
+    imp::verify_effective_lock()
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+    use std::os::raw::c_void;
+    use std::ptr;
+
+    use super::LockEffectiveness;
+
+    pub(super) fn verify_effective_lock() -> io::Result<LockEffectiveness> {
+        let page = crate::page_size();
+        // Safety: a fresh private anonymous mapping; nothing else refers to it.
+        let addr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                page,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANON,
+                -1,
+                0,
+            )
+        };
+        if addr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let canary = Canary { addr, len: page };
+        let before = super::process_lock_stats().ok();
+        // Safety: the canary mapping stays valid until `canary` drops.
+        unsafe { crate::mlock(canary.addr, canary.len)? };
+        let after = super::process_lock_stats().ok();
+        // Safety: as above.
+        let resident = unsafe { super::is_resident(canary.addr, canary.len)? };
+        let accounted = before
+            .zip(after)
+            .map(|(b, a)| a.locked_bytes() >= b.locked_bytes() + page as u64);
+        let flagged = super::verify_region_locked(canary.addr, canary.len).ok();
+        Ok(LockEffectiveness {
+            resident: resident.is_fully_resident(),
+            accounted,
+            flagged,
+        })
+    }
+
+    /// Unmaps (and so unlocks) the canary page however the check ends.
+    struct Canary {
+        addr: *mut c_void,
+        len: usize,
+    }
+
+    impl Drop for Canary {
+        fn drop(&mut self) {
+            // Safety: the mapping was created by `verify_effective_lock` and is unused.
+            unsafe { libc::munmap(self.addr, self.len) };
+        }
+    }
+}
+
+#[cfg(not(unix))]
+mod imp {
+    use std::io;
+
+    use super::LockEffectiveness;
+
+    pub(super) fn verify_effective_lock() -> io::Result<LockEffectiveness> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "verify_effective_lock unsupported on this platform",
+        ))
+    }
+}
+
+/// Bytes currently locked by this crate's live buffers and containers.
+///
+/// Every locked container is page-rounded, so this can be compared directly with
+/// [`ProcessLockStats::locked_bytes`]. Buffers whose `mlock` was Unsupported are not
+/// counted.
+pub fn locked_bytes() -> usize {
+    crate::secure_buf::locked_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_resident;
+
+    #[cfg(unix)]
+    #[test]
+    fn locked_buffer_is_fully_resident() {
+        let buf = crate::SecureBuf::from_slice(&[1; 100]).unwrap();
+        let report = unsafe { is_resident(buf.as_slice().as_ptr().cast(), buf.len()) }.unwrap();
+        assert_eq!(report.total_pages(), 1);
+        assert!(report.is_fully_resident());
+        assert_eq!(report.non_resident().count(), 0);
+
+        let empty = unsafe { is_resident(buf.as_slice().as_ptr().cast(), 0) }.unwrap();
+        assert_eq!(empty.total_pages(), 0);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn smaps_ranges_need_lo_flag_and_full_coverage() {
+        use super::{parse_smaps, range_locked, verify_region_locked};
+
+        let smaps = "\
+1000-3000 rw-p 00000000 00:00 0
+Rss:                   8 kB
+Locked:                8 kB
+VmFlags: rd wr mr mw me lo ac
+3000-4000 rw-p 00000000 00:00 0
+Rss:                   4 kB
+Locked:                0 kB
+VmFlags: rd wr mr mw me ac
+5000-6000 rw-p 00000000 00:00 0 [heap]
+Rss:                   0 kB
+Locked:                0 kB
+VmFlags: rd wr mr mw me lo ac
+";
+        let mappings = parse_smaps(smaps);
+        assert_eq!(mappings.len(), 3);
+        assert!(range_locked(&mappings, 0x1000, 0x3000));
+        assert!(!range_locked(&mappings, 0x2000, 0x4000));
+        assert!(!range_locked(&mappings, 0x4000, 0x6000));
+        assert!(range_locked(&mappings, 0x5000, 0x6000));
+
+        let buf = crate::SecureBuf::from_slice(&[1; 100]).unwrap();
+        assert!(verify_region_locked(buf.as_slice().as_ptr().cast(), buf.len()).unwrap());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn locking_is_effective_on_a_real_kernel() {
+        // Alone in its process, no other test's buffers move VmLck meanwhile.
+        if !crate::test_util::in_own_process("verify::tests::locking_is_effective_on_a_real_kernel")
+        {
+            return;
+        }
+        let report = super::verify_effective_lock().unwrap();
+        assert!(report.is_effective(), "{report}");
+        assert_eq!(report.flagged(), Some(true));
+        assert_eq!(report.accounted(), Some(true));
+
+        let noop = super::LockEffectiveness {
+            resident: false,
+            accounted: Some(false),
+            flagged: None,
+        };
+        assert!(!noop.is_effective());
+        assert_eq!(
+            noop.to_string(),
+            "mlock was accepted but did not pin the canary page (not resident, not counted \
+             in VmLck)"
+        );
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn wired_regions_are_found_in_the_vm_map() {
+        use super::{process_lock_stats, verify_region_locked};
+
+        let buf = crate::SecureBuf::from_slice(&[1; 100]).unwrap();
+        assert!(verify_region_locked(buf.as_slice().as_ptr().cast(), buf.len()).unwrap());
+        let stats = process_lock_stats().unwrap();
+        assert!(stats.locked_bytes() >= crate::page_size() as u64);
+        assert!(stats.resident_bytes() >= stats.locked_bytes());
+
+        let heap = vec![0u8; 1 << 20];
+        assert!(!verify_region_locked(heap.as_ptr().cast(), heap.len()).unwrap());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn status_fields_parse_to_bytes() {
+        use super::{locked_bytes, parse_status, process_lock_stats};
+
+        let stats =
+            parse_status("Name:\tx\nVmLck:\t      16 kB\nVmRSS:\t    1632 kB\nVmSwap:\t 0 kB\n");
+        assert_eq!(stats.locked_bytes(), 16 * 1024);
+        assert_eq!(stats.resident_bytes(), 1632 * 1024);
+        assert_eq!(stats.pinned_bytes(), 0);
+
+        let buf = crate::SecureBuf::new(1).unwrap();
+        assert!(locked_bytes() >= crate::page_size());
+        assert!(process_lock_stats().unwrap().locked_bytes() >= crate::page_size() as u64);
+        drop(buf);
+    }
+}
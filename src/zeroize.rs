@@ -0,0 +1,35 @@
+//! Zeroization used by the secure containers.
+
+use std::sync::atomic::{Ordering, compiler_fence};
+
+/// Windows x86/x64: the `rep stosb` over the range that `RtlSecureZeroMemory` expands
+/// to (it is an inline function in `winnt.h`, not an export), opaque to the optimizer.
+#[cfg(all(windows, any(target_arch = "x86", target_arch = "x86_64")))]
+pub(crate) fn zeroize(bytes: &mut [u8]) {
+    // Safety: edi/ecx describe exactly `bytes`, which `rep stosb` fills with al; the
+    // direction flag is clear per the ABI, and no flags are modified.
+    unsafe {
+        core::arch::asm!(
+            "rep stosb",
+            inout("edi") bytes.as_mut_ptr() => _,
+            inout("ecx") bytes.len() => _,
+            in("al") 0u8,
+            options(nostack, preserves_flags),
+        );
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrite `bytes` with zeros in a way the optimizer will not elide.
+///
+/// Plain `fill(0)` on memory that is about to be freed is a dead store and may be
+/// removed entirely; volatile writes plus a compiler fence keep the wipe in place, as
+/// `RtlSecureZeroMemory` does on ARM.
+#[cfg(not(all(windows, any(target_arch = "x86", target_arch = "x86_64"))))]
+pub(crate) fn zeroize(bytes: &mut [u8]) {
+    for b in bytes.iter_mut() {
+        // Safety: `b` is a valid, exclusive reference into the slice.
+        unsafe { std::ptr::write_volatile(b, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}